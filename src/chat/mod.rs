@@ -1,6 +1,8 @@
 pub mod context;
+pub mod prompt;
 
 pub use context::{
     ContextError, ContextProvider, ContextResult, CursorContext, FileContext, SelectionContext,
     ZedContextProvider,
 };
+pub use prompt::{ContextItem, PromptContext};
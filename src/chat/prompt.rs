@@ -0,0 +1,255 @@
+use crate::chat::context::{ContextError, ContextProvider};
+use crate::config::{BlockReason, BlockedItem, PrivacyPolicy};
+use std::fmt::Write;
+use std::path::PathBuf;
+
+/// Editor context cleared for sending to a provider.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ContextItem {
+    File {
+        path: PathBuf,
+        language: String,
+        content: String,
+    },
+    Selection {
+        path: PathBuf,
+        language: String,
+        text: String,
+        start_line: u32,
+        end_line: u32,
+    },
+}
+
+/// The editor context of a prompt, and what the privacy policy kept back.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PromptContext {
+    pub items: Vec<ContextItem>,
+    pub blocked: Vec<BlockedItem>,
+}
+
+impl PromptContext {
+    /// Collects what `provider` offers, subject to `policy`. Context must
+    /// only reach a prompt through here. A selection is withheld along with
+    /// its file when the file's path is blocked, but not when only the
+    /// whole file is too large or binary.
+    pub fn collect(
+        provider: &dyn ContextProvider,
+        policy: &PrivacyPolicy,
+    ) -> Result<Self, ContextError> {
+        let mut context = PromptContext::default();
+        let Some(file) = provider.get_file_context()? else {
+            return Ok(context);
+        };
+
+        if let Err(reason) = policy.check_path(&file.path) {
+            context.block(file.path, reason);
+            return Ok(context);
+        }
+        match policy.read_file(&file.path) {
+            Ok(content) => context.items.push(ContextItem::File {
+                path: file.path.clone(),
+                language: file.language.clone(),
+                content,
+            }),
+            Err(reason) => context.block(file.path.clone(), reason),
+        }
+
+        if let Some(selection) = provider.get_selection_context()? {
+            match policy.check_content(selection.text.as_bytes()) {
+                Ok(()) => context.items.push(ContextItem::Selection {
+                    path: file.path,
+                    language: file.language,
+                    text: selection.text,
+                    start_line: selection.start_line,
+                    end_line: selection.end_line,
+                }),
+                Err(reason) => context.block(file.path, reason),
+            }
+        }
+        Ok(context)
+    }
+
+    fn block(&mut self, path: PathBuf, reason: BlockReason) {
+        log::info!("Not sending {} to the provider: {}", path.display(), reason);
+        self.blocked.push(BlockedItem { path, reason });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// The context as prompt text, one fenced block per item.
+    pub fn to_prompt(&self) -> String {
+        let mut prompt = String::new();
+        for item in &self.items {
+            let (heading, language, body) = match item {
+                ContextItem::File {
+                    path,
+                    language,
+                    content,
+                } => (format!("File {}", path.display()), language, content),
+                ContextItem::Selection {
+                    path,
+                    language,
+                    text,
+                    start_line,
+                    end_line,
+                } => (
+                    format!(
+                        "Selection in {}, lines {}-{}",
+                        path.display(),
+                        start_line,
+                        end_line
+                    ),
+                    language,
+                    text,
+                ),
+            };
+            let _ = writeln!(prompt, "{}:\n```{}\n{}\n```\n", heading, language, body);
+        }
+        prompt
+    }
+
+    /// What was kept back and why, for showing to the user.
+    pub fn blocked_notice(&self) -> Option<String> {
+        if self.blocked.is_empty() {
+            return None;
+        }
+        let mut notice = String::from("Not sent to the provider:");
+        for item in &self.blocked {
+            let _ = write!(notice, "\n- {}", item);
+        }
+        Some(notice)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chat::context::{ContextResult, CursorContext, FileContext, SelectionContext};
+    use crate::config::privacy::NEVER_SEND_MARKER;
+    use crate::config::PrivacyConfig;
+    use std::path::Path;
+
+    struct FakeEditor {
+        file: Option<PathBuf>,
+        selection: Option<&'static str>,
+    }
+
+    impl ContextProvider for FakeEditor {
+        fn get_file_context(&self) -> ContextResult<FileContext> {
+            Ok(self.file.clone().map(|path| FileContext {
+                path,
+                size_bytes: 0,
+                language: "rust".to_string(),
+                modified: false,
+            }))
+        }
+
+        fn get_selection_context(&self) -> ContextResult<SelectionContext> {
+            Ok(self.selection.map(|text| SelectionContext {
+                text: text.to_string(),
+                start_line: 1,
+                start_column: 0,
+                end_line: 1,
+                end_column: text.len() as u32,
+            }))
+        }
+
+        fn get_cursor_context(&self) -> ContextResult<CursorContext> {
+            Ok(None)
+        }
+    }
+
+    fn workspace(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!(
+            "zed-copilot-prompt-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::create_dir_all(root.join("clients")).unwrap();
+        std::fs::write(root.join("src/main.rs"), "fn main() {}").unwrap();
+        std::fs::write(root.join(".env"), "DATABASE_URL=postgres://u:p@db").unwrap();
+        std::fs::write(root.join("src/big.rs"), "x".repeat(2048)).unwrap();
+        std::fs::write(root.join("clients").join(NEVER_SEND_MARKER), "").unwrap();
+        std::fs::write(root.join("clients/acme.rs"), "const KEY: &str = \"\";").unwrap();
+        root
+    }
+
+    fn collect(root: &Path, file: &str, selection: Option<&'static str>) -> PromptContext {
+        let config = PrivacyConfig {
+            max_file_size_kb: 1,
+            ..Default::default()
+        };
+        let policy = PrivacyPolicy::new(&config, Some(root)).unwrap();
+        let editor = FakeEditor {
+            file: Some(root.join(file)),
+            selection,
+        };
+        PromptContext::collect(&editor, &policy).unwrap()
+    }
+
+    #[test]
+    fn test_collects_allowed_context() {
+        let root = workspace("allowed");
+        let context = collect(&root, "src/main.rs", Some("main"));
+        assert!(context.blocked.is_empty());
+        assert_eq!(context.items.len(), 2);
+        let prompt = context.to_prompt();
+        assert!(prompt.contains("```rust\nfn main() {}\n```"));
+        assert!(prompt.contains("lines 1-1"));
+        assert_eq!(context.blocked_notice(), None);
+
+        let nothing_open = FakeEditor {
+            file: None,
+            selection: None,
+        };
+        let policy = PrivacyPolicy::new(&PrivacyConfig::default(), Some(&root)).unwrap();
+        assert!(PromptContext::collect(&nothing_open, &policy)
+            .unwrap()
+            .is_empty());
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_blocked_path_withholds_selection() {
+        let root = workspace("blocked");
+        let context = collect(&root, ".env", Some("DATABASE_URL"));
+        assert!(context.is_empty());
+        assert_eq!(
+            context.blocked,
+            vec![BlockedItem {
+                path: root.join(".env"),
+                reason: BlockReason::Denied(".env".to_string()),
+            }]
+        );
+        assert!(!context.to_prompt().contains("postgres"));
+
+        let context = collect(&root, "clients/acme.rs", Some("KEY"));
+        assert!(context.is_empty());
+        assert!(context
+            .blocked_notice()
+            .unwrap()
+            .contains("never-send marker"));
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_large_file_still_sends_selection() {
+        let root = workspace("large");
+        let context = collect(&root, "src/big.rs", Some("xxxx"));
+        assert_eq!(context.items.len(), 1);
+        assert!(matches!(context.items[0], ContextItem::Selection { .. }));
+        assert!(matches!(
+            context.blocked[0].reason,
+            BlockReason::TooLarge { .. }
+        ));
+        assert!(context
+            .blocked_notice()
+            .unwrap()
+            .starts_with("Not sent to the provider:\n- "));
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,342 @@
+use crate::config::errors::{ConfigError, ConfigResult};
+use crate::config::registry::ProviderRegistry;
+use crate::config::structs::RootConfig;
+use serde_json::Value;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// The configuration cannot be used as is.
+    Error,
+    /// The configuration works, but probably not as intended.
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// One problem found in the settings.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// JSON pointer to the offending setting, e.g. `/openai/model`. Empty
+    /// for the document root.
+    pub path: String,
+    pub severity: Severity,
+    pub message: String,
+    /// How to fix it, when there is an obvious fix.
+    pub suggestion: Option<String>,
+}
+
+impl Diagnostic {
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+
+    pub(crate) fn suggest(&mut self, suggestion: impl Into<String>) -> &mut Self {
+        self.suggestion = Some(suggestion.into());
+        self
+    }
+
+    /// Suggests the likeliest of `candidates` when `name` looks like a
+    /// typo of one, or `fallback` otherwise.
+    pub(crate) fn did_you_mean<'a>(
+        &mut self,
+        name: &str,
+        candidates: impl IntoIterator<Item = &'a str>,
+        fallback: Option<String>,
+    ) -> &mut Self {
+        match (closest(name, candidates), fallback) {
+            (Some(candidate), _) => self.suggest(format!("did you mean '{}'?", candidate)),
+            (None, Some(fallback)) => self.suggest(fallback),
+            (None, None) => self,
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: ", self.severity)?;
+        if !self.path.is_empty() {
+            write!(f, "{}: ", self.path)?;
+        }
+        write!(f, "{}", self.message)?;
+        if let Some(suggestion) = &self.suggestion {
+            write!(f, " ({})", suggestion)?;
+        }
+        Ok(())
+    }
+}
+
+/// Collects diagnostics, keeping the first error as a [`ConfigError`] so
+/// fail-fast callers see the same error they always did.
+#[derive(Debug, Default)]
+pub(crate) struct Diagnostics {
+    items: Vec<Diagnostic>,
+    first_error: Option<ConfigError>,
+}
+
+impl Diagnostics {
+    pub(crate) fn error(&mut self, path: &str, error: ConfigError) -> &mut Diagnostic {
+        let message = message_of(&error);
+        self.first_error.get_or_insert(error);
+        self.push(path, Severity::Error, message)
+    }
+
+    pub(crate) fn warning(&mut self, path: &str, message: impl Into<String>) -> &mut Diagnostic {
+        self.push(path, Severity::Warning, message.into())
+    }
+
+    fn push(&mut self, path: &str, severity: Severity, message: String) -> &mut Diagnostic {
+        self.items.push(Diagnostic {
+            path: path.to_string(),
+            severity,
+            message,
+            suggestion: None,
+        });
+        self.items.last_mut().expect("just pushed")
+    }
+
+    pub(crate) fn has_error_at(&self, path: &str) -> bool {
+        self.items
+            .iter()
+            .any(|diagnostic| diagnostic.is_error() && diagnostic.path == path)
+    }
+
+    pub(crate) fn has_errors(&self) -> bool {
+        self.first_error.is_some()
+    }
+
+    pub(crate) fn into_result(self) -> ConfigResult<()> {
+        match self.first_error {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+
+    pub(crate) fn into_vec(self) -> Vec<Diagnostic> {
+        self.items
+    }
+}
+
+fn message_of(error: &ConfigError) -> String {
+    match error {
+        ConfigError::ValidationError(msg)
+        | ConfigError::MissingField(msg)
+        | ConfigError::ParseError(msg) => msg.clone(),
+        ConfigError::EnvVarNotFound(name) => format!("environment variable {} is not set", name),
+        ConfigError::InvalidProvider(name) => format!("unknown provider '{}'", name),
+        ConfigError::JsonError(error) => format!(
+            "{} (line {}, column {})",
+            error.message, error.line, error.column
+        ),
+    }
+}
+
+/// `parent` extended with `key`, escaped as a JSON pointer token.
+pub(crate) fn pointer(parent: &str, key: &str) -> String {
+    format!("{}/{}", parent, key.replace('~', "~0").replace('/', "~1"))
+}
+
+/// The candidate closest to `name`, ignoring case, if it is within a
+/// third of `name`'s length in edits.
+fn closest<'a>(name: &str, candidates: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    let limit = (name.chars().count() / 3).max(1);
+    candidates
+        .into_iter()
+        .filter(|candidate| *candidate != name)
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= limit)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// Edits between `a` and `b`, where swapping adjacent characters counts
+/// as one edit, as it is a common typo.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.to_lowercase().chars().collect();
+    let b: Vec<char> = b.to_lowercase().chars().collect();
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in d[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            d[i][j] = (d[i - 1][j] + 1)
+                .min(d[i][j - 1] + 1)
+                .min(d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+    d[a.len()][b.len()]
+}
+
+/// Warns about settings in `raw` that `config`, deserialized from it,
+/// dropped. Serde ignores unknown fields, so these are found by comparing
+/// `raw` with `config` serialized back. Unknown top-level keys are kept as
+/// provider sections instead, and reported unless a provider of that name
+/// is registered.
+pub(crate) fn unknown_fields(raw: &Value, config: &RootConfig, diagnostics: &mut Diagnostics) {
+    let (Value::Object(fields), Ok(Value::Object(known))) = (raw, serde_json::to_value(config))
+    else {
+        return;
+    };
+
+    let registry = ProviderRegistry::global();
+    let kinds = registry.names();
+    let typed: Vec<&str> = known
+        .keys()
+        .map(String::as_str)
+        .filter(|key| !config.provider_sections.contains_key(*key))
+        .collect();
+
+    for (key, value) in fields {
+        let path = pointer("", key);
+        if config.provider_sections.contains_key(key) {
+            if !registry.contains(key) {
+                diagnostics
+                    .warning(
+                        &path,
+                        format!("'{}' is not a setting or a registered provider", key),
+                    )
+                    .did_you_mean(
+                        key,
+                        typed
+                            .iter()
+                            .copied()
+                            .chain(kinds.iter().map(String::as_str)),
+                        None,
+                    );
+            }
+        } else if let Some(known) = known.get(key) {
+            compare(&path, value, known, diagnostics);
+        }
+    }
+}
+
+fn compare(path: &str, raw: &Value, known: &Value, diagnostics: &mut Diagnostics) {
+    match (raw, known) {
+        (Value::Object(fields), Value::Object(known)) => {
+            for (key, value) in fields {
+                let path = pointer(path, key);
+                match known.get(key) {
+                    Some(known) => compare(&path, value, known, diagnostics),
+                    None => {
+                        diagnostics
+                            .warning(&path, format!("unknown setting '{}' is ignored", key))
+                            .did_you_mean(key, known.keys().map(String::as_str), None);
+                    }
+                }
+            }
+        }
+        (Value::Array(items), Value::Array(known)) => {
+            for (index, (item, known)) in items.iter().zip(known).enumerate() {
+                compare(&pointer(path, &index.to_string()), item, known, diagnostics);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn warnings_for(raw: Value) -> Vec<Diagnostic> {
+        let config: RootConfig = serde_json::from_value(raw.clone()).unwrap();
+        let mut diagnostics = Diagnostics::default();
+        unknown_fields(&raw, &config, &mut diagnostics);
+        diagnostics.into_vec()
+    }
+
+    #[test]
+    fn test_unknown_nested_fields() {
+        let diagnostics = warnings_for(json!({
+            "openai": {"api_key": "sk", "modle": "gpt-4"},
+            "chat": {"streaming": true},
+            "routing": {"rules": [{"profile": "fast", "langauges": ["rust"]}]}
+        }));
+        let paths: Vec<&str> = diagnostics.iter().map(|d| d.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            vec![
+                "/chat/streaming",
+                "/openai/modle",
+                "/routing/rules/0/langauges"
+            ]
+        );
+        assert!(diagnostics.iter().all(|d| d.severity == Severity::Warning));
+        assert_eq!(
+            diagnostics[1].suggestion.as_deref(),
+            Some("did you mean 'model'?")
+        );
+        assert_eq!(
+            diagnostics[2].suggestion.as_deref(),
+            Some("did you mean 'languages'?")
+        );
+    }
+
+    #[test]
+    fn test_unknown_top_level_keys() {
+        let diagnostics = warnings_for(json!({
+            "provider": "openai",
+            "opneai": {"api_key": "sk"},
+            "proflies": {},
+            "anthropic": {"api_key": "sk"}
+        }));
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].path, "/opneai");
+        assert_eq!(
+            diagnostics[0].suggestion.as_deref(),
+            Some("did you mean 'openai'?")
+        );
+        assert_eq!(
+            diagnostics[1].suggestion.as_deref(),
+            Some("did you mean 'profiles'?")
+        );
+    }
+
+    #[test]
+    fn test_route_target_shorthand_is_not_unknown() {
+        let diagnostics = warnings_for(json!({
+            "profiles": {"fast": {"provider": "openai", "temperature": 0.2}},
+            "routing": {"tasks": {"chat": "fast", "review": {"model": "o3"}}}
+        }));
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+    }
+
+    #[test]
+    fn test_closest_candidate() {
+        assert_eq!(closest("opnai", ["openai", "anthropic"]), Some("openai"));
+        assert_eq!(closest("OpenAI", ["openai", "anthropic"]), Some("openai"));
+        assert_eq!(closest("modle", ["model", "api_key"]), Some("model"));
+        assert_eq!(closest("mistral", ["openai", "anthropic"]), None);
+    }
+
+    #[test]
+    fn test_display() {
+        let mut diagnostics = Diagnostics::default();
+        diagnostics
+            .error(
+                "/provider",
+                ConfigError::InvalidProvider("opnai".to_string()),
+            )
+            .did_you_mean("opnai", ["openai"], None);
+        assert_eq!(
+            diagnostics.into_vec()[0].to_string(),
+            "error: /provider: unknown provider 'opnai' (did you mean 'openai'?)"
+        );
+    }
+}
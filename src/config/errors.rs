@@ -1,3 +1,5 @@
+use crate::config::jsonc::JsonError;
+use crate::config::registry::ProviderRegistry;
 use std::fmt;
 
 #[derive(Debug, Clone)]
@@ -7,6 +9,8 @@ pub enum ConfigError {
     EnvVarNotFound(String),
     InvalidProvider(String),
     ParseError(String),
+    /// Malformed or mistyped settings, with the position in the source.
+    JsonError(Box<JsonError>),
 }
 
 impl fmt::Display for ConfigError {
@@ -26,11 +30,13 @@ impl fmt::Display for ConfigError {
             ConfigError::InvalidProvider(msg) => {
                 write!(
                     f,
-                    "Invalid or unsupported provider: {}. Supported providers: openai, anthropic",
-                    msg
+                    "Invalid or unsupported provider: {}. Supported providers: {}",
+                    msg,
+                    ProviderRegistry::global().names().join(", ")
                 )
             }
             ConfigError::ParseError(msg) => write!(f, "Failed to parse configuration: {}", msg),
+            ConfigError::JsonError(error) => write!(f, "Failed to parse configuration: {}", error),
         }
     }
 }
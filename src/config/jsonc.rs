@@ -0,0 +1,471 @@
+use crate::config::errors::{ConfigError, ConfigResult};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// A JSON settings error pinned to a position in its source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonError {
+    pub file: Option<PathBuf>,
+    /// 1-based line of the offending token.
+    pub line: usize,
+    /// 1-based column, counted in characters.
+    pub column: usize,
+    /// JSON pointer to the offending value, e.g. `/openai/timeout_secs`.
+    /// Empty for syntax errors and for the document root.
+    pub pointer: String,
+    pub message: String,
+    /// The source line containing the error.
+    pub excerpt: String,
+}
+
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.file {
+            Some(file) => write!(f, "{}:{}:{}: ", file.display(), self.line, self.column)?,
+            None => write!(f, "line {}, column {}: ", self.line, self.column)?,
+        }
+        if !self.pointer.is_empty() {
+            write!(f, "{}: ", self.pointer)?;
+        }
+        write!(f, "{}", self.message)?;
+
+        let gutter = self.line.to_string();
+        let caret: String = self
+            .excerpt
+            .chars()
+            .take(self.column.saturating_sub(1))
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        write!(
+            f,
+            "\n{} | {}\n{} | {}^",
+            gutter,
+            self.excerpt,
+            " ".repeat(gutter.len()),
+            caret
+        )
+    }
+}
+
+/// A JSONC document: the original text, used for excerpts, and the same
+/// text with comments and trailing commas blanked out. Blanking keeps every
+/// byte offset, so positions in one are positions in the other.
+#[derive(Debug, Clone)]
+pub struct JsoncSource<'a> {
+    file: Option<&'a Path>,
+    original: &'a str,
+    stripped: String,
+}
+
+impl<'a> JsoncSource<'a> {
+    pub fn new(original: &'a str, file: Option<&'a Path>) -> ConfigResult<Self> {
+        let mut source = JsoncSource {
+            file,
+            original,
+            stripped: String::new(),
+        };
+        source.stripped = source.strip()?;
+        Ok(source)
+    }
+
+    pub fn parse(&self) -> ConfigResult<Value> {
+        serde_json::from_str(&self.stripped).map_err(|e| self.syntax_error(&e))
+    }
+
+    /// Deserializes the document, reporting type errors with the pointer
+    /// and position of the offending value.
+    pub fn deserialize<T: DeserializeOwned>(&self) -> ConfigResult<T> {
+        let value = self.parse()?;
+        deserialize_value(value, &[(self, "")])
+    }
+
+    /// Byte offset of the value at `pointer`, if present.
+    pub fn locate(&self, pointer: &str) -> Option<usize> {
+        let tokens: Vec<String> = pointer
+            .split('/')
+            .skip(1)
+            .map(|token| token.replace("~1", "/").replace("~0", "~"))
+            .collect();
+        Scanner {
+            text: &self.stripped,
+            pos: 0,
+        }
+        .find(&tokens)
+    }
+
+    pub(crate) fn error_at(
+        &self,
+        offset: usize,
+        pointer: &str,
+        message: impl Into<String>,
+    ) -> ConfigError {
+        let offset = offset.min(self.original.len());
+        let line_start = self.original[..offset].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = self.original[offset..]
+            .find('\n')
+            .map_or(self.original.len(), |i| offset + i);
+
+        ConfigError::JsonError(Box::new(JsonError {
+            file: self.file.map(Path::to_path_buf),
+            line: self.original[..offset].matches('\n').count() + 1,
+            column: self.original[line_start..offset].chars().count() + 1,
+            pointer: pointer.to_string(),
+            message: message.into(),
+            excerpt: self.original[line_start..line_end]
+                .trim_end_matches('\r')
+                .to_string(),
+        }))
+    }
+
+    fn syntax_error(&self, error: &serde_json::Error) -> ConfigError {
+        let line_start: usize = self
+            .original
+            .split_inclusive('\n')
+            .take(error.line().saturating_sub(1))
+            .map(str::len)
+            .sum();
+        // serde_json reports the position just past the offending byte.
+        let offset = (line_start + error.column()).saturating_sub(1);
+        let message = error.to_string();
+        let message = match message.rfind(" at line ") {
+            Some(end) => message[..end].to_string(),
+            None => message,
+        };
+        self.error_at(offset, "", message)
+    }
+
+    fn strip(&self) -> ConfigResult<String> {
+        let mut bytes = self.original.as_bytes().to_vec();
+        let mut i = 0;
+        while i < bytes.len() {
+            match bytes[i] {
+                b'"' => i = skip_string(&bytes, i),
+                b'/' if bytes.get(i + 1) == Some(&b'/') => {
+                    while i < bytes.len() && bytes[i] != b'\n' {
+                        bytes[i] = b' ';
+                        i += 1;
+                    }
+                }
+                b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                    let start = i;
+                    let end = self.original[i + 2..]
+                        .find("*/")
+                        .map(|end| i + 2 + end + 2)
+                        .ok_or_else(|| self.error_at(start, "", "unterminated block comment"))?;
+                    for byte in &mut bytes[start..end] {
+                        if *byte != b'\n' {
+                            *byte = b' ';
+                        }
+                    }
+                    i = end;
+                }
+                _ => i += 1,
+            }
+        }
+
+        // Trailing commas are found after comments are gone so that
+        // `[1, // last\n]` is handled too.
+        let mut i = 0;
+        while i < bytes.len() {
+            match bytes[i] {
+                b'"' => i = skip_string(&bytes, i),
+                b',' => {
+                    let next = bytes[i + 1..]
+                        .iter()
+                        .find(|byte| !byte.is_ascii_whitespace());
+                    if matches!(next, Some(b'}') | Some(b']')) {
+                        bytes[i] = b' ';
+                    }
+                    i += 1;
+                }
+                _ => i += 1,
+            }
+        }
+
+        // Comments are blanked whole, every byte of a multi-byte character
+        // included, so the text is still valid UTF-8.
+        Ok(String::from_utf8(bytes).expect("blanking whole characters keeps UTF-8 valid"))
+    }
+}
+
+/// Deserializes `value`, which was built from one or more sources. On a type
+/// error the pointer is looked up in each source in turn, each under its
+/// prefix (e.g. `/zed_copilot`), to find a position to report.
+pub fn deserialize_value<T: DeserializeOwned>(
+    value: Value,
+    sources: &[(&JsoncSource, &str)],
+) -> ConfigResult<T> {
+    serde_path_to_error::deserialize(value).map_err(|e| {
+        let pointer = to_pointer(e.path());
+        locate_error(sources, &pointer, e.inner().to_string())
+    })
+}
+
+/// An error for the value at `pointer`, positioned in the first of
+/// `sources` that contains it.
+pub(crate) fn locate_error(
+    sources: &[(&JsoncSource, &str)],
+    pointer: &str,
+    message: String,
+) -> ConfigError {
+    sources
+        .iter()
+        .find_map(|(source, prefix)| {
+            source
+                .locate(&format!("{}{}", prefix, pointer))
+                .map(|offset| source.error_at(offset, pointer, message.clone()))
+        })
+        .unwrap_or_else(|| {
+            if pointer.is_empty() {
+                ConfigError::ParseError(message)
+            } else {
+                ConfigError::ParseError(format!("{}: {}", pointer, message))
+            }
+        })
+}
+
+fn to_pointer(path: &serde_path_to_error::Path) -> String {
+    use serde_path_to_error::Segment;
+
+    let mut pointer = String::new();
+    for segment in path.iter() {
+        let token = match segment {
+            Segment::Seq { index } => index.to_string(),
+            Segment::Map { key } => key.replace('~', "~0").replace('/', "~1"),
+            Segment::Enum { variant } => variant.clone(),
+            Segment::Unknown => continue,
+        };
+        pointer.push('/');
+        pointer.push_str(&token);
+    }
+    pointer
+}
+
+/// Index just past the string literal starting at `start`.
+fn skip_string(bytes: &[u8], start: usize) -> usize {
+    let mut i = start + 1;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            b'"' => return i + 1,
+            _ => i += 1,
+        }
+    }
+    bytes.len()
+}
+
+/// Walks already-valid JSON to find the offset of a value by path.
+struct Scanner<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl Scanner<'_> {
+    fn find(&mut self, tokens: &[String]) -> Option<usize> {
+        self.skip_whitespace();
+        let Some((token, rest)) = tokens.split_first() else {
+            return Some(self.pos);
+        };
+
+        match self.peek()? {
+            b'{' => {
+                self.pos += 1;
+                loop {
+                    self.skip_whitespace();
+                    if self.peek()? != b'"' {
+                        return None;
+                    }
+                    let key = self.read_string()?;
+                    self.skip_whitespace();
+                    self.expect(b':')?;
+                    if key == *token {
+                        return self.find(rest);
+                    }
+                    self.skip_value()?;
+                    self.skip_whitespace();
+                    self.expect(b',')?;
+                }
+            }
+            b'[' => {
+                let index: usize = token.parse().ok()?;
+                self.pos += 1;
+                for i in 0.. {
+                    self.skip_whitespace();
+                    if self.peek()? == b']' {
+                        return None;
+                    }
+                    if i == index {
+                        return self.find(rest);
+                    }
+                    self.skip_value()?;
+                    self.skip_whitespace();
+                    self.expect(b',')?;
+                }
+                None
+            }
+            _ => None,
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.text.as_bytes().get(self.pos).copied()
+    }
+
+    fn expect(&mut self, byte: u8) -> Option<()> {
+        (self.peek()? == byte).then(|| self.pos += 1)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(|b| b.is_ascii_whitespace()) {
+            self.pos += 1;
+        }
+    }
+
+    fn read_string(&mut self) -> Option<String> {
+        let start = self.pos;
+        self.pos = skip_string(self.text.as_bytes(), start);
+        serde_json::from_str(&self.text[start..self.pos]).ok()
+    }
+
+    fn skip_value(&mut self) -> Option<()> {
+        self.skip_whitespace();
+        match self.peek()? {
+            b'"' => {
+                self.pos = skip_string(self.text.as_bytes(), self.pos);
+            }
+            b'{' | b'[' => {
+                let mut depth = 0usize;
+                loop {
+                    match self.peek()? {
+                        b'"' => {
+                            self.pos = skip_string(self.text.as_bytes(), self.pos);
+                            continue;
+                        }
+                        b'{' | b'[' => depth += 1,
+                        b'}' | b']' => {
+                            depth -= 1;
+                            if depth == 0 {
+                                self.pos += 1;
+                                break;
+                            }
+                        }
+                        _ => {}
+                    }
+                    self.pos += 1;
+                }
+            }
+            _ => {
+                while self
+                    .peek()
+                    .is_some_and(|b| !matches!(b, b',' | b'}' | b']') && !b.is_ascii_whitespace())
+                {
+                    self.pos += 1;
+                }
+            }
+        }
+        Some(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::structs::RootConfig;
+
+    fn json_error(result: ConfigResult<impl fmt::Debug>) -> JsonError {
+        match result {
+            Err(ConfigError::JsonError(error)) => *error,
+            other => panic!("Expected JsonError, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_comments_and_trailing_commas() {
+        let source = r#"{
+            // line comment with "quotes"
+            "provider": "openai", /* inline */
+            "openai": {
+                "api_key": "sk-// not a comment",
+                "model": "gpt-4o", // last field
+            },
+            "list": [1, 2, /* three */],
+        }"#;
+        let value = JsoncSource::new(source, None).unwrap().parse().unwrap();
+        assert_eq!(value["provider"], "openai");
+        assert_eq!(value["openai"]["api_key"], "sk-// not a comment");
+        assert_eq!(value["list"], serde_json::json!([1, 2]));
+    }
+
+    #[test]
+    fn test_syntax_error_location() {
+        let source = "{\n  // settings\n  \"enabled\": tru\n}";
+        let error = json_error(
+            JsoncSource::new(source, Some(Path::new("settings.json")))
+                .unwrap()
+                .parse(),
+        );
+
+        assert_eq!(error.file, Some(PathBuf::from("settings.json")));
+        assert_eq!((error.line, error.column), (3, 17));
+        assert_eq!(error.excerpt, "  \"enabled\": tru");
+        assert!(error.message.contains("expected ident"));
+        assert!(!error.message.contains("at line"));
+    }
+
+    #[test]
+    fn test_unterminated_block_comment() {
+        let source = "{\n  /* open\n}";
+        let error = json_error(JsoncSource::new(source, None));
+        assert_eq!((error.line, error.column), (2, 3));
+        assert_eq!(error.message, "unterminated block comment");
+    }
+
+    #[test]
+    fn test_type_error_carries_pointer_and_position() {
+        let source = r#"{
+    "openai": {
+        "api_key": "sk-test", // key
+        "timeout_secs": "thirty"
+    }
+}"#;
+        let error = json_error(
+            JsoncSource::new(source, Some(Path::new("settings.json")))
+                .unwrap()
+                .deserialize::<RootConfig>(),
+        );
+        assert_eq!(error.pointer, "/openai/timeout_secs");
+        assert_eq!((error.line, error.column), (4, 25));
+        assert!(error.message.contains("expected u64"));
+        assert_eq!(
+            error.to_string(),
+            "settings.json:4:25: /openai/timeout_secs: invalid type: string \"thirty\", expected u64\n\
+             4 |         \"timeout_secs\": \"thirty\"\n  \
+               |                         ^"
+        );
+    }
+
+    #[test]
+    fn test_missing_field_points_at_object() {
+        let source = "{\"profiles\": {\"fast\": {\"model\": \"gpt-4o\"}}}";
+        let error = json_error(
+            JsoncSource::new(source, None)
+                .unwrap()
+                .deserialize::<RootConfig>(),
+        );
+        assert_eq!(error.pointer, "/profiles/fast");
+        assert_eq!((error.line, error.column), (1, 23));
+        assert!(error.message.contains("missing field `provider`"));
+    }
+
+    #[test]
+    fn test_locate_escaped_keys_and_arrays() {
+        let source = r#"{"a/b": {"x~y": [10, {"z": true}]}}"#;
+        let source = JsoncSource::new(source, None).unwrap();
+        assert_eq!(source.locate("/a~1b/x~0y/1/z"), Some(27));
+        assert_eq!(source.locate(""), Some(0));
+        assert_eq!(source.locate("/a~1b/x~0y/2"), None);
+        assert_eq!(source.locate("/missing"), None);
+    }
+}
@@ -0,0 +1,231 @@
+use crate::config::migrations::Migrated;
+use crate::config::structs::RootConfig;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::PathBuf;
+
+/// Prefix of environment variables that override settings.
+pub const ENV_PREFIX: &str = "ZED_COPILOT_";
+
+/// Separates nesting levels in an override variable's name, since setting
+/// names themselves contain single underscores.
+const ENV_NESTING: &str = "__";
+
+/// Where a setting's value came from. Layers are applied in declaration
+/// order, each overriding the ones before it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigSource {
+    Default,
+    User(PathBuf),
+    Project(PathBuf),
+    /// The name of the environment variable.
+    Env(String),
+}
+
+impl fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigSource::Default => write!(f, "built-in default"),
+            ConfigSource::User(path) => write!(f, "user settings ({})", path.display()),
+            ConfigSource::Project(path) => write!(f, "project settings ({})", path.display()),
+            ConfigSource::Env(name) => write!(f, "environment variable {}", name),
+        }
+    }
+}
+
+/// The layer that set each field, keyed by JSON pointer relative to the
+/// extension's section (e.g. `/openai/model`). Only leaves are recorded;
+/// arrays count as leaves.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Provenance {
+    sources: BTreeMap<String, ConfigSource>,
+}
+
+impl Provenance {
+    /// Records `source` for every leaf of `layer`, replacing whatever
+    /// earlier layers set at or below those leaves.
+    pub fn record(&mut self, layer: &Value, source: &ConfigSource) {
+        self.record_at(String::new(), layer, source);
+    }
+
+    fn record_at(&mut self, pointer: String, value: &Value, source: &ConfigSource) {
+        match value {
+            Value::Object(fields) if !fields.is_empty() => {
+                self.sources.remove(&pointer);
+                for (key, field) in fields {
+                    let child =
+                        format!("{}/{}", pointer, key.replace('~', "~0").replace('/', "~1"));
+                    self.record_at(child, field, source);
+                }
+            }
+            _ => {
+                let prefix = format!("{}/", pointer);
+                self.sources.retain(|key, _| !key.starts_with(&prefix));
+                self.sources.insert(pointer, source.clone());
+            }
+        }
+    }
+
+    /// Source of the value at `pointer`: the layer that set it or one of
+    /// its ancestors, or the built-in default when no layer did.
+    pub fn source_of(&self, pointer: &str) -> &ConfigSource {
+        let mut current = pointer;
+        loop {
+            if let Some(source) = self.sources.get(current) {
+                return source;
+            }
+            match current.rfind('/') {
+                Some(end) => current = &current[..end],
+                None => return &ConfigSource::Default,
+            }
+        }
+    }
+
+    /// Every recorded field and its source, ordered by pointer.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &ConfigSource)> {
+        self.sources
+            .iter()
+            .map(|(pointer, source)| (pointer.as_str(), source))
+    }
+}
+
+/// A configuration merged from layers, with the source of each field.
+#[derive(Debug, Clone)]
+pub struct LayeredConfig {
+    pub config: RootConfig,
+    pub provenance: Provenance,
+    /// Settings files written for an older version, with their sections
+    /// as migrated. Shown to the user so they can update the files.
+    pub migrations: Vec<(ConfigSource, Migrated)>,
+}
+
+/// Builds the environment layer from `ZED_COPILOT_*` variables. Each name
+/// after the prefix is lowercased and split on `__` into a path, so
+/// `ZED_COPILOT_OPENAI__TIMEOUT_SECS=60` sets `openai.timeout_secs`.
+/// Values that parse as JSON are used as such, anything else as a string.
+/// Returns one single-field layer per variable, with the variable's name.
+pub fn env_layer(vars: impl IntoIterator<Item = (String, String)>) -> Vec<(String, Value)> {
+    let mut overrides: Vec<(String, String)> = vars
+        .into_iter()
+        .filter(|(name, _)| name.starts_with(ENV_PREFIX))
+        .collect();
+    // Parents sort before their children, so `ZED_COPILOT_OPENAI` is
+    // applied before `ZED_COPILOT_OPENAI__MODEL` refines it.
+    overrides.sort();
+
+    overrides
+        .into_iter()
+        .filter_map(|(name, raw)| {
+            let path: Vec<String> = name[ENV_PREFIX.len()..]
+                .split(ENV_NESTING)
+                .map(str::to_lowercase)
+                .collect();
+            if path.iter().any(String::is_empty) {
+                log::warn!("Ignoring malformed settings override {}", name);
+                return None;
+            }
+
+            let value = serde_json::from_str(&raw).unwrap_or(Value::String(raw));
+            let mut layer = Value::Object(Map::new());
+            set_path(&mut layer, &path, value);
+            Some((name, layer))
+        })
+        .collect()
+}
+
+fn set_path(target: &mut Value, path: &[String], value: Value) {
+    let Some((key, rest)) = path.split_first() else {
+        *target = value;
+        return;
+    };
+    if !target.is_object() {
+        *target = Value::Object(Map::new());
+    }
+    if let Value::Object(fields) = target {
+        let child = fields.entry(key.clone()).or_insert(Value::Null);
+        set_path(child, rest, value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn vars(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_env_layer_paths_and_values() {
+        let layers = env_layer(vars(&[
+            ("ZED_COPILOT_OPENAI__TIMEOUT_SECS", "60"),
+            ("ZED_COPILOT_ENABLED", "false"),
+            ("ZED_COPILOT_OPENAI__MODEL", "gpt-4o"),
+            ("ZED_COPILOT_PROFILES__FAST__MODEL", "\"123\""),
+            ("OPENAI_API_KEY", "sk-ignored"),
+            ("ZED_COPILOT_", "ignored"),
+            ("ZED_COPILOT_CHAT____X", "ignored"),
+        ]));
+
+        let names: Vec<&str> = layers.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "ZED_COPILOT_ENABLED",
+                "ZED_COPILOT_OPENAI__MODEL",
+                "ZED_COPILOT_OPENAI__TIMEOUT_SECS",
+                "ZED_COPILOT_PROFILES__FAST__MODEL",
+            ]
+        );
+        assert_eq!(layers[0].1, json!({"enabled": false}));
+        assert_eq!(layers[1].1, json!({"openai": {"model": "gpt-4o"}}));
+        assert_eq!(layers[2].1, json!({"openai": {"timeout_secs": 60}}));
+        assert_eq!(layers[3].1, json!({"profiles": {"fast": {"model": "123"}}}));
+    }
+
+    #[test]
+    fn test_provenance_later_layers_win() {
+        let user = ConfigSource::User(PathBuf::from("/u/settings.json"));
+        let project = ConfigSource::Project(PathBuf::from("/p/.zed/settings.json"));
+        let mut provenance = Provenance::default();
+        provenance.record(
+            &json!({"enabled": true, "openai": {"api_key": "k", "model": "gpt-4"}}),
+            &user,
+        );
+        provenance.record(&json!({"openai": {"model": "gpt-4o"}}), &project);
+
+        assert_eq!(provenance.source_of("/enabled"), &user);
+        assert_eq!(provenance.source_of("/openai/api_key"), &user);
+        assert_eq!(provenance.source_of("/openai/model"), &project);
+        assert_eq!(
+            provenance.source_of("/chat/streaming_enabled"),
+            &ConfigSource::Default
+        );
+    }
+
+    #[test]
+    fn test_provenance_replacing_object_drops_children() {
+        let user = ConfigSource::User(PathBuf::from("/u/settings.json"));
+        let env = ConfigSource::Env("ZED_COPILOT_ROUTING".to_string());
+        let mut provenance = Provenance::default();
+        provenance.record(&json!({"routing": {"tasks": {"chat": "smart"}}}), &user);
+        provenance.record(&json!({"routing": null}), &env);
+
+        assert_eq!(provenance.iter().count(), 1);
+        assert_eq!(provenance.source_of("/routing/tasks/chat"), &env);
+    }
+
+    #[test]
+    fn test_source_display() {
+        assert_eq!(
+            ConfigSource::Env("ZED_COPILOT_ENABLED".to_string()).to_string(),
+            "environment variable ZED_COPILOT_ENABLED"
+        );
+        assert_eq!(ConfigSource::Default.to_string(), "built-in default");
+    }
+}
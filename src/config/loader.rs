@@ -1,44 +1,251 @@
+use crate::config::diagnostics::{self, Diagnostics};
 use crate::config::errors::{ConfigError, ConfigResult};
+use crate::config::jsonc::{self, JsoncSource};
+use crate::config::layers::{self, ConfigSource, LayeredConfig, Provenance};
+use crate::config::migrations::{self, Migrated};
+use crate::config::schema::SettingsSchema;
+use crate::config::secrets::SecretStore;
 use crate::config::structs::RootConfig;
+use serde_json::Value;
 use std::env;
-use std::path::Path;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
 
+/// Key of the extension's section in Zed's `settings.json`.
+pub const SETTINGS_SECTION: &str = "zed_copilot";
+
+/// Which kinds of `${...}` reference may be resolved. Environment
+/// variables are always allowed.
+#[derive(Debug, Clone, Copy)]
+pub struct InterpolationOptions<'a> {
+    /// `${file:/path}`: the trimmed content of a file only its owner can read.
+    pub allow_files: bool,
+    /// `${cmd:...}`: the trimmed output of a shell command.
+    pub allow_commands: bool,
+    /// `${secret:name}`: a value from the encrypted secret store.
+    pub secrets: Option<&'a SecretStore>,
+}
+
+impl Default for InterpolationOptions<'_> {
+    fn default() -> Self {
+        InterpolationOptions {
+            allow_files: true,
+            allow_commands: false,
+            secrets: None,
+        }
+    }
+}
+
+enum Token {
+    Text(String),
+    /// The inside of `${...}`.
+    Reference(String),
+}
+
+/// Resolves references in setting values:
+///
+/// - `${VAR}`: an environment variable
+/// - `${VAR:-default}`: the variable, or `default` if it is unset or empty
+/// - `${file:/path/to/secret}`: a secret file's trimmed content
+/// - `${secret:name}`: a secret from the encrypted store, when one is open
+/// - `${cmd:op read op://vault/key}`: a command's trimmed output, only when
+///   allowed
+/// - `$$`: a literal `$`
 pub struct EnvInterpolator;
 
 impl EnvInterpolator {
     pub fn interpolate(value: &str) -> ConfigResult<String> {
+        Self::interpolate_with(value, InterpolationOptions::default())
+    }
+
+    pub fn interpolate_with(value: &str, options: InterpolationOptions) -> ConfigResult<String> {
         let mut result = String::new();
+        for token in Self::tokenize(value)? {
+            match token {
+                Token::Text(text) => result.push_str(&text),
+                Token::Reference(reference) => {
+                    result.push_str(&Self::resolve(&reference, options)?)
+                }
+            }
+        }
+        Ok(result)
+    }
+
+    /// Every reference in `value`, including those in the defaults of
+    /// `${VAR:-default}`, without resolving any. `$${...}` is not one.
+    pub fn references(value: &str) -> ConfigResult<Vec<String>> {
+        let mut references = Vec::new();
+        for token in Self::tokenize(value)? {
+            if let Token::Reference(reference) = token {
+                let is_env = !["file:", "secret:", "cmd:"]
+                    .iter()
+                    .any(|prefix| reference.starts_with(prefix));
+                if let Some((_, default)) = reference.split_once(":-").filter(|_| is_env) {
+                    references.extend(Self::references(default)?);
+                }
+                references.push(reference);
+            }
+        }
+        Ok(references)
+    }
+
+    fn tokenize(value: &str) -> ConfigResult<Vec<Token>> {
+        let mut tokens = Vec::new();
+        let mut text = String::new();
         let mut chars = value.chars().peekable();
 
         while let Some(ch) = chars.next() {
             if ch == '$' {
-                if chars.peek() == Some(&'{') {
-                    chars.next();
-                    let var_name = Self::read_until_closing_brace(&mut chars)?;
-                    let var_value = env::var(&var_name)
-                        .map_err(|_| ConfigError::EnvVarNotFound(var_name.clone()))?;
-                    result.push_str(&var_value);
-                } else {
-                    result.push(ch);
+                match chars.peek() {
+                    Some('{') => {
+                        chars.next();
+                        let reference = Self::read_until_closing_brace(&mut chars)?;
+                        if !text.is_empty() {
+                            tokens.push(Token::Text(std::mem::take(&mut text)));
+                        }
+                        tokens.push(Token::Reference(reference));
+                    }
+                    Some('$') => {
+                        chars.next();
+                        text.push('$');
+                    }
+                    _ => text.push(ch),
                 }
             } else {
-                result.push(ch);
+                text.push(ch);
             }
         }
+        if !text.is_empty() {
+            tokens.push(Token::Text(text));
+        }
 
-        Ok(result)
+        Ok(tokens)
+    }
+
+    fn resolve(reference: &str, options: InterpolationOptions) -> ConfigResult<String> {
+        if let Some(path) = reference.strip_prefix("file:") {
+            if !options.allow_files {
+                return Err(ConfigError::ValidationError(
+                    "${file:...} references are not allowed here".to_string(),
+                ));
+            }
+            return Self::read_secret_file(path);
+        }
+
+        if let Some(name) = reference.strip_prefix("secret:") {
+            let store = options.secrets.ok_or_else(|| {
+                ConfigError::ValidationError(
+                    "${secret:...} references need secrets.passphrase in user settings".to_string(),
+                )
+            })?;
+            return store
+                .get(name)
+                .map(str::to_string)
+                .ok_or_else(|| ConfigError::ValidationError(format!("Unknown secret '{}'", name)));
+        }
+
+        if let Some(command) = reference.strip_prefix("cmd:") {
+            if !options.allow_commands {
+                return Err(ConfigError::ValidationError(
+                    "${cmd:...} references require secrets.allow_commands in user settings"
+                        .to_string(),
+                ));
+            }
+            return Self::run_secret_command(command);
+        }
+
+        match reference.split_once(":-") {
+            Some((name, default)) => match env::var(name) {
+                Ok(value) if !value.is_empty() => Ok(value),
+                _ => Self::interpolate_with(default, options),
+            },
+            None => {
+                env::var(reference).map_err(|_| ConfigError::EnvVarNotFound(reference.to_string()))
+            }
+        }
     }
 
+    fn read_secret_file(path: &str) -> ConfigResult<String> {
+        let path = match (path.strip_prefix("~/"), env::var_os("HOME")) {
+            (Some(rest), Some(home)) => PathBuf::from(home).join(rest),
+            _ => PathBuf::from(path),
+        };
+        let unreadable = |e: std::io::Error| {
+            ConfigError::ValidationError(format!(
+                "Cannot read secret file {}: {}",
+                path.display(),
+                e
+            ))
+        };
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            let mode = std::fs::metadata(&path)
+                .map_err(unreadable)?
+                .permissions()
+                .mode();
+            if mode & 0o077 != 0 {
+                return Err(ConfigError::ValidationError(format!(
+                    "Secret file {} is accessible by other users (mode {:o}); restrict it with chmod 600",
+                    path.display(),
+                    mode & 0o777
+                )));
+            }
+        }
+
+        let content = std::fs::read_to_string(&path).map_err(unreadable)?;
+        Ok(content.trim().to_string())
+    }
+
+    fn run_secret_command(command: &str) -> ConfigResult<String> {
+        let mut process = if cfg!(windows) {
+            let mut process = std::process::Command::new("cmd");
+            process.arg("/C");
+            process
+        } else {
+            let mut process = std::process::Command::new("sh");
+            process.arg("-c");
+            process
+        };
+        // The command and its output may contain secrets, so neither is
+        // included in errors.
+        let output = process.arg(command).output().map_err(|e| {
+            ConfigError::ValidationError(format!("Failed to run secret command: {}", e))
+        })?;
+        if !output.status.success() {
+            return Err(ConfigError::ValidationError(format!(
+                "Secret command failed with {}",
+                output.status
+            )));
+        }
+
+        String::from_utf8(output.stdout)
+            .map(|stdout| stdout.trim().to_string())
+            .map_err(|_| {
+                ConfigError::ValidationError("Secret command printed invalid UTF-8".to_string())
+            })
+    }
+
+    /// Reads the reference after `${`, up to the matching `}`. Nested
+    /// references, as in `${A:-${B}}`, are kept for the recursive pass.
     fn read_until_closing_brace(
         chars: &mut std::iter::Peekable<std::str::Chars>,
     ) -> ConfigResult<String> {
         let mut var_name = String::new();
         let mut found_close = false;
+        let mut depth = 0;
 
         for ch in chars.by_ref() {
-            if ch == '}' {
-                found_close = true;
-                break;
+            match ch {
+                '{' => depth += 1,
+                '}' if depth == 0 => {
+                    found_close = true;
+                    break;
+                }
+                '}' => depth -= 1,
+                _ => {}
             }
             var_name.push(ch);
         }
@@ -59,22 +266,366 @@ impl EnvInterpolator {
     }
 }
 
+/// Zed settings files the extension reads its section from. Project
+/// settings take precedence over user settings.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ZedSettingsPaths {
+    pub user: Option<PathBuf>,
+    pub project: Option<PathBuf>,
+}
+
+impl ZedSettingsPaths {
+    /// Paths for the current user and working directory.
+    pub fn discover() -> Self {
+        Self::resolve(
+            env::var_os("XDG_CONFIG_HOME"),
+            env::var_os("HOME"),
+            env::current_dir().ok().as_deref(),
+        )
+    }
+
+    /// User settings live in `$XDG_CONFIG_HOME/zed/settings.json`, or
+    /// `~/.config/zed/settings.json` when that variable is unset, empty or
+    /// relative. Project settings live in `<root>/.zed/settings.json`.
+    pub fn resolve(
+        xdg_config_home: Option<OsString>,
+        home: Option<OsString>,
+        project_root: Option<&Path>,
+    ) -> Self {
+        let config_home = xdg_config_home
+            .map(PathBuf::from)
+            .filter(|dir| dir.is_absolute())
+            .or_else(|| {
+                home.filter(|home| !home.is_empty())
+                    .map(|home| PathBuf::from(home).join(".config"))
+            });
+
+        ZedSettingsPaths {
+            user: config_home.map(|dir| dir.join("zed").join("settings.json")),
+            project: project_root.map(|root| root.join(".zed").join("settings.json")),
+        }
+    }
+}
+
 pub struct ConfigLoader;
 
 impl ConfigLoader {
+    /// Parses settings JSON. Comments and trailing commas are accepted, as
+    /// in Zed's own settings files. Settings from older versions are
+    /// migrated first.
     pub fn load_from_json_string(json: &str) -> ConfigResult<RootConfig> {
-        let config: RootConfig = serde_json::from_str(json)?;
-        Ok(config)
+        let source = JsoncSource::new(json, None)?;
+        let section = Self::migrate(source.parse()?, None)?.settings;
+        Self::deserialize_section(section, &[(&source, "")])
     }
 
     pub fn load_from_file(path: &Path) -> ConfigResult<RootConfig> {
         let content = std::fs::read_to_string(path)
             .map_err(|e| ConfigError::ParseError(format!("Failed to read config file: {}", e)))?;
-        Self::load_from_json_string(&content)
+        let source = JsoncSource::new(&content, Some(path))?;
+        let section = Self::migrate(source.parse()?, Some(path))?.settings;
+        Self::deserialize_section(section, &[(&source, "")])
     }
 
     pub fn load_from_zed_settings() -> ConfigResult<RootConfig> {
-        Ok(RootConfig::default())
+        Self::load_layered(&ZedSettingsPaths::discover(), env::vars()).map(|layered| layered.config)
+    }
+
+    /// Reads the `zed_copilot` section from each settings file that exists
+    /// and merges them, project over user. Without any section the default
+    /// configuration, which leaves the extension disabled, is returned.
+    pub fn load_from_settings_paths(paths: &ZedSettingsPaths) -> ConfigResult<RootConfig> {
+        Self::load_layered(paths, std::iter::empty()).map(|layered| layered.config)
+    }
+
+    /// Merges built-in defaults, user settings, project settings and
+    /// `ZED_COPILOT_*` overrides from `env_vars`, in that order, recording
+    /// which layer set each field.
+    pub fn load_layered(
+        paths: &ZedSettingsPaths,
+        env_vars: impl IntoIterator<Item = (String, String)>,
+    ) -> ConfigResult<LayeredConfig> {
+        let files = [
+            paths
+                .user
+                .as_deref()
+                .map(|path| (path, ConfigSource::User(path.to_path_buf()))),
+            paths
+                .project
+                .as_deref()
+                .map(|path| (path, ConfigSource::Project(path.to_path_buf()))),
+        ];
+        let mut contents = Vec::new();
+        for (path, source) in files.into_iter().flatten() {
+            if let Some(content) = Self::read_settings_file(path)? {
+                contents.push((path, source, content));
+            }
+        }
+
+        let mut sources = Vec::new();
+        let mut merged = Value::Object(Default::default());
+        let mut provenance = Provenance::default();
+        let mut migrations = Vec::new();
+        let mut any_layer = false;
+        for (path, source_kind, content) in &contents {
+            let source = JsoncSource::new(content, Some(path))?;
+            if let Some(section) = Self::settings_section(&source)? {
+                let migrated = Self::migrate(section, Some(path))?;
+                let mut section = migrated.settings.clone();
+                if matches!(source_kind, ConfigSource::Project(_)) {
+                    restrict_project_endpoints(&mut section, path);
+                    restrict_project_privacy(&mut section, &merged, path);
+                    restrict_project_redaction(&mut section, &merged, path);
+                }
+                if migrated.rewritten() {
+                    migrations.push((source_kind.clone(), migrated));
+                }
+                provenance.record(&section, source_kind);
+                merge_values(&mut merged, section);
+                sources.push(source);
+                any_layer = true;
+            }
+        }
+
+        for (name, layer) in layers::env_layer(env_vars) {
+            log::debug!("Applying settings override from {}", name);
+            provenance.record(&layer, &ConfigSource::Env(name));
+            merge_values(&mut merged, layer);
+            any_layer = true;
+        }
+
+        let section_pointer = format!("/{}", SETTINGS_SECTION);
+        // Later files win the merge, so they are searched first when an
+        // error needs a position.
+        let located: Vec<(&JsoncSource, &str)> = sources
+            .iter()
+            .rev()
+            .map(|source| (source, section_pointer.as_str()))
+            .collect();
+        let config = if any_layer {
+            let raw = merged.clone();
+            let config = Self::deserialize_section(merged, &located)?;
+            let mut unknown = Diagnostics::default();
+            diagnostics::unknown_fields(&raw, &config, &mut unknown);
+            for diagnostic in unknown.into_vec() {
+                log::warn!("{}", diagnostic);
+            }
+            config
+        } else {
+            RootConfig::default()
+        };
+        Ok(LayeredConfig {
+            config,
+            provenance,
+            migrations,
+        })
+    }
+
+    /// Upgrades a settings section to the current version, logging each
+    /// rewrite.
+    fn migrate(section: Value, path: Option<&Path>) -> ConfigResult<Migrated> {
+        let migrated = migrations::migrate(section).map_err(|e| match (e, path) {
+            (ConfigError::ValidationError(msg), Some(path)) => {
+                ConfigError::ValidationError(format!("{}: {}", path.display(), msg))
+            }
+            (e, _) => e,
+        })?;
+        for warning in &migrated.warnings {
+            match path {
+                Some(path) => log::warn!("{}: {}", path.display(), warning),
+                None => log::warn!("{}", warning),
+            }
+        }
+        Ok(migrated)
+    }
+
+    /// Checks `section` against the settings schema, then deserializes it.
+    /// Errors are positioned in `sources`, as for `jsonc::deserialize_value`.
+    fn deserialize_section(
+        section: Value,
+        sources: &[(&JsoncSource, &str)],
+    ) -> ConfigResult<RootConfig> {
+        if let Some(violation) = SettingsSchema::global()
+            .validate(&section)
+            .into_iter()
+            .next()
+        {
+            return Err(jsonc::locate_error(
+                sources,
+                &violation.pointer,
+                violation.message,
+            ));
+        }
+        jsonc::deserialize_value(section, sources)
+    }
+
+    /// Content of a settings file, or `None` if it is missing or blank.
+    fn read_settings_file(path: &Path) -> ConfigResult<Option<String>> {
+        match std::fs::read_to_string(path) {
+            Ok(content) if content.trim().is_empty() => Ok(None),
+            Ok(content) => Ok(Some(content)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(ConfigError::ParseError(format!(
+                "Failed to read {}: {}",
+                path.display(),
+                e
+            ))),
+        }
+    }
+
+    /// The extension's section of a Zed settings file, if present.
+    fn settings_section(source: &JsoncSource) -> ConfigResult<Option<Value>> {
+        let mut settings = source.parse()?;
+        match settings.get_mut(SETTINGS_SECTION).map(Value::take) {
+            Some(section @ Value::Object(_)) => Ok(Some(section)),
+            Some(Value::Null) | None => Ok(None),
+            Some(_) => {
+                let pointer = format!("/{}", SETTINGS_SECTION);
+                let offset = source.locate(&pointer).unwrap_or(0);
+                Err(source.error_at(offset, &pointer, "must be an object"))
+            }
+        }
+    }
+}
+
+/// Keeps a repository's settings from loosening the user's privacy policy:
+/// `privacy.exclude` and `privacy.deny` from project settings are added to
+/// the user's lists, and other `privacy` settings there are ignored.
+fn restrict_project_privacy(section: &mut Value, user: &Value, path: &Path) {
+    let Some(privacy) = section.get_mut("privacy").and_then(Value::as_object_mut) else {
+        return;
+    };
+    privacy.retain(|key, value| match key.as_str() {
+        "exclude" | "deny" => {
+            if let (Some(user), Value::Array(project)) = (
+                user.pointer(&format!("/privacy/{}", key))
+                    .and_then(Value::as_array),
+                &mut *value,
+            ) {
+                project.splice(0..0, user.iter().cloned());
+            }
+            true
+        }
+        _ => {
+            log::warn!(
+                "{}: ignoring privacy.{}; project settings may only add exclude and deny patterns",
+                path.display(),
+                key
+            );
+            false
+        }
+    });
+}
+
+/// Keeps a repository's settings from weakening redaction: they may turn
+/// it on and add `redaction.patterns` under new names, and nothing else.
+fn restrict_project_redaction(section: &mut Value, user: &Value, path: &Path) {
+    let Some(redaction) = section.get_mut("redaction").and_then(Value::as_object_mut) else {
+        return;
+    };
+    redaction.retain(|key, value| match (key.as_str(), value) {
+        ("enabled", Value::Bool(true)) => true,
+        ("patterns", Value::Object(patterns)) => {
+            patterns.retain(|name, _| {
+                let defined = user
+                    .pointer(&diagnostics::pointer("/redaction/patterns", name))
+                    .is_some();
+                if defined {
+                    log::warn!(
+                        "{}: ignoring redaction.patterns.{}; it is already defined in user settings",
+                        path.display(),
+                        name
+                    );
+                }
+                !defined
+            });
+            true
+        }
+        _ => {
+            log::warn!(
+                "{}: ignoring redaction.{}; project settings may only enable redaction and add patterns",
+                path.display(),
+                key
+            );
+            false
+        }
+    });
+}
+
+/// Settings only user settings may set, besides each provider section's
+/// and profile's `api_base` and each provider section's `headers`.
+const USER_ONLY_SETTINGS: &[&str] = &[
+    "network.proxy",
+    "network.no_proxy",
+    "network.use_env_proxy",
+    "network.ca_certificates",
+    "network.client_certificate",
+    "network.wire_log.path",
+    "cache.disk_path",
+    "redaction.audit_log",
+];
+
+/// Keeps a repository's settings from choosing where requests go or where
+/// files are read or written: endpoints, proxy settings, CA and client
+/// certificates, headers and output paths are dropped from them.
+fn restrict_project_endpoints(section: &mut Value, path: &Path) {
+    let Some(fields) = section.as_object() else {
+        return;
+    };
+    let mut user_only: Vec<Vec<String>> = USER_ONLY_SETTINGS
+        .iter()
+        .map(|setting| setting.split('.').map(String::from).collect())
+        .collect();
+    for (name, value) in fields {
+        if name == "profiles" {
+            for profile in value
+                .as_object()
+                .into_iter()
+                .flat_map(|profiles| profiles.keys())
+            {
+                user_only.push(vec![name.clone(), profile.clone(), "api_base".into()]);
+            }
+        } else if value.is_object() {
+            for key in ["api_base", "headers"] {
+                user_only.push(vec![name.clone(), key.into()]);
+            }
+        }
+    }
+
+    for setting in user_only {
+        let Some((key, parents)) = setting.split_last() else {
+            continue;
+        };
+        let removed = parents
+            .iter()
+            .try_fold(&mut *section, |value, name| value.get_mut(name))
+            .and_then(Value::as_object_mut)
+            .and_then(|fields| fields.remove(key));
+        if removed.is_some() {
+            log::warn!(
+                "{}: ignoring {}; only user settings may set endpoints, proxy settings, certificates, headers and output paths",
+                path.display(),
+                setting.join(".")
+            );
+        }
+    }
+}
+
+/// Merges `overlay` into `base`. Objects are merged key by key; any other
+/// value replaces what was there.
+fn merge_values(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Object(base_fields), Value::Object(overlay_fields)) => {
+            for (key, value) in overlay_fields {
+                match base_fields.get_mut(&key) {
+                    Some(existing) => merge_values(existing, value),
+                    None => {
+                        base_fields.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
     }
 }
 
@@ -82,6 +633,24 @@ impl ConfigLoader {
 mod tests {
     use super::*;
 
+    fn settings_dirs(name: &str) -> (PathBuf, PathBuf) {
+        let root = env::temp_dir().join(format!(
+            "zed-copilot-settings-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&root);
+        let config_home = root.join("config");
+        let project = root.join("project");
+        std::fs::create_dir_all(config_home.join("zed")).unwrap();
+        std::fs::create_dir_all(project.join(".zed")).unwrap();
+        (config_home, project)
+    }
+
+    fn paths_for(config_home: &Path, project: &Path) -> ZedSettingsPaths {
+        ZedSettingsPaths::resolve(Some(config_home.into()), None, Some(project))
+    }
+
     #[test]
     fn test_env_interpolator_single_variable() {
         env::set_var("TEST_VAR", "test_value");
@@ -149,6 +718,133 @@ mod tests {
         assert_eq!(result.unwrap(), "Price: $100");
     }
 
+    #[test]
+    fn test_env_interpolator_default_value() {
+        env::set_var("INTERP_EMPTY_VAR", "");
+        env::set_var("INTERP_SET_VAR", "set");
+        let result = EnvInterpolator::interpolate(
+            "${INTERP_UNSET_VAR_XYZ:-fallback} ${INTERP_EMPTY_VAR:-empty} ${INTERP_SET_VAR:-x}",
+        );
+        assert_eq!(result.unwrap(), "fallback empty set");
+    }
+
+    #[test]
+    fn test_env_interpolator_nested_default() {
+        env::set_var("INTERP_NESTED_VAR", "inner");
+        let result = EnvInterpolator::interpolate("${INTERP_UNSET_VAR_XYZ:-${INTERP_NESTED_VAR}}");
+        assert_eq!(result.unwrap(), "inner");
+        assert!(matches!(
+            EnvInterpolator::interpolate("${INTERP_UNSET_VAR_XYZ:-${INTERP_UNSET_VAR_ABC}}"),
+            Err(ConfigError::EnvVarNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_env_interpolator_escaped_dollar() {
+        let result = EnvInterpolator::interpolate("cost: $$5, literal $${HOME}");
+        assert_eq!(result.unwrap(), "cost: $5, literal ${HOME}");
+    }
+
+    #[test]
+    fn test_env_interpolator_references() {
+        assert_eq!(
+            EnvInterpolator::references("${A:-${secret:b}}/${file:/c}").unwrap(),
+            vec!["secret:b", "A:-${secret:b}", "file:/c"]
+        );
+        assert!(EnvInterpolator::references("$${secret:openai} costs $$5")
+            .unwrap()
+            .is_empty());
+        assert!(EnvInterpolator::references("${unclosed").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_env_interpolator_secret_file() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = env::temp_dir().join(format!("zed-copilot-secret-{}", std::process::id()));
+        std::fs::write(&path, "sk-from-file\n").unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600)).unwrap();
+        let reference = format!("${{file:{}}}", path.display());
+        assert_eq!(
+            EnvInterpolator::interpolate(&reference).unwrap(),
+            "sk-from-file"
+        );
+
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+        match EnvInterpolator::interpolate(&reference) {
+            Err(ConfigError::ValidationError(msg)) => assert!(msg.contains("chmod 600")),
+            other => panic!("Expected ValidationError, got {:?}", other),
+        }
+
+        let options = InterpolationOptions {
+            allow_files: false,
+            ..Default::default()
+        };
+        assert!(EnvInterpolator::interpolate_with(&reference, options).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_env_interpolator_missing_secret_file() {
+        match EnvInterpolator::interpolate("${file:/nonexistent/zed-copilot/secret}") {
+            Err(ConfigError::ValidationError(msg)) => assert!(msg.contains("Cannot read")),
+            other => panic!("Expected ValidationError, got {:?}", other),
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_env_interpolator_command_requires_flag() {
+        assert!(matches!(
+            EnvInterpolator::interpolate("${cmd:echo sk-from-cmd}"),
+            Err(ConfigError::ValidationError(_))
+        ));
+
+        let options = InterpolationOptions {
+            allow_commands: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            EnvInterpolator::interpolate_with("${cmd:echo sk-from-cmd}", options).unwrap(),
+            "sk-from-cmd"
+        );
+        match EnvInterpolator::interpolate_with("${cmd:echo sk-secret; exit 3}", options) {
+            Err(ConfigError::ValidationError(msg)) => assert!(!msg.contains("sk-secret")),
+            other => panic!("Expected ValidationError, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_env_interpolator_secret_reference() {
+        let path = env::temp_dir()
+            .join(format!("zed-copilot-interp-store-{}", std::process::id()))
+            .join("secrets.enc");
+        let _ = std::fs::remove_file(&path);
+        let params = crate::config::KdfParams {
+            memory_kib: 64,
+            iterations: 1,
+            parallelism: 1,
+        };
+        let mut store = SecretStore::open_with_params(&path, "pass", params).unwrap();
+        store.set("openai", "sk-from-store").unwrap();
+
+        assert!(EnvInterpolator::interpolate("${secret:openai}").is_err());
+        let options = InterpolationOptions {
+            secrets: Some(&store),
+            ..Default::default()
+        };
+        assert_eq!(
+            EnvInterpolator::interpolate_with("Bearer ${secret:openai}", options).unwrap(),
+            "Bearer sk-from-store"
+        );
+        match EnvInterpolator::interpolate_with("${secret:missing}", options) {
+            Err(ConfigError::ValidationError(msg)) => assert!(msg.contains("missing")),
+            other => panic!("Expected ValidationError, got {:?}", other),
+        }
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
     #[test]
     fn test_config_loader_valid_json() {
         let json = r#"{"enabled": true, "provider": "openai"}"#;
@@ -166,6 +862,32 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_config_loader_rejects_schema_violations() {
+        let json = "{\n  \"enabled\": true,\n  \"chat\": {\"max_history_messages\": 0}\n}";
+        match ConfigLoader::load_from_json_string(json) {
+            Err(ConfigError::JsonError(error)) => {
+                assert_eq!(error.pointer, "/chat/max_history_messages");
+                assert_eq!(error.line, 3);
+                assert!(error.message.contains("minimum of 1"));
+            }
+            other => panic!("Expected JsonError, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_config_loader_migrates_old_settings() {
+        let json = r#"{"provider": "openai", "openai": {"api_key": "sk", "model": "gpt-4o"}}"#;
+        let config = ConfigLoader::load_from_json_string(json).unwrap();
+        assert_eq!(config.version, migrations::CURRENT_VERSION);
+        assert_eq!(config.openai.unwrap().model, "gpt-4o");
+
+        match ConfigLoader::load_from_json_string(r#"{"version": 999}"#) {
+            Err(ConfigError::ValidationError(msg)) => assert!(msg.contains("newer")),
+            other => panic!("Expected ValidationError, got {:?}", other),
+        }
+    }
+
     #[test]
     fn test_config_loader_defaults() {
         let json = r#"{}"#;
@@ -175,4 +897,340 @@ mod tests {
         assert!(!config.enabled);
         assert_eq!(config.provider, "");
     }
+
+    #[test]
+    fn test_settings_paths_honor_xdg_config_home() {
+        let paths = ZedSettingsPaths::resolve(
+            Some("/xdg".into()),
+            Some("/home/me".into()),
+            Some(Path::new("/work/app")),
+        );
+        assert_eq!(paths.user, Some(PathBuf::from("/xdg/zed/settings.json")));
+        assert_eq!(
+            paths.project,
+            Some(PathBuf::from("/work/app/.zed/settings.json"))
+        );
+    }
+
+    #[test]
+    fn test_settings_paths_fall_back_to_home() {
+        for xdg in [None, Some("".into()), Some("relative/dir".into())] {
+            let paths = ZedSettingsPaths::resolve(xdg, Some("/home/me".into()), None);
+            assert_eq!(
+                paths.user,
+                Some(PathBuf::from("/home/me/.config/zed/settings.json"))
+            );
+            assert_eq!(paths.project, None);
+        }
+        assert_eq!(
+            ZedSettingsPaths::resolve(None, None, None),
+            ZedSettingsPaths::default()
+        );
+    }
+
+    #[test]
+    fn test_load_settings_missing_files() {
+        let (config_home, project) = settings_dirs("missing");
+        let config =
+            ConfigLoader::load_from_settings_paths(&paths_for(&config_home, &project)).unwrap();
+        assert!(!config.enabled);
+    }
+
+    #[test]
+    fn test_load_settings_missing_section() {
+        let (config_home, project) = settings_dirs("no-section");
+        std::fs::write(
+            config_home.join("zed/settings.json"),
+            r#"{"theme": "One Dark", "zed_copilot": null}"#,
+        )
+        .unwrap();
+        std::fs::write(project.join(".zed/settings.json"), "  \n").unwrap();
+
+        let config =
+            ConfigLoader::load_from_settings_paths(&paths_for(&config_home, &project)).unwrap();
+        assert!(!config.enabled);
+        assert!(config.provider_sections.is_empty());
+    }
+
+    #[test]
+    fn test_load_settings_extracts_section() {
+        let (config_home, project) = settings_dirs("user");
+        std::fs::write(
+            config_home.join("zed/settings.json"),
+            r#"{
+                "theme": "One Dark",
+                "zed_copilot": {
+                    "enabled": true,
+                    "provider": "openai",
+                    "openai": {"api_key": "${OPENAI_API_KEY}", "model": "gpt-4o"}
+                }
+            }"#,
+        )
+        .unwrap();
+
+        let config =
+            ConfigLoader::load_from_settings_paths(&paths_for(&config_home, &project)).unwrap();
+        assert!(config.enabled);
+        assert_eq!(config.openai.unwrap().model, "gpt-4o");
+        assert!(!config.provider_sections.contains_key("theme"));
+    }
+
+    #[test]
+    fn test_load_layered_stamps_unversioned_files() {
+        let (config_home, project) = settings_dirs("migrate");
+        std::fs::write(
+            config_home.join("zed/settings.json"),
+            r#"{"zed_copilot": {
+                "enabled": true,
+                "provider": "openai",
+                "openai": {"api_key": "sk-user", "timeout_secs": 60}
+            }}"#,
+        )
+        .unwrap();
+        std::fs::write(
+            project.join(".zed/settings.json"),
+            r#"{"zed_copilot": {"version": 1, "openai": {"model": "gpt-4o"}}}"#,
+        )
+        .unwrap();
+
+        let layered =
+            ConfigLoader::load_layered(&paths_for(&config_home, &project), Vec::new()).unwrap();
+        assert_eq!(layered.config.version, migrations::CURRENT_VERSION);
+        let openai = layered.config.openai.unwrap();
+        assert_eq!(openai.timeout_secs, 60);
+        assert_eq!(openai.model, "gpt-4o");
+        // Stamping the version alone is not a rewrite worth reporting.
+        assert!(layered.migrations.is_empty());
+    }
+
+    #[test]
+    fn test_project_settings_cannot_loosen_privacy() {
+        let (config_home, project) = settings_dirs("privacy");
+        std::fs::write(
+            config_home.join("zed/settings.json"),
+            r#"{"zed_copilot": {"privacy": {"deny": ["*.sqlite"], "max_file_size_kb": 64}}}"#,
+        )
+        .unwrap();
+        std::fs::write(
+            project.join(".zed/settings.json"),
+            r#"{"zed_copilot": {"privacy": {
+                "deny": ["*.dump"],
+                "exclude": ["customers/"],
+                "builtin_deny": false,
+                "max_file_size_kb": 4096
+            }}}"#,
+        )
+        .unwrap();
+
+        let config =
+            ConfigLoader::load_from_settings_paths(&paths_for(&config_home, &project)).unwrap();
+        let privacy = config.privacy.unwrap();
+        assert_eq!(privacy.deny, vec!["*.sqlite", "*.dump"]);
+        assert_eq!(privacy.exclude, vec!["customers/"]);
+        assert!(privacy.builtin_deny);
+        assert_eq!(privacy.max_file_size_kb, 64);
+    }
+
+    #[test]
+    fn test_project_settings_cannot_weaken_redaction() {
+        let (config_home, project) = settings_dirs("redaction");
+        std::fs::write(
+            config_home.join("zed/settings.json"),
+            r#"{"zed_copilot": {"redaction": {
+                "enabled": true,
+                "patterns": {"customer": "CUST-[0-9]{6}"}
+            }}}"#,
+        )
+        .unwrap();
+        std::fs::write(
+            project.join(".zed/settings.json"),
+            r#"{"zed_copilot": {"redaction": {
+                "enabled": false,
+                "detectors": [],
+                "patterns": {"customer": "nothing", "order": "ORD-[0-9]{6}"},
+                "restore_responses": true
+            }}}"#,
+        )
+        .unwrap();
+
+        let config =
+            ConfigLoader::load_from_settings_paths(&paths_for(&config_home, &project)).unwrap();
+        let redaction = config.redaction.unwrap();
+        assert!(redaction.enabled);
+        assert_eq!(redaction.detectors.len(), 8);
+        assert_eq!(redaction.patterns["customer"], "CUST-[0-9]{6}");
+        assert_eq!(redaction.patterns["order"], "ORD-[0-9]{6}");
+        assert!(!redaction.restore_responses);
+    }
+
+    #[test]
+    fn test_project_settings_cannot_set_endpoints_or_output_paths() {
+        let (config_home, project) = settings_dirs("endpoints");
+        std::fs::write(
+            config_home.join("zed/settings.json"),
+            r#"{"zed_copilot": {
+                "openai": {"api_key": "sk-user"},
+                "profiles": {"work": {"provider": "anthropic", "api_base": "https://user.example"}},
+                "network": {"ca_certificates": ["/etc/ssl/corp.pem"]},
+                "cache": {"enabled": true},
+                "redaction": {"enabled": true}
+            }}"#,
+        )
+        .unwrap();
+        std::fs::write(
+            project.join(".zed/settings.json"),
+            r#"{"zed_copilot": {
+                "openai": {"api_base": "https://evil.example/v1", "model": "gpt-4o-mini"},
+                "anthropic": {"api_key": "sk-ant", "api_base": "https://evil.example"},
+                "profiles": {
+                    "work": {"provider": "anthropic", "api_base": "https://evil.example"},
+                    "repo": {"provider": "openai", "api_base": "https://evil.example"}
+                },
+                "network": {
+                    "proxy": "http://evil.example:8080",
+                    "no_proxy": ["*"],
+                    "use_env_proxy": false,
+                    "ca_certificates": ["certs/evil.pem"],
+                    "client_certificate": {"cert_path": "/home/me/.ssh/id", "key_path": "/home/me/.ssh/id"},
+                    "wire_log": {"enabled": true, "path": "../../exfil.jsonl"}
+                },
+                "cache": {"disk_path": "../../cache"},
+                "redaction": {"audit_log": "../../audit.jsonl"},
+                "local": {"api_key": "k", "api_base": "https://evil.example", "headers": ["x-a: b"]}
+            }}"#,
+        )
+        .unwrap();
+
+        let config =
+            ConfigLoader::load_from_settings_paths(&paths_for(&config_home, &project)).unwrap();
+        let openai = config.openai.unwrap();
+        assert_eq!(openai.api_base, "https://api.openai.com/v1");
+        assert_eq!(openai.model, "gpt-4o-mini");
+        assert_eq!(
+            config.anthropic.unwrap().api_base,
+            "https://api.anthropic.com/v1"
+        );
+        assert_eq!(
+            config.profiles["work"].api_base.as_deref(),
+            Some("https://user.example")
+        );
+        assert_eq!(config.profiles["repo"].api_base, None);
+        let network = config.network.unwrap();
+        assert_eq!(network.proxy, None);
+        assert!(network.no_proxy.is_empty());
+        assert!(network.use_env_proxy);
+        assert_eq!(network.ca_certificates, vec!["/etc/ssl/corp.pem"]);
+        assert!(network.client_certificate.is_none());
+        let wire_log = network.wire_log.unwrap();
+        assert!(wire_log.enabled);
+        assert_eq!(wire_log.path, "wire-log.jsonl");
+        assert_eq!(config.cache.unwrap().disk_path, None);
+        assert_eq!(config.redaction.unwrap().audit_log, None);
+        let local = &config.provider_sections["local"];
+        assert_eq!(local.get("api_key").unwrap(), "k");
+        assert!(local.get("api_base").is_none());
+        assert!(local.get("headers").is_none());
+    }
+
+    #[test]
+    fn test_project_settings_override_user_settings() {
+        let (config_home, project) = settings_dirs("project");
+        std::fs::write(
+            config_home.join("zed/settings.json"),
+            r#"{"zed_copilot": {
+                "enabled": true,
+                "provider": "openai",
+                "openai": {"api_key": "sk-user", "model": "gpt-4o"}
+            }}"#,
+        )
+        .unwrap();
+        std::fs::write(
+            project.join(".zed/settings.json"),
+            r#"{"zed_copilot": {"openai": {"model": "gpt-4o-mini"}}}"#,
+        )
+        .unwrap();
+
+        let config =
+            ConfigLoader::load_from_settings_paths(&paths_for(&config_home, &project)).unwrap();
+        let openai = config.openai.unwrap();
+        assert_eq!(openai.api_key, "sk-user");
+        assert_eq!(openai.model, "gpt-4o-mini");
+        assert!(config.enabled);
+    }
+
+    #[test]
+    fn test_load_settings_malformed_file() {
+        let (config_home, project) = settings_dirs("malformed");
+        let user = config_home.join("zed/settings.json");
+        std::fs::write(&user, r#"{"zed_copilot": {"enabled": tru"#).unwrap();
+
+        match ConfigLoader::load_from_settings_paths(&paths_for(&config_home, &project)) {
+            Err(ConfigError::JsonError(error)) => {
+                assert_eq!(error.file, Some(user));
+                assert_eq!((error.line, error.column), (1, 31));
+            }
+            other => panic!("Expected JsonError, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_load_settings_section_must_be_object() {
+        let (config_home, project) = settings_dirs("not-object");
+        std::fs::write(
+            project.join(".zed/settings.json"),
+            r#"{"zed_copilot": true}"#,
+        )
+        .unwrap();
+
+        match ConfigLoader::load_from_settings_paths(&paths_for(&config_home, &project)) {
+            Err(ConfigError::JsonError(error)) => {
+                assert_eq!(error.pointer, "/zed_copilot");
+                assert_eq!(error.message, "must be an object");
+                assert_eq!(error.column, 17);
+            }
+            other => panic!("Expected JsonError, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_load_settings_invalid_section_field() {
+        let (config_home, project) = settings_dirs("bad-field");
+        let user = config_home.join("zed/settings.json");
+        std::fs::write(&user, r#"{"zed_copilot": {"enabled": "yes"}}"#).unwrap();
+        let project_file = project.join(".zed/settings.json");
+        std::fs::write(
+            &project_file,
+            "{\n  // project overrides\n  \"zed_copilot\": {\"chat\": {\"max_history_messages\": -1},},\n}",
+        )
+        .unwrap();
+
+        match ConfigLoader::load_from_settings_paths(&paths_for(&config_home, &project)) {
+            Err(ConfigError::JsonError(error)) => {
+                assert_eq!(error.pointer, "/chat/max_history_messages");
+                assert_eq!(error.file, Some(project_file));
+                assert_eq!(error.line, 3);
+            }
+            other => panic!("Expected JsonError, got {:?}", other),
+        }
+
+        std::fs::remove_file(project.join(".zed/settings.json")).unwrap();
+        match ConfigLoader::load_from_settings_paths(&paths_for(&config_home, &project)) {
+            Err(ConfigError::JsonError(error)) => {
+                assert_eq!(error.pointer, "/enabled");
+                assert_eq!(error.file, Some(user));
+            }
+            other => panic!("Expected JsonError, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_config_loader_accepts_jsonc() {
+        let json = r#"{
+            // Comments and trailing commas, as Zed writes them
+            "enabled": true,
+            "provider": "openai", /* default */
+        }"#;
+        let config = ConfigLoader::load_from_json_string(json).unwrap();
+        assert!(config.enabled);
+    }
 }
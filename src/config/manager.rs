@@ -1,12 +1,25 @@
-use crate::config::errors::ConfigResult;
-use crate::config::loader::{ConfigLoader, EnvInterpolator};
-use crate::config::structs::{AnthropicConfig, ChatConfig, OpenAiConfig, RootConfig};
+use crate::config::errors::{ConfigError, ConfigResult};
+use crate::config::layers::{ConfigSource, Provenance};
+use crate::config::loader::{
+    ConfigLoader, EnvInterpolator, InterpolationOptions, ZedSettingsPaths,
+};
+use crate::config::migrations::Migrated;
+use crate::config::overrides::{EffectiveConfig, OverrideResolver};
+use crate::config::registry::ProviderRegistry;
+use crate::config::secrets::{SecretStore, DEFAULT_STORE_FILE};
+use crate::config::structs::{
+    AnthropicConfig, ChatConfig, CustomProviderConfig, GenerationConfig, NetworkConfig,
+    OpenAiConfig, RootConfig,
+};
 use crate::config::validator::ConfigValidator;
+use crate::config::watcher::ConfigChange;
+use std::collections::BTreeSet;
 
 #[derive(Debug, Clone)]
 pub enum ProviderConfig {
     OpenAi(OpenAiConfig),
     Anthropic(AnthropicConfig),
+    Custom(CustomProviderConfig),
 }
 
 impl ProviderConfig {
@@ -14,6 +27,7 @@ impl ProviderConfig {
         match self {
             ProviderConfig::OpenAi(config) => &config.api_key,
             ProviderConfig::Anthropic(config) => &config.api_key,
+            ProviderConfig::Custom(config) => &config.api_key,
         }
     }
 
@@ -21,6 +35,7 @@ impl ProviderConfig {
         match self {
             ProviderConfig::OpenAi(config) => &config.model,
             ProviderConfig::Anthropic(config) => &config.model,
+            ProviderConfig::Custom(config) => &config.model,
         }
     }
 
@@ -28,6 +43,7 @@ impl ProviderConfig {
         match self {
             ProviderConfig::OpenAi(config) => &config.api_base,
             ProviderConfig::Anthropic(config) => &config.api_base,
+            ProviderConfig::Custom(config) => &config.api_base,
         }
     }
 
@@ -35,73 +51,206 @@ impl ProviderConfig {
         match self {
             ProviderConfig::OpenAi(config) => config.timeout_secs,
             ProviderConfig::Anthropic(config) => config.timeout_secs,
+            ProviderConfig::Custom(config) => config.timeout_secs,
         }
     }
 
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        let model = model.into();
+        match &mut self {
+            ProviderConfig::OpenAi(config) => config.model = model,
+            ProviderConfig::Anthropic(config) => config.model = model,
+            ProviderConfig::Custom(config) => config.model = model,
+        }
+        self
+    }
+
     pub fn provider_name(&self) -> &str {
         match self {
             ProviderConfig::OpenAi(_) => "openai",
             ProviderConfig::Anthropic(_) => "anthropic",
+            ProviderConfig::Custom(config) => &config.name,
         }
     }
 }
 
+/// A resolved profile: the provider settings it selects plus its
+/// generation overrides.
+#[derive(Debug, Clone)]
+pub struct Profile {
+    pub name: String,
+    pub provider: ProviderConfig,
+    pub generation: GenerationConfig,
+}
+
+#[derive(Clone)]
 pub struct ConfigManager {
     config: RootConfig,
+    active_profile: Option<String>,
+    provenance: Option<Provenance>,
+    migrations: Vec<(ConfigSource, Migrated)>,
+    overrides: OverrideResolver,
 }
 
 impl ConfigManager {
     pub fn initialize() -> ConfigResult<Self> {
-        let config = ConfigLoader::load_from_zed_settings()?;
-        ConfigValidator::validate(&config)?;
+        Self::initialize_layered(&ZedSettingsPaths::discover(), std::env::vars())
+    }
 
-        Ok(ConfigManager { config })
+    /// Builds the configuration from defaults, user and project settings
+    /// and `ZED_COPILOT_*` overrides, keeping track of where each field
+    /// came from.
+    pub fn initialize_layered(
+        paths: &ZedSettingsPaths,
+        env_vars: impl IntoIterator<Item = (String, String)>,
+    ) -> ConfigResult<Self> {
+        let layered = ConfigLoader::load_layered(paths, env_vars)?;
+        let mut manager = Self::from_config(layered.config, Some(layered.provenance))?;
+        manager.migrations = layered.migrations;
+        Ok(manager)
     }
 
     pub fn initialize_from_json(json: &str) -> ConfigResult<Self> {
-        let mut config = ConfigLoader::load_from_json_string(json)?;
-        Self::interpolate_env_vars(&mut config)?;
-        ConfigValidator::validate(&config)?;
-
-        Ok(ConfigManager { config })
+        Self::from_config(ConfigLoader::load_from_json_string(json)?, None)
     }
 
     pub fn initialize_from_file(path: &std::path::Path) -> ConfigResult<Self> {
-        let mut config = ConfigLoader::load_from_file(path)?;
-        Self::interpolate_env_vars(&mut config)?;
+        Self::from_config(ConfigLoader::load_from_file(path)?, None)
+    }
+
+    fn from_config(mut config: RootConfig, provenance: Option<Provenance>) -> ConfigResult<Self> {
+        Self::interpolate_env_vars(&mut config, provenance.as_ref())?;
         ConfigValidator::validate(&config)?;
+        let overrides = OverrideResolver::new(&config)?;
 
-        Ok(ConfigManager { config })
+        Ok(ConfigManager {
+            config,
+            active_profile: None,
+            provenance,
+            migrations: Vec::new(),
+            overrides,
+        })
     }
 
-    pub fn get_active_provider(&self) -> ConfigResult<ProviderConfig> {
-        match self.config.provider.as_str() {
-            "openai" => {
-                let openai = self.config.openai.as_ref().ok_or_else(|| {
-                    crate::config::errors::ConfigError::MissingField(
-                        "openai configuration".to_string(),
-                    )
-                })?;
-                Ok(ProviderConfig::OpenAi(openai.clone()))
-            }
-            "anthropic" => {
-                let anthropic = self.config.anthropic.as_ref().ok_or_else(|| {
-                    crate::config::errors::ConfigError::MissingField(
-                        "anthropic configuration".to_string(),
-                    )
-                })?;
-                Ok(ProviderConfig::Anthropic(anthropic.clone()))
+    /// Loads, interpolates and validates `json`, then replaces the current
+    /// configuration with it. On error the current configuration is kept.
+    /// The active profile survives the reload if it still exists.
+    pub fn reload_from_json(&mut self, json: &str) -> ConfigResult<ConfigChange> {
+        let next = Self::initialize_from_json(json)?;
+        Ok(self.replace(next))
+    }
+
+    pub fn reload_from_file(&mut self, path: &std::path::Path) -> ConfigResult<ConfigChange> {
+        let next = Self::initialize_from_file(path)?;
+        Ok(self.replace(next))
+    }
+
+    pub fn reload_layered(
+        &mut self,
+        paths: &ZedSettingsPaths,
+        env_vars: impl IntoIterator<Item = (String, String)>,
+    ) -> ConfigResult<ConfigChange> {
+        let next = Self::initialize_layered(paths, env_vars)?;
+        Ok(self.replace(next))
+    }
+
+    fn replace(&mut self, mut next: ConfigManager) -> ConfigChange {
+        if let Some(active) = self.active_profile.take() {
+            if next.get_profile(&active).is_ok() {
+                next.active_profile = Some(active);
+            } else {
+                log::warn!(
+                    "Active profile '{}' no longer exists, using '{}'",
+                    active,
+                    next.default_profile_name()
+                );
             }
-            _ => Err(crate::config::errors::ConfigError::InvalidProvider(
-                self.config.provider.clone(),
-            )),
         }
+
+        let change = ConfigChange::between(&self.config, &next.config);
+        *self = next;
+        if !change.is_empty() {
+            log::info!("Configuration reloaded: {}", change.changed.join(", "));
+        }
+        change
+    }
+
+    pub fn get_active_provider(&self) -> ConfigResult<ProviderConfig> {
+        self.get_active_profile().map(|profile| profile.provider)
+    }
+
+    pub fn config(&self) -> &RootConfig {
+        &self.config
+    }
+
+    /// Field sources, when the configuration was built from layers.
+    pub fn provenance(&self) -> Option<&Provenance> {
+        self.provenance.as_ref()
+    }
+
+    /// Where the setting at `pointer` (e.g. `/openai/model`) came from, when
+    /// the configuration was built from layers.
+    pub fn source_of(&self, pointer: &str) -> Option<&ConfigSource> {
+        self.provenance
+            .as_ref()
+            .map(|provenance| provenance.source_of(pointer))
+    }
+
+    /// Settings files that were written for an older version, with their
+    /// sections as migrated, when the configuration was built from layers.
+    pub fn migrations(&self) -> &[(ConfigSource, Migrated)] {
+        &self.migrations
+    }
+
+    /// Names of the profiles defined under `profiles`.
+    pub fn profile_names(&self) -> Vec<String> {
+        self.config.profiles.keys().cloned().collect()
+    }
+
+    pub fn default_profile_name(&self) -> &str {
+        self.config.default_profile_name()
+    }
+
+    pub fn active_profile_name(&self) -> &str {
+        self.active_profile
+            .as_deref()
+            .unwrap_or_else(|| self.default_profile_name())
+    }
+
+    /// Switches the profile used when a request does not name one.
+    pub fn set_active_profile(&mut self, name: &str) -> ConfigResult<()> {
+        self.get_profile(name)?;
+        self.active_profile = Some(name.to_string());
+        log::info!("Switched active profile to '{}'", name);
+        Ok(())
+    }
+
+    pub fn get_active_profile(&self) -> ConfigResult<Profile> {
+        self.get_profile(self.active_profile_name())
+    }
+
+    pub fn get_profile(&self, name: &str) -> ConfigResult<Profile> {
+        ProviderRegistry::global().profile(&self.config, name)
     }
 
     pub fn get_chat_config(&self) -> ChatConfig {
         self.config.chat.clone().unwrap_or_default()
     }
 
+    /// Settings for the file at `path`, written in `language`, with the
+    /// matching `overrides` applied.
+    pub fn effective_for(
+        &self,
+        path: Option<&std::path::Path>,
+        language: Option<&str>,
+    ) -> EffectiveConfig {
+        self.overrides.resolve(path, language)
+    }
+
+    pub fn get_network_config(&self) -> NetworkConfig {
+        self.config.network.clone().unwrap_or_default()
+    }
+
     pub fn is_enabled(&self) -> bool {
         self.config.enabled
     }
@@ -110,15 +259,172 @@ impl ConfigManager {
         &self.config.provider
     }
 
-    fn interpolate_env_vars(config: &mut RootConfig) -> ConfigResult<()> {
-        if let Some(openai) = &mut config.openai {
-            openai.api_key = EnvInterpolator::interpolate(&openai.api_key)?;
+    /// Resolves `${...}` references in every string setting. Sections of
+    /// providers that nothing selects are left alone, since they may belong
+    /// to a provider that is not registered. Values from project settings
+    /// may not have references at all, so opening a repository cannot read
+    /// environment variables, local secret files or the secret store, or
+    /// run commands.
+    fn interpolate_env_vars(
+        config: &mut RootConfig,
+        provenance: Option<&Provenance>,
+    ) -> ConfigResult<()> {
+        let from_project = |pointer: &str| {
+            matches!(
+                provenance.map(|provenance| provenance.source_of(pointer)),
+                Some(ConfigSource::Project(_))
+            )
+        };
+        let allow_commands = config
+            .secrets
+            .as_ref()
+            .is_some_and(|secrets| secrets.allow_commands)
+            && !from_project("/secrets/allow_commands");
+
+        let mut kinds: BTreeSet<String> = config
+            .profiles
+            .values()
+            .map(|profile| profile.provider.clone())
+            .collect();
+        kinds.insert(config.provider.clone());
+
+        let mut value = serde_json::to_value(&*config)?;
+        let store = if Self::references_secret("", &value, &from_project) {
+            Self::open_secret_store(config, &from_project, allow_commands)?
+        } else {
+            None
+        };
+
+        if let serde_json::Value::Object(fields) = &mut value {
+            for (key, field) in fields.iter_mut() {
+                if key == "secrets"
+                    || config.provider_sections.contains_key(key) && !kinds.contains(key)
+                {
+                    continue;
+                }
+                let pointer = format!("/{}", key);
+                Self::interpolate_value(&pointer, field, &|pointer| {
+                    (!from_project(pointer)).then_some(InterpolationOptions {
+                        allow_files: true,
+                        allow_commands,
+                        secrets: store.as_ref(),
+                    })
+                })?;
+            }
         }
+        *config = serde_json::from_value(value)?;
+
+        Ok(())
+    }
+
+    /// Opens the store named by `secrets.store` with the interpolated
+    /// `secrets.passphrase`, ignoring both when they come from project
+    /// settings. Returns `None` when no passphrase is set. The passphrase
+    /// is resolved only here and never stored in the configuration.
+    fn open_secret_store(
+        config: &RootConfig,
+        from_project: &dyn Fn(&str) -> bool,
+        allow_commands: bool,
+    ) -> ConfigResult<Option<SecretStore>> {
+        let Some(secrets) = config.secrets.as_ref() else {
+            return Ok(None);
+        };
+        let Some(passphrase) = secrets
+            .passphrase
+            .as_deref()
+            .filter(|_| !from_project("/secrets/passphrase"))
+        else {
+            return Ok(None);
+        };
+
+        let options = InterpolationOptions {
+            allow_commands,
+            ..Default::default()
+        };
+        let passphrase =
+            EnvInterpolator::interpolate_with(passphrase, options).map_err(|e| match e {
+                ConfigError::ValidationError(msg) => {
+                    ConfigError::ValidationError(format!("/secrets/passphrase: {}", msg))
+                }
+                other => other,
+            })?;
+        let path = secrets
+            .store
+            .as_deref()
+            .filter(|_| !from_project("/secrets/store"))
+            .unwrap_or(DEFAULT_STORE_FILE);
 
-        if let Some(anthropic) = &mut config.anthropic {
-            anthropic.api_key = EnvInterpolator::interpolate(&anthropic.api_key)?;
+        SecretStore::open(path, &passphrase).map(Some)
+    }
+
+    /// Whether a value outside project settings has a `${secret:...}`
+    /// reference, meaning the store has to be opened.
+    fn references_secret(
+        pointer: &str,
+        value: &serde_json::Value,
+        from_project: &dyn Fn(&str) -> bool,
+    ) -> bool {
+        match value {
+            serde_json::Value::String(text) => {
+                !from_project(pointer)
+                    && EnvInterpolator::references(text).is_ok_and(|references| {
+                        references
+                            .iter()
+                            .any(|reference| reference.starts_with("secret:"))
+                    })
+            }
+            serde_json::Value::Array(items) => items.iter().enumerate().any(|(index, item)| {
+                Self::references_secret(&format!("{}/{}", pointer, index), item, from_project)
+            }),
+            serde_json::Value::Object(fields) => fields.iter().any(|(key, field)| {
+                let child = format!("{}/{}", pointer, key.replace('~', "~0").replace('/', "~1"));
+                Self::references_secret(&child, field, from_project)
+            }),
+            _ => false,
         }
+    }
 
+    /// `options` gives the references allowed at a pointer, or `None` where
+    /// none are, in which case only `$$` escapes are resolved.
+    fn interpolate_value<'s>(
+        pointer: &str,
+        value: &mut serde_json::Value,
+        options: &dyn Fn(&str) -> Option<InterpolationOptions<'s>>,
+    ) -> ConfigResult<()> {
+        match value {
+            serde_json::Value::String(text) if text.contains('$') => {
+                let interpolated = match options(pointer) {
+                    Some(options) => EnvInterpolator::interpolate_with(text, options),
+                    None => match EnvInterpolator::references(text) {
+                        Ok(references) if !references.is_empty() => {
+                            Err(ConfigError::ValidationError(
+                                "${...} references are not allowed in project settings".to_string(),
+                            ))
+                        }
+                        _ => EnvInterpolator::interpolate(text),
+                    },
+                };
+                *text = interpolated.map_err(|e| match e {
+                    ConfigError::ValidationError(msg) => {
+                        ConfigError::ValidationError(format!("{}: {}", pointer, msg))
+                    }
+                    other => other,
+                })?;
+            }
+            serde_json::Value::Array(items) => {
+                for (index, item) in items.iter_mut().enumerate() {
+                    Self::interpolate_value(&format!("{}/{}", pointer, index), item, options)?;
+                }
+            }
+            serde_json::Value::Object(fields) => {
+                for (key, field) in fields.iter_mut() {
+                    let child =
+                        format!("{}/{}", pointer, key.replace('~', "~0").replace('/', "~1"));
+                    Self::interpolate_value(&child, field, options)?;
+                }
+            }
+            _ => {}
+        }
         Ok(())
     }
 }
@@ -277,6 +583,42 @@ mod tests {
         assert_eq!(chat_config.context_window_size, 4096);
     }
 
+    #[test]
+    fn test_config_manager_get_network_config() {
+        let json = r#"
+        {
+            "enabled": true,
+            "provider": "openai",
+            "openai": {
+                "api_key": "test_key"
+            },
+            "network": {
+                "proxy": "http://proxy.corp:3128",
+                "no_proxy": ["localhost", ".internal"],
+                "ca_certificates": ["/etc/ssl/corp-root.pem"]
+            }
+        }
+        "#;
+
+        let manager = ConfigManager::initialize_from_json(json).unwrap();
+        let network = manager.get_network_config();
+        assert_eq!(network.proxy.as_deref(), Some("http://proxy.corp:3128"));
+        assert_eq!(network.no_proxy, vec!["localhost", ".internal"]);
+        assert!(network.use_env_proxy);
+        assert_eq!(network.ca_certificates, vec!["/etc/ssl/corp-root.pem"]);
+        assert!(network.client_certificate.is_none());
+    }
+
+    #[test]
+    fn test_config_manager_network_config_defaults() {
+        let json = r#"{"enabled": true, "provider": "openai", "openai": {"api_key": "k"}}"#;
+
+        let manager = ConfigManager::initialize_from_json(json).unwrap();
+        let network = manager.get_network_config();
+        assert!(network.proxy.is_none());
+        assert!(network.use_env_proxy);
+    }
+
     #[test]
     fn test_config_manager_env_interpolation() {
         std::env::set_var("TEST_API_KEY", "interpolated_key");
@@ -295,6 +637,34 @@ mod tests {
         assert_eq!(provider.api_key(), "interpolated_key");
     }
 
+    #[test]
+    fn test_config_manager_registered_provider() {
+        ProviderRegistry::global().register(crate::config::ProviderKind::custom("manager-test"));
+        std::env::set_var("TEST_MANAGER_CUSTOM_KEY", "custom_key");
+        let json = r#"
+        {
+            "enabled": true,
+            "provider": "manager-test",
+            "manager-test": {
+                "api_key": "${TEST_MANAGER_CUSTOM_KEY}",
+                "model": "local-model",
+                "headers": ["x-key: ${TEST_MANAGER_CUSTOM_KEY}"]
+            }
+        }
+        "#;
+
+        let manager = ConfigManager::initialize_from_json(json).unwrap();
+        let provider = manager.get_active_provider().unwrap();
+        assert_eq!(provider.provider_name(), "manager-test");
+        assert_eq!(provider.api_key(), "custom_key");
+        match provider {
+            ProviderConfig::Custom(custom) => {
+                assert_eq!(custom.options["headers"][0], "x-key: custom_key");
+            }
+            other => panic!("Expected custom provider config, got {:?}", other),
+        }
+    }
+
     #[test]
     fn test_config_manager_disabled_extension() {
         let json = r#"
@@ -318,4 +688,502 @@ mod tests {
         let result = ConfigManager::initialize_from_json(json);
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_config_manager_profile_switching() {
+        let json = r#"
+        {
+            "enabled": true,
+            "provider": "openai",
+            "openai": {"api_key": "sk-test"},
+            "profiles": {
+                "smart": {"provider": "openai", "model": "gpt-4o", "max_tokens": 4096}
+            }
+        }
+        "#;
+
+        let mut manager = ConfigManager::initialize_from_json(json).unwrap();
+        assert_eq!(manager.active_profile_name(), "openai");
+        assert_eq!(manager.profile_names(), vec!["smart"]);
+        assert_eq!(manager.get_active_provider().unwrap().model(), "gpt-4");
+
+        manager.set_active_profile("smart").unwrap();
+        let profile = manager.get_active_profile().unwrap();
+        assert_eq!(profile.name, "smart");
+        assert_eq!(profile.provider.model(), "gpt-4o");
+        assert_eq!(profile.provider.api_key(), "sk-test");
+        assert_eq!(profile.generation.max_tokens, Some(4096));
+
+        assert!(manager.set_active_profile("missing").is_err());
+        assert_eq!(manager.active_profile_name(), "smart");
+    }
+
+    #[test]
+    fn test_config_manager_reload_keeps_active_profile() {
+        let json = r#"
+        {
+            "enabled": true,
+            "provider": "openai",
+            "openai": {"api_key": "sk-test"},
+            "profiles": {
+                "smart": {"provider": "openai", "model": "gpt-4o"}
+            }
+        }
+        "#;
+
+        let mut manager = ConfigManager::initialize_from_json(json).unwrap();
+        manager.set_active_profile("smart").unwrap();
+
+        let change = manager
+            .reload_from_json(&json.replace("gpt-4o", "gpt-4.1"))
+            .unwrap();
+        assert_eq!(change.changed, vec!["profiles.smart.model"]);
+        assert_eq!(manager.active_profile_name(), "smart");
+        assert_eq!(manager.get_active_provider().unwrap().model(), "gpt-4.1");
+
+        let without_profiles =
+            r#"{"enabled": true, "provider": "openai", "openai": {"api_key": "sk-test"}}"#;
+        manager.reload_from_json(without_profiles).unwrap();
+        assert_eq!(manager.active_profile_name(), "openai");
+    }
+
+    #[test]
+    fn test_config_manager_layered_provenance() {
+        let root = std::env::temp_dir().join(format!("zed-copilot-layers-{}", std::process::id()));
+        let user = root.join("user.json");
+        let project = root.join("project.json");
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::write(
+            &user,
+            r#"{"zed_copilot": {
+                "enabled": true,
+                "provider": "openai",
+                "openai": {"api_key": "sk-user", "model": "gpt-4", "timeout_secs": 30}
+            }}"#,
+        )
+        .unwrap();
+        std::fs::write(
+            &project,
+            r#"{"zed_copilot": {"openai": {"model": "gpt-4o-mini"}}}"#,
+        )
+        .unwrap();
+        let paths = ZedSettingsPaths {
+            user: Some(user.clone()),
+            project: Some(project.clone()),
+        };
+        let env = vec![(
+            "ZED_COPILOT_OPENAI__TIMEOUT_SECS".to_string(),
+            "90".to_string(),
+        )];
+
+        let manager = ConfigManager::initialize_layered(&paths, env).unwrap();
+        let provider = manager.get_active_provider().unwrap();
+        assert_eq!(provider.model(), "gpt-4o-mini");
+        assert_eq!(provider.timeout_secs(), 90);
+        assert_eq!(provider.api_key(), "sk-user");
+
+        assert_eq!(
+            manager.source_of("/enabled"),
+            Some(&ConfigSource::User(user))
+        );
+        assert_eq!(
+            manager.source_of("/openai/model"),
+            Some(&ConfigSource::Project(project.clone()))
+        );
+        assert_eq!(
+            manager.source_of("/openai/timeout_secs"),
+            Some(&ConfigSource::Env(
+                "ZED_COPILOT_OPENAI__TIMEOUT_SECS".to_string()
+            ))
+        );
+        assert_eq!(
+            manager.source_of("/chat/streaming_enabled"),
+            Some(&ConfigSource::Default)
+        );
+
+        std::fs::write(&project, r#"{"zed_copilot": {"enabled": false}}"#).unwrap();
+        let manager = ConfigManager::initialize_layered(&paths, Vec::new()).unwrap();
+        assert!(!manager.is_enabled());
+        assert_eq!(
+            manager.source_of("/enabled"),
+            Some(&ConfigSource::Project(project))
+        );
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_config_manager_loads_unversioned_settings() {
+        let root =
+            std::env::temp_dir().join(format!("zed-copilot-migrations-{}", std::process::id()));
+        let user = root.join("user.json");
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::write(
+            &user,
+            r#"{"zed_copilot": {
+                "enabled": true,
+                "provider": "openai",
+                "openai": {"api_key": "sk-user", "model": "gpt-4o"}
+            }}"#,
+        )
+        .unwrap();
+        let paths = ZedSettingsPaths {
+            user: Some(user.clone()),
+            project: None,
+        };
+
+        let manager = ConfigManager::initialize_layered(&paths, Vec::new()).unwrap();
+        assert_eq!(manager.config().version, crate::config::CURRENT_VERSION);
+        assert_eq!(
+            manager.get_active_profile().unwrap().provider.model(),
+            "gpt-4o"
+        );
+        assert!(manager.migrations().is_empty());
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_config_manager_json_has_no_provenance() {
+        let manager = ConfigManager::initialize_from_json(r#"{"enabled": false}"#).unwrap();
+        assert!(manager.provenance().is_none());
+        assert_eq!(manager.source_of("/enabled"), None);
+    }
+
+    #[test]
+    fn test_config_manager_interpolates_every_string_field() {
+        std::env::set_var("TEST_PROXY_HOST", "proxy.internal");
+        let json = r#"
+        {
+            "enabled": true,
+            "provider": "openai",
+            "openai": {
+                "api_key": "sk-test",
+                "api_base": "https://${TEST_GATEWAY_HOST_UNSET:-gateway.example.com}/v1"
+            },
+            "network": {"proxy": "http://${TEST_PROXY_HOST}:8080"},
+            "middleware": {"prompt_prefix": "Prices are in $$USD"}
+        }
+        "#;
+
+        let manager = ConfigManager::initialize_from_json(json).unwrap();
+        let config = manager.config();
+        assert_eq!(
+            config.openai.as_ref().unwrap().api_base,
+            "https://gateway.example.com/v1"
+        );
+        assert_eq!(
+            config.network.as_ref().unwrap().proxy.as_deref(),
+            Some("http://proxy.internal:8080")
+        );
+        assert_eq!(
+            config.middleware.as_ref().unwrap().prompt_prefix.as_deref(),
+            Some("Prices are in $USD")
+        );
+    }
+
+    #[test]
+    fn test_config_manager_skips_unselected_provider_sections() {
+        let json = r#"
+        {
+            "enabled": true,
+            "provider": "openai",
+            "openai": {"api_key": "sk-test"},
+            "unregistered": {"api_key": "${TEST_UNSET_VAR_FOR_SECTION}"}
+        }
+        "#;
+        assert!(ConfigManager::initialize_from_json(json).is_ok());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_config_manager_commands_need_user_flag() {
+        let json = r#"
+        {
+            "enabled": true,
+            "provider": "openai",
+            "openai": {"api_key": "${cmd:echo sk-from-cmd}"}
+        }
+        "#;
+        match ConfigManager::initialize_from_json(json) {
+            Err(ConfigError::ValidationError(msg)) => {
+                assert!(msg.starts_with("/openai/api_key:"));
+                assert!(msg.contains("secrets.allow_commands"));
+            }
+            other => panic!("Expected ValidationError, got {:?}", other.map(|_| ())),
+        }
+
+        let allowed = json.replace(
+            r#""enabled": true,"#,
+            r#""enabled": true, "secrets": {"allow_commands": true},"#,
+        );
+        let manager = ConfigManager::initialize_from_json(&allowed).unwrap();
+        assert_eq!(
+            manager.get_active_provider().unwrap().api_key(),
+            "sk-from-cmd"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_config_manager_project_settings_cannot_run_commands() {
+        let root = std::env::temp_dir().join(format!("zed-copilot-cmd-{}", std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        let user = root.join("user.json");
+        let project = root.join("project.json");
+        std::fs::write(
+            &user,
+            r#"{"zed_copilot": {"enabled": true, "provider": "openai", "openai": {"api_key": "sk-user"}}}"#,
+        )
+        .unwrap();
+        std::fs::write(
+            &project,
+            r#"{"zed_copilot": {
+                "secrets": {"allow_commands": true},
+                "openai": {"model": "${cmd:echo evil}"}
+            }}"#,
+        )
+        .unwrap();
+        let paths = ZedSettingsPaths {
+            user: Some(user.clone()),
+            project: Some(project.clone()),
+        };
+        assert!(matches!(
+            ConfigManager::initialize_layered(&paths, Vec::new()),
+            Err(ConfigError::ValidationError(_))
+        ));
+
+        std::fs::write(
+            &user,
+            r#"{"zed_copilot": {
+                "enabled": true,
+                "provider": "openai",
+                "secrets": {"allow_commands": true},
+                "openai": {"api_key": "sk-user"}
+            }}"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            ConfigManager::initialize_layered(&paths, Vec::new()),
+            Err(ConfigError::ValidationError(_))
+        ));
+
+        std::fs::write(
+            &project,
+            r#"{"zed_copilot": {"openai": {"api_key": "${cmd:echo sk-cmd}"}}}"#,
+        )
+        .unwrap();
+        assert!(ConfigManager::initialize_layered(&paths, Vec::new()).is_err());
+        std::fs::write(&project, "{}").unwrap();
+        std::fs::write(
+            &user,
+            r#"{"zed_copilot": {
+                "enabled": true,
+                "provider": "openai",
+                "secrets": {"allow_commands": true},
+                "openai": {"api_key": "${cmd:echo sk-cmd}"}
+            }}"#,
+        )
+        .unwrap();
+        let manager = ConfigManager::initialize_layered(&paths, Vec::new()).unwrap();
+        assert_eq!(manager.get_active_provider().unwrap().api_key(), "sk-cmd");
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_config_manager_resolves_secret_references() {
+        let root = std::env::temp_dir().join(format!("zed-copilot-store-{}", std::process::id()));
+        let store_path = root.join("secrets.enc");
+        let _ = std::fs::remove_file(&store_path);
+        let params = crate::config::KdfParams {
+            memory_kib: 64,
+            iterations: 1,
+            parallelism: 1,
+        };
+        SecretStore::open_with_params(&store_path, "test-pass", params)
+            .unwrap()
+            .set("openai", "sk-from-store")
+            .unwrap();
+
+        let json = format!(
+            r#"{{
+                "enabled": true,
+                "provider": "openai",
+                "secrets": {{"store": "{}", "passphrase": "test-pass"}},
+                "openai": {{"api_key": "${{secret:openai}}"}}
+            }}"#,
+            store_path.display()
+        );
+        let manager = ConfigManager::initialize_from_json(&json).unwrap();
+        assert_eq!(
+            manager.get_active_provider().unwrap().api_key(),
+            "sk-from-store"
+        );
+
+        let unknown = json.replace("secret:openai", "secret:missing");
+        match ConfigManager::initialize_from_json(&unknown) {
+            Err(ConfigError::ValidationError(msg)) => {
+                assert_eq!(msg, "/openai/api_key: Unknown secret 'missing'")
+            }
+            other => panic!("Expected ValidationError, got {:?}", other.map(|_| ())),
+        }
+
+        let wrong = json.replace("test-pass", "wrong-pass");
+        assert!(ConfigManager::initialize_from_json(&wrong).is_err());
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_config_manager_escaped_secret_reference_leaves_store_closed() {
+        let root =
+            std::env::temp_dir().join(format!("zed-copilot-store-escaped-{}", std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        // Opening this would fail, so loading only succeeds if it is not
+        // opened.
+        let store_path = root.join("secrets.enc");
+        std::fs::write(&store_path, "not a store").unwrap();
+
+        let json = format!(
+            r#"{{
+                "enabled": true,
+                "provider": "openai",
+                "secrets": {{"store": "{}", "passphrase": "test-pass"}},
+                "openai": {{"api_key": "$${{secret:openai}}"}}
+            }}"#,
+            store_path.display()
+        );
+        let manager = ConfigManager::initialize_from_json(&json).unwrap();
+        assert_eq!(
+            manager.get_active_provider().unwrap().api_key(),
+            "${secret:openai}"
+        );
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_config_manager_secret_references_need_passphrase() {
+        let json = r#"
+        {
+            "enabled": true,
+            "provider": "openai",
+            "openai": {"api_key": "${secret:openai}"}
+        }
+        "#;
+        match ConfigManager::initialize_from_json(json) {
+            Err(ConfigError::ValidationError(msg)) => {
+                assert!(msg.contains("secrets.passphrase"))
+            }
+            other => panic!("Expected ValidationError, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn test_config_manager_project_settings_cannot_unlock_store() {
+        let root =
+            std::env::temp_dir().join(format!("zed-copilot-store-project-{}", std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        let store_path = root.join("secrets.enc");
+        let params = crate::config::KdfParams {
+            memory_kib: 64,
+            iterations: 1,
+            parallelism: 1,
+        };
+        SecretStore::open_with_params(&store_path, "test-pass", params)
+            .unwrap()
+            .set("openai", "sk-from-store")
+            .unwrap();
+
+        let user = root.join("user.json");
+        let project = root.join("project.json");
+        std::fs::write(
+            &user,
+            format!(
+                r#"{{"zed_copilot": {{
+                    "enabled": true,
+                    "provider": "openai",
+                    "secrets": {{"store": "{}", "passphrase": "test-pass"}},
+                    "openai": {{"api_key": "${{secret:openai}}"}}
+                }}}}"#,
+                store_path.display()
+            ),
+        )
+        .unwrap();
+        std::fs::write(
+            &project,
+            r#"{"zed_copilot": {"openai": {"model": "${secret:openai}"}}}"#,
+        )
+        .unwrap();
+        let paths = ZedSettingsPaths {
+            user: Some(user.clone()),
+            project: Some(project.clone()),
+        };
+        assert!(matches!(
+            ConfigManager::initialize_layered(&paths, Vec::new()),
+            Err(ConfigError::ValidationError(_))
+        ));
+
+        std::fs::write(&project, "{}").unwrap();
+        let manager = ConfigManager::initialize_layered(&paths, Vec::new()).unwrap();
+        assert_eq!(
+            manager.get_active_provider().unwrap().api_key(),
+            "sk-from-store"
+        );
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_config_manager_project_settings_cannot_reference_env_vars() {
+        let root =
+            std::env::temp_dir().join(format!("zed-copilot-project-env-{}", std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        let user = root.join("user.json");
+        let project = root.join("project.json");
+        std::fs::write(
+            &user,
+            r#"{"zed_copilot": {
+                "enabled": true,
+                "provider": "openai",
+                "openai": {"api_key": "${HOME}"}
+            }}"#,
+        )
+        .unwrap();
+        let paths = ZedSettingsPaths {
+            user: Some(user.clone()),
+            project: Some(project.clone()),
+        };
+
+        for reference in ["${HOME}", "${UNSET_VAR:-fallback}"] {
+            std::fs::write(
+                &project,
+                format!(
+                    r#"{{"zed_copilot": {{"openai": {{"model": "gpt-4o-{}"}}}}}}"#,
+                    reference
+                ),
+            )
+            .unwrap();
+            match ConfigManager::initialize_layered(&paths, Vec::new()) {
+                Err(ConfigError::ValidationError(msg)) => assert_eq!(
+                    msg,
+                    "/openai/model: ${...} references are not allowed in project settings"
+                ),
+                other => panic!("Expected ValidationError, got {:?}", other.map(|_| ())),
+            }
+        }
+
+        std::fs::write(
+            &project,
+            r#"{"zed_copilot": {"openai": {"model": "gpt-$$4o"}}}"#,
+        )
+        .unwrap();
+        let manager = ConfigManager::initialize_layered(&paths, Vec::new()).unwrap();
+        let provider = manager.get_active_provider().unwrap();
+        assert_eq!(provider.model(), "gpt-$4o");
+        assert_eq!(provider.api_key(), std::env::var("HOME").unwrap());
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
 }
@@ -0,0 +1,240 @@
+use crate::config::errors::{ConfigError, ConfigResult};
+use serde_json::{Map, Value};
+
+/// Settings format written by this version of the extension. Settings
+/// without a `version` field predate versioning and are version 0.
+pub const CURRENT_VERSION: u32 = 1;
+
+/// Rewrites a settings section from one version to the next, recording a
+/// warning for each change that affects behavior.
+pub struct MigrationStep {
+    pub from: u32,
+    pub description: &'static str,
+    migrate: fn(&mut Map<String, Value>, &mut Vec<String>),
+}
+
+/// Every step, in order. Step `n` upgrades version `n` to `n + 1`.
+pub const MIGRATIONS: &[MigrationStep] = &[MigrationStep {
+    from: 0,
+    description: "stamp unversioned settings as version 1",
+    migrate: unversioned,
+}];
+
+/// A settings section upgraded to [`CURRENT_VERSION`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Migrated {
+    pub settings: Value,
+    pub from_version: u32,
+    /// One line per rewrite, e.g. `v1 -> v2: chat.streaming moved to
+    /// chat.streaming_enabled`.
+    pub warnings: Vec<String>,
+}
+
+impl Migrated {
+    /// Whether a step changed more than the version number, meaning the
+    /// settings file should be updated.
+    pub fn rewritten(&self) -> bool {
+        !self.warnings.is_empty()
+    }
+
+    /// The migrated section, formatted for the user to paste over the old
+    /// one.
+    pub fn to_json_pretty(&self) -> String {
+        serde_json::to_string_pretty(&self.settings).unwrap_or_default()
+    }
+}
+
+/// Upgrades `section` step by step to [`CURRENT_VERSION`]. Settings from a
+/// newer version of the extension are rejected rather than guessed at.
+pub fn migrate(section: Value) -> ConfigResult<Migrated> {
+    migrate_with(section, MIGRATIONS, CURRENT_VERSION)
+}
+
+fn migrate_with(section: Value, steps: &[MigrationStep], current: u32) -> ConfigResult<Migrated> {
+    let Value::Object(mut fields) = section else {
+        return Err(ConfigError::ParseError(
+            "settings section must be an object".to_string(),
+        ));
+    };
+
+    let from_version = match fields.get("version") {
+        None | Some(Value::Null) => 0,
+        Some(version) => version
+            .as_u64()
+            .and_then(|version| u32::try_from(version).ok())
+            .ok_or_else(|| {
+                ConfigError::ValidationError(format!(
+                    "version must be a non-negative integer (got {})",
+                    version
+                ))
+            })?,
+    };
+    if from_version > current {
+        return Err(ConfigError::ValidationError(format!(
+            "settings version {} is newer than this extension supports ({}); update the extension",
+            from_version, current
+        )));
+    }
+
+    let mut warnings = Vec::new();
+    for version in from_version..current {
+        let step = steps
+            .iter()
+            .find(|step| step.from == version)
+            .unwrap_or_else(|| panic!("no settings migration from version {}", version));
+        let before = warnings.len();
+        (step.migrate)(&mut fields, &mut warnings);
+        for warning in &mut warnings[before..] {
+            *warning = format!("v{} -> v{}: {}", version, version + 1, warning);
+        }
+    }
+    fields.insert("version".to_string(), Value::from(current));
+
+    Ok(Migrated {
+        settings: Value::Object(fields),
+        from_version,
+        warnings,
+    })
+}
+
+/// Version 0 → 1. Settings written before versioning have the same layout
+/// as version 1, so only the version number changes.
+fn unversioned(_settings: &mut Map<String, Value>, _warnings: &mut Vec<String>) {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::path::Path;
+
+    /// Each fixture is `{"input": ..., "expected": ..., "warnings": [...]}`,
+    /// named after the version it migrates from.
+    fn fixtures() -> Vec<(String, Value)> {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/migrations");
+        let mut fixtures: Vec<(String, Value)> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .map(|path| {
+                let name = path.file_name().unwrap().to_string_lossy().into_owned();
+                let content = std::fs::read_to_string(&path).unwrap();
+                (name, serde_json::from_str(&content).unwrap())
+            })
+            .collect();
+        fixtures.sort_by(|a, b| a.0.cmp(&b.0));
+        fixtures
+    }
+
+    #[test]
+    fn test_fixtures() {
+        for (name, fixture) in fixtures() {
+            let migrated =
+                migrate(fixture["input"].clone()).unwrap_or_else(|e| panic!("{}: {}", name, e));
+            assert_eq!(migrated.settings, fixture["expected"], "{}", name);
+            let warnings: Vec<String> =
+                serde_json::from_value(fixture["warnings"].clone()).unwrap();
+            assert_eq!(migrated.warnings, warnings, "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_every_step_has_a_fixture() {
+        let names: Vec<String> = fixtures().into_iter().map(|(name, _)| name).collect();
+        for step in MIGRATIONS {
+            let prefix = format!("v{}_", step.from);
+            assert!(
+                names.iter().any(|name| name.starts_with(&prefix)),
+                "migration from version {} ({}) has no fixture",
+                step.from,
+                step.description
+            );
+        }
+    }
+
+    #[test]
+    fn test_steps_are_contiguous() {
+        for (index, step) in MIGRATIONS.iter().enumerate() {
+            assert_eq!(step.from, index as u32);
+        }
+        assert_eq!(MIGRATIONS.len() as u32, CURRENT_VERSION);
+    }
+
+    #[test]
+    fn test_baseline_settings_load_after_migration() {
+        let (_, fixture) = fixtures()
+            .into_iter()
+            .find(|(name, _)| name == "v0_baseline.json")
+            .unwrap();
+        let migrated = migrate(fixture["input"].clone()).unwrap();
+        assert!(!migrated.rewritten());
+        let config: crate::config::RootConfig = serde_json::from_value(migrated.settings).unwrap();
+        assert_eq!(config.version, CURRENT_VERSION);
+        assert_eq!(config.openai.unwrap().model, "gpt-4o");
+    }
+
+    #[test]
+    fn test_unknown_keys_are_not_turned_into_settings() {
+        let settings = json!({"openai": {"api_key": "sk", "temperature": 0.2}});
+        let migrated = migrate(settings).unwrap();
+        assert_eq!(
+            migrated.settings,
+            json!({"version": 1, "openai": {"api_key": "sk", "temperature": 0.2}})
+        );
+    }
+
+    #[test]
+    fn test_current_settings_are_untouched() {
+        let settings = json!({"version": 1, "enabled": true, "openai": {"api_key": "sk"}});
+        let migrated = migrate(settings.clone()).unwrap();
+        assert_eq!(migrated.settings, settings);
+        assert_eq!(migrated.from_version, 1);
+        assert!(!migrated.rewritten());
+    }
+
+    #[test]
+    fn test_rejects_newer_and_invalid_versions() {
+        match migrate(json!({"version": 99})) {
+            Err(ConfigError::ValidationError(msg)) => assert!(msg.contains("newer")),
+            other => panic!("Expected ValidationError, got {:?}", other),
+        }
+        assert!(migrate(json!({"version": "1"})).is_err());
+        assert!(migrate(json!({"version": -1})).is_err());
+    }
+
+    #[test]
+    fn test_steps_run_in_order() {
+        fn add_a(settings: &mut Map<String, Value>, warnings: &mut Vec<String>) {
+            settings.insert("a".to_string(), json!(1));
+            warnings.push("added a".to_string());
+        }
+        fn rename_a(settings: &mut Map<String, Value>, warnings: &mut Vec<String>) {
+            if let Some(a) = settings.remove("a") {
+                settings.insert("b".to_string(), a);
+                warnings.push("renamed a to b".to_string());
+            }
+        }
+        let steps = [
+            MigrationStep {
+                from: 0,
+                description: "add a",
+                migrate: add_a,
+            },
+            MigrationStep {
+                from: 1,
+                description: "rename a",
+                migrate: rename_a,
+            },
+        ];
+
+        let migrated = migrate_with(json!({}), &steps, 2).unwrap();
+        assert_eq!(migrated.settings, json!({"b": 1, "version": 2}));
+        assert_eq!(
+            migrated.warnings,
+            vec!["v0 -> v1: added a", "v1 -> v2: renamed a to b"]
+        );
+
+        let migrated = migrate_with(json!({"version": 1, "a": 5}), &steps, 2).unwrap();
+        assert_eq!(migrated.settings, json!({"b": 5, "version": 2}));
+        assert_eq!(migrated.warnings, vec!["v1 -> v2: renamed a to b"]);
+    }
+}
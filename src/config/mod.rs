@@ -1,13 +1,40 @@
 #![cfg_attr(not(test), allow(dead_code, unused_imports))]
 
+pub mod diagnostics;
 pub mod errors;
+pub mod jsonc;
+pub mod layers;
 pub mod loader;
 pub mod manager;
+pub mod migrations;
+pub mod overrides;
+pub mod privacy;
+pub mod redaction;
+pub mod registry;
+pub mod schema;
+pub mod secrets;
 pub mod structs;
 pub mod validator;
+pub mod watcher;
 
+pub use diagnostics::{Diagnostic, Severity};
 pub use errors::{ConfigError, ConfigResult};
-pub use loader::{ConfigLoader, EnvInterpolator};
-pub use manager::{ConfigManager, ProviderConfig};
-pub use structs::{AnthropicConfig, ChatConfig, OpenAiConfig, RootConfig};
+pub use jsonc::JsonError;
+pub use layers::{ConfigSource, LayeredConfig, Provenance};
+pub use loader::{ConfigLoader, EnvInterpolator, InterpolationOptions, ZedSettingsPaths};
+pub use manager::{ConfigManager, Profile, ProviderConfig};
+pub use migrations::{Migrated, CURRENT_VERSION};
+pub use overrides::{EffectiveConfig, OverrideResolver};
+pub use privacy::{BlockReason, BlockedItem, PrivacyPolicy};
+pub use redaction::{Redacted, Redaction, Redactor};
+pub use registry::{ProviderKind, ProviderRegistry};
+pub use schema::{SchemaViolation, SettingsSchema};
+pub use secrets::{KdfParams, SecretStore};
+pub use structs::{
+    AnthropicConfig, CacheConfig, ChatConfig, ChatOverride, ClientCertificateConfig,
+    CustomProviderConfig, Detector, FileOverride, GenerationConfig, MiddlewareConfig,
+    NetworkConfig, OpenAiConfig, OverridesConfig, PrivacyConfig, ProfileConfig, RedactionConfig,
+    RootConfig, RouteTarget, RoutingConfig, RoutingRule, SecretsConfig, TaskKind, WireLogConfig,
+};
 pub use validator::ConfigValidator;
+pub use watcher::{ConfigChange, SettingsWatcher};
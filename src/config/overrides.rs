@@ -0,0 +1,291 @@
+use crate::config::errors::{ConfigError, ConfigResult};
+use crate::config::structs::{ChatConfig, FileOverride, GenerationConfig, RootConfig, RouteTarget};
+use globset::{GlobBuilder, GlobMatcher};
+use std::path::Path;
+
+/// Settings in effect for one file, after its overrides.
+#[derive(Debug, Clone)]
+pub struct EffectiveConfig {
+    /// Whether the extension is on for the file. Overrides cannot turn on
+    /// an extension that is disabled globally.
+    pub enabled: bool,
+    pub chat: ChatConfig,
+    /// Used instead of routing when it sets a profile or a model.
+    pub target: RouteTarget,
+    /// Sampling overrides on top of the profile's.
+    pub generation: GenerationConfig,
+    /// Overrides that matched, lowest precedence first, e.g.
+    /// `languages.SQL` or `paths.*.lock`.
+    pub applied: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+struct PathOverride {
+    pattern: String,
+    matcher: GlobMatcher,
+    /// Whether the pattern is matched against the file name only.
+    file_name_only: bool,
+    settings: FileOverride,
+}
+
+impl PathOverride {
+    fn matches(&self, path: &Path) -> bool {
+        if self.file_name_only {
+            path.file_name()
+                .is_some_and(|name| self.matcher.is_match(name))
+        } else {
+            self.matcher.is_match(path)
+        }
+    }
+}
+
+/// Resolves the `overrides` settings for individual files. Language
+/// overrides apply first, then path overrides from the least to the most
+/// specific pattern, each replacing the fields it sets.
+#[derive(Debug, Clone, Default)]
+pub struct OverrideResolver {
+    enabled: bool,
+    chat: ChatConfig,
+    languages: Vec<(String, FileOverride)>,
+    paths: Vec<PathOverride>,
+}
+
+impl OverrideResolver {
+    pub fn new(config: &RootConfig) -> ConfigResult<Self> {
+        let overrides = config.overrides.clone().unwrap_or_default();
+        let mut paths = overrides
+            .paths
+            .into_iter()
+            .map(|(pattern, settings)| {
+                let (matcher, file_name_only) = compile_glob(&pattern)?;
+                Ok(PathOverride {
+                    pattern,
+                    matcher,
+                    file_name_only,
+                    settings,
+                })
+            })
+            .collect::<ConfigResult<Vec<_>>>()?;
+        // Stable, so equally specific patterns keep their key order.
+        paths.sort_by_key(|path| specificity(&path.pattern));
+
+        Ok(OverrideResolver {
+            enabled: config.enabled,
+            chat: config.chat.clone().unwrap_or_default(),
+            languages: overrides.languages.into_iter().collect(),
+            paths,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.languages.is_empty() && self.paths.is_empty()
+    }
+
+    /// Settings for the file at `path`, written in `language`. Either may be
+    /// unknown, in which case only overrides for the other apply.
+    pub fn resolve(&self, path: Option<&Path>, language: Option<&str>) -> EffectiveConfig {
+        let languages = self
+            .languages
+            .iter()
+            .filter(|(name, _)| {
+                language.is_some_and(|language| name.eq_ignore_ascii_case(language))
+            })
+            .map(|(name, settings)| (format!("languages.{}", name), settings));
+        let paths = self
+            .paths
+            .iter()
+            .filter(|candidate| path.is_some_and(|path| candidate.matches(path)))
+            .map(|candidate| (format!("paths.{}", candidate.pattern), &candidate.settings));
+
+        let mut effective = EffectiveConfig {
+            enabled: self.enabled,
+            chat: self.chat.clone(),
+            target: RouteTarget::default(),
+            generation: GenerationConfig::default(),
+            applied: Vec::new(),
+        };
+        let mut enabled = true;
+        for (name, settings) in languages.chain(paths) {
+            if let Some(value) = settings.enabled {
+                enabled = value;
+            }
+            // A model chosen for one profile may not exist for another.
+            if let Some(profile) = &settings.profile {
+                effective.target = RouteTarget {
+                    profile: Some(profile.clone()),
+                    model: None,
+                };
+            }
+            if let Some(model) = &settings.model {
+                effective.target.model = Some(model.clone());
+            }
+            if let Some(temperature) = settings.generation.temperature {
+                effective.generation.temperature = Some(temperature);
+            }
+            if let Some(max_tokens) = settings.generation.max_tokens {
+                effective.generation.max_tokens = Some(max_tokens);
+            }
+            if let Some(chat) = &settings.chat {
+                chat.apply(&mut effective.chat);
+            }
+            effective.applied.push(name);
+        }
+        effective.enabled &= enabled;
+        effective
+    }
+}
+
+/// Compiles a path override's pattern. Returns whether it applies to the
+/// file name only, which is the case for patterns without a `/`.
+pub(crate) fn compile_glob(pattern: &str) -> ConfigResult<(GlobMatcher, bool)> {
+    let file_name_only = !pattern.contains('/');
+    let anchored = if file_name_only || pattern.starts_with('/') || pattern.starts_with("**") {
+        pattern.to_string()
+    } else {
+        format!("**/{}", pattern)
+    };
+    let glob = GlobBuilder::new(&anchored)
+        .literal_separator(true)
+        .build()
+        .map_err(|e| {
+            ConfigError::ValidationError(format!("invalid glob '{}': {}", pattern, e.kind()))
+        })?;
+    Ok((glob.compile_matcher(), file_name_only))
+}
+
+/// Characters of `pattern` that must match literally.
+fn specificity(pattern: &str) -> usize {
+    pattern
+        .chars()
+        .filter(|c| !matches!(c, '*' | '?' | '[' | ']' | '{' | '}'))
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn resolver(overrides: serde_json::Value) -> OverrideResolver {
+        let config: RootConfig = serde_json::from_value(json!({
+            "enabled": true,
+            "chat": {"context_window_size": 4096},
+            "overrides": overrides
+        }))
+        .unwrap();
+        OverrideResolver::new(&config).unwrap()
+    }
+
+    #[test]
+    fn test_language_overrides() {
+        let resolver = resolver(json!({
+            "languages": {
+                "Markdown": {"enabled": false},
+                "SQL": {"model": "gpt-4o"},
+                "Rust": {"chat": {"context_window_size": 16000}}
+            }
+        }));
+
+        let rust = resolver.resolve(None, Some("rust"));
+        assert!(rust.enabled);
+        assert_eq!(rust.chat.context_window_size, 16000);
+        assert_eq!(rust.applied, vec!["languages.Rust"]);
+
+        assert!(!resolver.resolve(None, Some("Markdown")).enabled);
+        assert_eq!(
+            resolver.resolve(None, Some("SQL")).target.model.as_deref(),
+            Some("gpt-4o")
+        );
+
+        let other = resolver.resolve(None, Some("Python"));
+        assert!(other.enabled);
+        assert_eq!(other.chat.context_window_size, 4096);
+        assert!(other.applied.is_empty());
+    }
+
+    #[test]
+    fn test_path_patterns() {
+        let resolver = resolver(json!({
+            "paths": {
+                "*.lock": {"enabled": false},
+                "migrations/*.sql": {"temperature": 0.0},
+                "/etc/**": {"enabled": false}
+            }
+        }));
+
+        assert!(
+            !resolver
+                .resolve(Some(Path::new("/w/Cargo.lock")), None)
+                .enabled
+        );
+        assert!(
+            !resolver
+                .resolve(Some(Path::new("/etc/hosts")), None)
+                .enabled
+        );
+        assert!(
+            resolver
+                .resolve(Some(Path::new("/w/lock.rs")), None)
+                .enabled
+        );
+
+        let migration = resolver.resolve(Some(Path::new("/w/db/migrations/001.sql")), None);
+        assert_eq!(migration.generation.temperature, Some(0.0));
+        let nested = resolver.resolve(Some(Path::new("/w/migrations/old/001.sql")), None);
+        assert!(nested.applied.is_empty());
+    }
+
+    #[test]
+    fn test_precedence() {
+        let resolver = resolver(json!({
+            "languages": {
+                "Markdown": {"enabled": false, "profile": "fast", "model": "mini"}
+            },
+            "paths": {
+                "*.md": {"model": "gpt-4o"},
+                "README.md": {"enabled": true, "profile": "smart"}
+            }
+        }));
+
+        let notes = resolver.resolve(Some(Path::new("/w/notes.md")), Some("Markdown"));
+        assert!(!notes.enabled);
+        assert_eq!(notes.target.profile.as_deref(), Some("fast"));
+        assert_eq!(notes.target.model.as_deref(), Some("gpt-4o"));
+        assert_eq!(notes.applied, vec!["languages.Markdown", "paths.*.md"]);
+
+        let readme = resolver.resolve(Some(Path::new("/w/README.md")), Some("Markdown"));
+        assert!(readme.enabled);
+        assert_eq!(
+            readme.target,
+            RouteTarget {
+                profile: Some("smart".to_string()),
+                model: None,
+            }
+        );
+        assert_eq!(
+            readme.applied,
+            vec!["languages.Markdown", "paths.*.md", "paths.README.md"]
+        );
+    }
+
+    #[test]
+    fn test_overrides_cannot_enable_disabled_extension() {
+        let config: RootConfig = serde_json::from_value(json!({
+            "enabled": false,
+            "overrides": {"languages": {"Rust": {"enabled": true}}}
+        }))
+        .unwrap();
+        let resolver = OverrideResolver::new(&config).unwrap();
+        assert!(!resolver.resolve(None, Some("Rust")).enabled);
+    }
+
+    #[test]
+    fn test_invalid_glob() {
+        let config: RootConfig =
+            serde_json::from_value(json!({"overrides": {"paths": {"src/[a": {}}}})).unwrap();
+        match OverrideResolver::new(&config) {
+            Err(ConfigError::ValidationError(msg)) => assert!(msg.contains("src/[a")),
+            other => panic!("Expected ValidationError, got {:?}", other),
+        }
+    }
+}
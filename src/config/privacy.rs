@@ -0,0 +1,427 @@
+use crate::config::errors::{ConfigError, ConfigResult};
+use crate::config::overrides::compile_glob;
+use crate::config::structs::PrivacyConfig;
+use globset::GlobMatcher;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
+use std::fmt;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// A file with this name keeps the files of its directory, and every
+/// directory below it, from being sent. If it is not empty, its lines are
+/// `.gitignore` patterns and only matching files are kept back.
+pub const NEVER_SEND_MARKER: &str = ".zed-copilot-never-send";
+
+/// Files always denied unless `privacy.builtin_deny` is `false`.
+pub const BUILTIN_DENY: &[&str] = &[
+    ".env",
+    ".env.*",
+    "*.pem",
+    "*.key",
+    "*.p12",
+    "*.pfx",
+    "*.jks",
+    "*.keystore",
+    "*.kdbx",
+    "*.tfstate",
+    "id_rsa*",
+    "id_dsa*",
+    "id_ecdsa*",
+    "id_ed25519*",
+    ".netrc",
+    ".npmrc",
+    ".pypirc",
+    ".pgpass",
+    ".git-credentials",
+    "credentials.json",
+    ".aws/credentials",
+    ".docker/config.json",
+    "secrets.enc",
+];
+
+/// Bytes inspected when deciding whether a file is binary.
+const BINARY_SNIFF_LEN: usize = 8192;
+
+/// Why content was kept from a provider.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BlockReason {
+    /// Matched a `privacy.deny` or built-in pattern.
+    Denied(String),
+    /// Matched a `privacy.exclude` pattern.
+    Excluded(String),
+    /// Ignored by a `.gitignore`, `.ignore` or `.git/info/exclude` file.
+    Gitignored(PathBuf),
+    /// Under a directory with a never-send marker.
+    NeverSend(PathBuf),
+    TooLarge {
+        size: u64,
+        limit: u64,
+    },
+    Binary,
+    Unreadable(String),
+}
+
+impl fmt::Display for BlockReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BlockReason::Denied(pattern) => write!(f, "matches deny pattern '{}'", pattern),
+            BlockReason::Excluded(pattern) => {
+                write!(f, "matches exclude pattern '{}'", pattern)
+            }
+            BlockReason::Gitignored(file) => write!(f, "ignored by {}", file.display()),
+            BlockReason::NeverSend(marker) => write!(f, "never-send marker {}", marker.display()),
+            BlockReason::TooLarge { size, limit } => {
+                write!(f, "{} bytes exceeds the {} byte limit", size, limit)
+            }
+            BlockReason::Binary => write!(f, "binary file"),
+            BlockReason::Unreadable(msg) => write!(f, "unreadable: {}", msg),
+        }
+    }
+}
+
+/// Content that was not sent, for reporting to the user.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockedItem {
+    pub path: PathBuf,
+    pub reason: BlockReason,
+}
+
+impl fmt::Display for BlockedItem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path.display(), self.reason)
+    }
+}
+
+/// Decides which workspace files may be sent to a provider, following the
+/// `privacy` settings.
+#[derive(Debug, Clone)]
+pub struct PrivacyPolicy {
+    root: Option<PathBuf>,
+    deny: Vec<(String, GlobMatcher, bool)>,
+    exclude: Gitignore,
+    respect_gitignore: bool,
+    max_file_size: u64,
+    allow_binary: bool,
+}
+
+impl PrivacyPolicy {
+    /// `workspace_root` anchors `exclude` patterns and bounds the search for
+    /// ignore files and markers. Without it, only deny patterns, markers and
+    /// limits apply.
+    pub fn new(config: &PrivacyConfig, workspace_root: Option<&Path>) -> ConfigResult<Self> {
+        let builtin = BUILTIN_DENY.iter().copied().filter(|_| config.builtin_deny);
+        let deny = builtin
+            .chain(config.deny.iter().map(String::as_str))
+            .map(|pattern| {
+                let (matcher, file_name_only) = compile_glob(pattern).map_err(|e| match e {
+                    ConfigError::ValidationError(msg) => {
+                        ConfigError::ValidationError(format!("privacy.deny: {}", msg))
+                    }
+                    other => other,
+                })?;
+                Ok((pattern.to_string(), matcher, file_name_only))
+            })
+            .collect::<ConfigResult<Vec<_>>>()?;
+
+        Ok(PrivacyPolicy {
+            root: workspace_root.map(Path::to_path_buf),
+            deny,
+            exclude: exclude_matcher(workspace_root.unwrap_or(Path::new("/")), &config.exclude)?,
+            respect_gitignore: config.respect_gitignore,
+            max_file_size: config.max_file_size_kb.saturating_mul(1024),
+            allow_binary: config.allow_binary,
+        })
+    }
+
+    /// Checks everything that depends on the path alone: deny and exclude
+    /// patterns, ignore files and never-send markers.
+    pub fn check_path(&self, path: &Path) -> Result<(), BlockReason> {
+        for (pattern, matcher, file_name_only) in &self.deny {
+            let matched = if *file_name_only {
+                path.file_name().is_some_and(|name| matcher.is_match(name))
+            } else {
+                matcher.is_match(path)
+            };
+            if matched {
+                return Err(BlockReason::Denied(pattern.clone()));
+            }
+        }
+
+        if let Some(marker) = self.never_send_marker(path) {
+            return Err(BlockReason::NeverSend(marker));
+        }
+
+        let Some(root) = self.root.as_deref().filter(|root| path.starts_with(root)) else {
+            return Ok(());
+        };
+        if let Match::Ignore(glob) = self.exclude.matched_path_or_any_parents(path, false) {
+            return Err(BlockReason::Excluded(glob.original().to_string()));
+        }
+        if self.respect_gitignore {
+            if let Some(file) = ignored_by(root, path) {
+                return Err(BlockReason::Gitignored(file));
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks `content`, which came from `path` or was selected in it,
+    /// against the size and binary limits.
+    pub fn check_content(&self, content: &[u8]) -> Result<(), BlockReason> {
+        let size = content.len() as u64;
+        if size > self.max_file_size {
+            return Err(BlockReason::TooLarge {
+                size,
+                limit: self.max_file_size,
+            });
+        }
+        if !self.allow_binary && is_binary(content) {
+            return Err(BlockReason::Binary);
+        }
+        Ok(())
+    }
+
+    /// Reads the file at `path` if the policy allows sending it. Binary
+    /// files are returned lossily decoded when `allow_binary` is set.
+    pub fn read_file(&self, path: &Path) -> Result<String, BlockReason> {
+        self.check_path(path)?;
+        let unreadable = |e: std::io::Error| BlockReason::Unreadable(e.to_string());
+        let size = std::fs::metadata(path).map_err(unreadable)?.len();
+        if size > self.max_file_size {
+            return Err(BlockReason::TooLarge {
+                size,
+                limit: self.max_file_size,
+            });
+        }
+        let mut content = Vec::new();
+        std::fs::File::open(path)
+            .map_err(unreadable)?
+            .take(self.max_file_size + 1)
+            .read_to_end(&mut content)
+            .map_err(unreadable)?;
+        self.check_content(&content)?;
+        Ok(String::from_utf8_lossy(&content).into_owned())
+    }
+
+    /// The nearest marker in the directories containing `path`, up to the
+    /// workspace root, that applies to it.
+    fn never_send_marker(&self, path: &Path) -> Option<PathBuf> {
+        for dir in path.ancestors().skip(1) {
+            let marker = dir.join(NEVER_SEND_MARKER);
+            if let Ok(content) = std::fs::read_to_string(&marker) {
+                if content.trim().is_empty() {
+                    return Some(marker);
+                }
+                let (patterns, _) = Gitignore::new(&marker);
+                if patterns
+                    .matched_path_or_any_parents(path, false)
+                    .is_ignore()
+                {
+                    return Some(marker);
+                }
+            }
+            if self.root.as_deref() == Some(dir) {
+                break;
+            }
+        }
+        None
+    }
+}
+
+/// Builds the matcher for `privacy.exclude`, reporting the first invalid
+/// pattern.
+pub(crate) fn exclude_matcher(root: &Path, patterns: &[String]) -> ConfigResult<Gitignore> {
+    let mut builder = GitignoreBuilder::new(root);
+    for pattern in patterns {
+        builder.add_line(None, pattern).map_err(|e| {
+            ConfigError::ValidationError(format!(
+                "privacy.exclude: invalid pattern '{}': {}",
+                pattern, e
+            ))
+        })?;
+    }
+    builder
+        .build()
+        .map_err(|e| ConfigError::ValidationError(format!("privacy.exclude: {}", e)))
+}
+
+/// The ignore file that excludes `path`, checking the nearest directory
+/// first so that deeper files can re-include what a parent excluded.
+fn ignored_by(root: &Path, path: &Path) -> Option<PathBuf> {
+    for dir in path.ancestors().skip(1) {
+        if !dir.starts_with(root) {
+            break;
+        }
+        let mut files = vec![dir.join(".ignore"), dir.join(".gitignore")];
+        if dir == root {
+            files.push(root.join(".git").join("info").join("exclude"));
+        }
+        for file in files.into_iter().filter(|file| file.is_file()) {
+            let mut builder = GitignoreBuilder::new(dir);
+            builder.add(&file);
+            let matcher = builder.build().unwrap_or_else(|_| Gitignore::empty());
+            match matcher.matched_path_or_any_parents(path, false) {
+                Match::Ignore(_) => return Some(file),
+                Match::Whitelist(_) => return None,
+                Match::None => {}
+            }
+        }
+    }
+    None
+}
+
+fn is_binary(content: &[u8]) -> bool {
+    let sniffed = &content[..content.len().min(BINARY_SNIFF_LEN)];
+    sniffed.contains(&0) || std::str::from_utf8(content).is_err()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn workspace(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!(
+            "zed-copilot-privacy-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        root
+    }
+
+    fn write(path: &Path, content: &str) {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn test_builtin_and_user_deny() {
+        let config = PrivacyConfig {
+            deny: vec!["*.sqlite".to_string()],
+            ..Default::default()
+        };
+        let policy = PrivacyPolicy::new(&config, None).unwrap();
+        let denied = |path: &str| policy.check_path(Path::new(path));
+
+        assert_eq!(
+            denied("/w/.env"),
+            Err(BlockReason::Denied(".env".to_string()))
+        );
+        assert!(denied("/w/.env.production").is_err());
+        assert!(denied("/home/me/.ssh/id_ed25519").is_err());
+        assert!(denied("/home/me/.aws/credentials").is_err());
+        assert!(denied("/w/data/app.sqlite").is_err());
+        assert!(denied("/w/src/main.rs").is_ok());
+        assert!(denied("/w/src/credentials.rs").is_ok());
+
+        let config = PrivacyConfig {
+            builtin_deny: false,
+            ..Default::default()
+        };
+        let policy = PrivacyPolicy::new(&config, None).unwrap();
+        assert!(policy.check_path(Path::new("/w/.env")).is_ok());
+    }
+
+    #[test]
+    fn test_exclude_and_gitignore() {
+        let root = workspace("ignore");
+        write(&root.join(".gitignore"), "target/\n*.log\n");
+        write(&root.join("src/.gitignore"), "!keep.log\n");
+        let config = PrivacyConfig {
+            exclude: vec!["customers/".to_string(), "/fixtures/*.csv".to_string()],
+            ..Default::default()
+        };
+        let policy = PrivacyPolicy::new(&config, Some(&root)).unwrap();
+
+        assert_eq!(
+            policy.check_path(&root.join("customers/acme/orders.json")),
+            Err(BlockReason::Excluded("customers/".to_string()))
+        );
+        assert!(policy.check_path(&root.join("fixtures/users.csv")).is_err());
+        assert!(policy
+            .check_path(&root.join("src/fixtures/users.csv"))
+            .is_ok());
+        assert_eq!(
+            policy.check_path(&root.join("target/debug/build.rs")),
+            Err(BlockReason::Gitignored(root.join(".gitignore")))
+        );
+        assert!(policy.check_path(&root.join("server.log")).is_err());
+        assert!(policy.check_path(&root.join("src/keep.log")).is_ok());
+        assert!(policy.check_path(&root.join("src/lib.rs")).is_ok());
+
+        let config = PrivacyConfig {
+            respect_gitignore: false,
+            ..Default::default()
+        };
+        let policy = PrivacyPolicy::new(&config, Some(&root)).unwrap();
+        assert!(policy.check_path(&root.join("server.log")).is_ok());
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_never_send_marker() {
+        let root = workspace("marker");
+        write(&root.join("clients").join(NEVER_SEND_MARKER), "");
+        write(&root.join("src").join(NEVER_SEND_MARKER), "*.sql\n");
+        let policy = PrivacyPolicy::new(&PrivacyConfig::default(), Some(&root)).unwrap();
+
+        assert_eq!(
+            policy.check_path(&root.join("clients/acme/report.md")),
+            Err(BlockReason::NeverSend(
+                root.join("clients").join(NEVER_SEND_MARKER)
+            ))
+        );
+        assert!(policy.check_path(&root.join("src/db/seed.sql")).is_err());
+        assert!(policy.check_path(&root.join("src/db/schema.rs")).is_ok());
+        assert!(policy.check_path(&root.join("README.md")).is_ok());
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_size_and_binary_limits() {
+        let root = workspace("limits");
+        let config = PrivacyConfig {
+            max_file_size_kb: 1,
+            ..Default::default()
+        };
+        let policy = PrivacyPolicy::new(&config, Some(&root)).unwrap();
+
+        write(&root.join("src/small.rs"), "fn main() {}\n");
+        assert_eq!(
+            policy.read_file(&root.join("src/small.rs")).unwrap(),
+            "fn main() {}\n"
+        );
+        write(&root.join("src/big.rs"), &"x".repeat(2048));
+        assert_eq!(
+            policy.read_file(&root.join("src/big.rs")),
+            Err(BlockReason::TooLarge {
+                size: 2048,
+                limit: 1024
+            })
+        );
+        std::fs::write(root.join("src/logo.png"), [0x89, b'P', b'N', b'G', 0, 0]).unwrap();
+        assert_eq!(
+            policy.read_file(&root.join("src/logo.png")),
+            Err(BlockReason::Binary)
+        );
+        assert!(matches!(
+            policy.read_file(&root.join("src/missing.rs")),
+            Err(BlockReason::Unreadable(_))
+        ));
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_invalid_patterns() {
+        let config = PrivacyConfig {
+            deny: vec!["[oops".to_string()],
+            ..Default::default()
+        };
+        match PrivacyPolicy::new(&config, None) {
+            Err(ConfigError::ValidationError(msg)) => assert!(msg.contains("privacy.deny")),
+            other => panic!("Expected ValidationError, got {:?}", other),
+        }
+    }
+}
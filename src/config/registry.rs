@@ -0,0 +1,368 @@
+use crate::config::errors::{ConfigError, ConfigResult};
+use crate::config::manager::{Profile, ProviderConfig};
+use crate::config::structs::{
+    AnthropicConfig, CustomProviderConfig, GenerationConfig, OpenAiConfig, RootConfig,
+};
+use crate::config::validator::ConfigValidator;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::{OnceLock, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+/// Turns a provider's raw settings section into a [`ProviderConfig`]. The
+/// first argument is the name the kind was registered under.
+pub type ConfigDeserializer = fn(&str, serde_json::Value) -> ConfigResult<ProviderConfig>;
+
+pub type ConfigCheck = fn(&ProviderConfig) -> ConfigResult<()>;
+
+/// Config-side description of a provider: its settings key, how to parse
+/// that section and how to validate the result.
+#[derive(Clone)]
+pub struct ProviderKind {
+    name: String,
+    deserialize: ConfigDeserializer,
+    validate: ConfigCheck,
+}
+
+impl ProviderKind {
+    pub fn new(
+        name: impl Into<String>,
+        deserialize: ConfigDeserializer,
+        validate: ConfigCheck,
+    ) -> Self {
+        ProviderKind {
+            name: name.into(),
+            deserialize,
+            validate,
+        }
+    }
+
+    /// Kind whose section is parsed into a [`CustomProviderConfig`] and
+    /// checked with the common api_key/model/timeout rules.
+    pub fn custom(name: impl Into<String>) -> Self {
+        Self::new(
+            name,
+            deserialize_custom,
+            ConfigValidator::validate_provider_fields,
+        )
+    }
+
+    pub fn with_validator(mut self, validate: ConfigCheck) -> Self {
+        self.validate = validate;
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn deserialize(&self, section: serde_json::Value) -> ConfigResult<ProviderConfig> {
+        (self.deserialize)(&self.name, section)
+            .map_err(|e| ConfigError::ParseError(format!("{} section: {}", self.name, e)))
+    }
+
+    pub fn validate(&self, config: &ProviderConfig) -> ConfigResult<()> {
+        (self.validate)(config)
+    }
+}
+
+impl fmt::Debug for ProviderKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProviderKind")
+            .field("name", &self.name)
+            .finish_non_exhaustive()
+    }
+}
+
+fn deserialize_openai(_: &str, section: serde_json::Value) -> ConfigResult<ProviderConfig> {
+    let config: OpenAiConfig = serde_json::from_value(section)?;
+    Ok(ProviderConfig::OpenAi(config))
+}
+
+fn deserialize_anthropic(_: &str, section: serde_json::Value) -> ConfigResult<ProviderConfig> {
+    let config: AnthropicConfig = serde_json::from_value(section)?;
+    Ok(ProviderConfig::Anthropic(config))
+}
+
+fn deserialize_custom(name: &str, section: serde_json::Value) -> ConfigResult<ProviderConfig> {
+    let mut config: CustomProviderConfig = serde_json::from_value(section)?;
+    config.name = name.to_string();
+    Ok(ProviderConfig::Custom(config))
+}
+
+/// Provider kinds known to the config layer, keyed by the value of the
+/// `provider` setting. Constructors are registered alongside through
+/// `ProviderFactory::register`.
+#[derive(Debug, Default)]
+pub struct ProviderRegistry {
+    kinds: RwLock<BTreeMap<String, ProviderKind>>,
+}
+
+impl ProviderRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_builtins() -> Self {
+        let registry = Self::new();
+        registry.register(ProviderKind::new(
+            "openai",
+            deserialize_openai,
+            ConfigValidator::validate_provider_fields,
+        ));
+        registry.register(ProviderKind::new(
+            "anthropic",
+            deserialize_anthropic,
+            ConfigValidator::validate_provider_fields,
+        ));
+        registry
+    }
+
+    pub fn global() -> &'static ProviderRegistry {
+        static GLOBAL: OnceLock<ProviderRegistry> = OnceLock::new();
+        GLOBAL.get_or_init(Self::with_builtins)
+    }
+
+    /// Adds a kind, replacing any previous kind with the same name.
+    pub fn register(&self, kind: ProviderKind) {
+        self.write().insert(kind.name.clone(), kind);
+    }
+
+    pub fn get(&self, name: &str) -> Option<ProviderKind> {
+        self.read().get(name).cloned()
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.read().contains_key(name)
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.read().keys().cloned().collect()
+    }
+
+    /// Parses the section of the provider selected by `config.provider`.
+    pub fn resolve(&self, config: &RootConfig) -> ConfigResult<ProviderConfig> {
+        self.resolve_with_kind(config).map(|(_, provider)| provider)
+    }
+
+    /// Resolves and validates the selected provider.
+    pub fn validate(&self, config: &RootConfig) -> ConfigResult<()> {
+        let (kind, provider) = self.resolve_with_kind(config)?;
+        kind.validate(&provider)
+    }
+
+    fn resolve_with_kind(
+        &self,
+        config: &RootConfig,
+    ) -> ConfigResult<(ProviderKind, ProviderConfig)> {
+        let kind = self
+            .get(&config.provider)
+            .ok_or_else(|| ConfigError::InvalidProvider(config.provider.clone()))?;
+        let section = config.provider_section(kind.name()).ok_or_else(|| {
+            ConfigError::MissingField(format!("{} configuration section", kind.name()))
+        })?;
+        let provider = kind.deserialize(section)?;
+        Ok((kind, provider))
+    }
+
+    /// Resolves a profile by name. Entries of `profiles` take precedence;
+    /// the top-level `provider` name resolves to an implicit profile with no
+    /// generation overrides.
+    pub fn profile(&self, config: &RootConfig, name: &str) -> ConfigResult<Profile> {
+        if config.profiles.contains_key(name) {
+            return self.resolve_profile(config, name);
+        }
+
+        if name == config.provider {
+            return Ok(Profile {
+                name: name.to_string(),
+                provider: self.resolve(config)?,
+                generation: GenerationConfig::default(),
+            });
+        }
+
+        Err(ConfigError::ValidationError(format!(
+            "Unknown profile '{}'",
+            name
+        )))
+    }
+
+    /// Resolves a named entry of `profiles`: the provider's top-level
+    /// section, if any, overlaid with the profile's own fields.
+    pub fn resolve_profile(&self, config: &RootConfig, name: &str) -> ConfigResult<Profile> {
+        self.resolve_profile_with_kind(config, name)
+            .map(|(_, profile)| profile)
+    }
+
+    pub fn validate_profile(&self, config: &RootConfig, name: &str) -> ConfigResult<()> {
+        let (kind, profile) = self.resolve_profile_with_kind(config, name)?;
+        kind.validate(&profile.provider)
+    }
+
+    fn resolve_profile_with_kind(
+        &self,
+        config: &RootConfig,
+        name: &str,
+    ) -> ConfigResult<(ProviderKind, Profile)> {
+        let profile = config
+            .profiles
+            .get(name)
+            .ok_or_else(|| ConfigError::ValidationError(format!("Unknown profile '{}'", name)))?;
+        let kind = self
+            .get(&profile.provider)
+            .ok_or_else(|| ConfigError::InvalidProvider(profile.provider.clone()))?;
+
+        let mut section = config
+            .provider_section(kind.name())
+            .unwrap_or_else(|| serde_json::Value::Object(Default::default()));
+        if let serde_json::Value::Object(fields) = &mut section {
+            let overrides = [
+                ("model", &profile.model),
+                ("api_base", &profile.api_base),
+                ("api_key", &profile.api_key),
+            ];
+            for (key, value) in overrides {
+                if let Some(value) = value {
+                    fields.insert(key.to_string(), serde_json::Value::String(value.clone()));
+                }
+            }
+        }
+
+        let provider = kind.deserialize(section)?;
+        let profile = Profile {
+            name: name.to_string(),
+            provider,
+            generation: profile.generation,
+        };
+        Ok((kind, profile))
+    }
+
+    fn read(&self) -> RwLockReadGuard<'_, BTreeMap<String, ProviderKind>> {
+        self.kinds.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn write(&self) -> RwLockWriteGuard<'_, BTreeMap<String, ProviderKind>> {
+        self.kinds.write().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::loader::ConfigLoader;
+
+    fn registry_with_custom() -> ProviderRegistry {
+        let registry = ProviderRegistry::with_builtins();
+        registry.register(ProviderKind::custom("ollama"));
+        registry
+    }
+
+    #[test]
+    fn test_builtins_registered() {
+        let registry = ProviderRegistry::with_builtins();
+        assert_eq!(registry.names(), vec!["anthropic", "openai"]);
+        assert!(registry.contains("openai"));
+        assert!(!registry.contains("ollama"));
+    }
+
+    #[test]
+    fn test_resolve_builtin() {
+        let config = ConfigLoader::load_from_json_string(
+            r#"{"provider": "anthropic", "anthropic": {"api_key": "sk-ant", "model": "claude"}}"#,
+        )
+        .unwrap();
+        let provider = ProviderRegistry::with_builtins().resolve(&config).unwrap();
+        assert!(matches!(provider, ProviderConfig::Anthropic(_)));
+        assert_eq!(provider.model(), "claude");
+    }
+
+    #[test]
+    fn test_resolve_custom_section() {
+        let config = ConfigLoader::load_from_json_string(
+            r#"{
+                "enabled": true,
+                "provider": "ollama",
+                "ollama": {
+                    "api_key": "unused",
+                    "model": "llama3",
+                    "api_base": "http://localhost:11434",
+                    "keep_alive": "5m"
+                }
+            }"#,
+        )
+        .unwrap();
+        let registry = registry_with_custom();
+        let provider = registry.resolve(&config).unwrap();
+
+        assert_eq!(provider.provider_name(), "ollama");
+        assert_eq!(provider.model(), "llama3");
+        assert_eq!(provider.timeout_secs(), 30);
+        match provider {
+            ProviderConfig::Custom(custom) => {
+                assert_eq!(custom.options["keep_alive"], "5m");
+            }
+            other => panic!("Expected custom provider config, got {:?}", other),
+        }
+        assert!(registry.validate(&config).is_ok());
+    }
+
+    #[test]
+    fn test_resolve_unknown_provider() {
+        let config = RootConfig {
+            provider: "ollama".to_string(),
+            ..Default::default()
+        };
+        match ProviderRegistry::with_builtins().resolve(&config) {
+            Err(ConfigError::InvalidProvider(name)) => assert_eq!(name, "ollama"),
+            other => panic!("Expected InvalidProvider error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_resolve_missing_section() {
+        let config = RootConfig {
+            provider: "ollama".to_string(),
+            ..Default::default()
+        };
+        match registry_with_custom().resolve(&config) {
+            Err(ConfigError::MissingField(msg)) => assert!(msg.contains("ollama")),
+            other => panic!("Expected MissingField error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_custom_section_parse_error_names_section() {
+        let config = ConfigLoader::load_from_json_string(
+            r#"{"provider": "ollama", "ollama": {"model": "llama3"}}"#,
+        )
+        .unwrap();
+        match registry_with_custom().resolve(&config) {
+            Err(ConfigError::ParseError(msg)) => {
+                assert!(msg.contains("ollama section"));
+                assert!(msg.contains("api_key"));
+            }
+            other => panic!("Expected ParseError, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_custom_validator() {
+        fn require_localhost(config: &ProviderConfig) -> ConfigResult<()> {
+            if config.api_base().starts_with("http://localhost") {
+                Ok(())
+            } else {
+                Err(ConfigError::ValidationError("not local".to_string()))
+            }
+        }
+
+        let registry = ProviderRegistry::new();
+        registry.register(ProviderKind::custom("local").with_validator(require_localhost));
+        let config = ConfigLoader::load_from_json_string(
+            r#"{"provider": "local", "local": {"api_key": "k", "api_base": "https://remote"}}"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            registry.validate(&config),
+            Err(ConfigError::ValidationError(_))
+        ));
+    }
+}
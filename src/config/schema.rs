@@ -0,0 +1,197 @@
+use crate::config::loader::SETTINGS_SECTION;
+use crate::config::structs::RootConfig;
+use jsonschema::error::ValidationErrorKind;
+use jsonschema::JSONSchema;
+use schemars::gen::SchemaSettings;
+use serde_json::{json, Value};
+use std::sync::OnceLock;
+
+/// Where the generated schema is checked in, relative to the crate root.
+pub const SCHEMA_PATH: &str = "docs/settings.schema.json";
+
+/// Schema of the extension's settings section, derived from [`RootConfig`].
+/// Optional settings are not marked nullable and have no `null` default; a
+/// `null` setting means unset and is dropped before validation.
+pub fn section_schema() -> Value {
+    let schema = SchemaSettings::draft07()
+        .with(|settings| settings.option_add_null_type = false)
+        .into_generator()
+        .into_root_schema_for::<RootConfig>();
+    let mut schema = serde_json::to_value(schema).unwrap_or_default();
+    if let Value::Object(fields) = &mut schema {
+        fields.remove("$schema");
+        fields.remove("title");
+    }
+    remove_null_defaults(&mut schema);
+    schema
+}
+
+fn remove_null_defaults(schema: &mut Value) {
+    match schema {
+        Value::Object(fields) => {
+            if fields.get("default").is_some_and(Value::is_null) {
+                fields.remove("default");
+            }
+            fields.values_mut().for_each(remove_null_defaults);
+        }
+        Value::Array(items) => items.iter_mut().for_each(remove_null_defaults),
+        _ => {}
+    }
+}
+
+/// The published schema for Zed's `settings.json`: the section schema under
+/// its key, with its definitions moved to the document root so `$ref`s
+/// still resolve.
+pub fn settings_schema() -> Value {
+    let mut section = section_schema();
+    let definitions = section
+        .as_object_mut()
+        .and_then(|fields| fields.remove("definitions"))
+        .unwrap_or_else(|| json!({}));
+
+    json!({
+        "$schema": "http://json-schema.org/draft-07/schema#",
+        "title": "Zed Copilot Configuration Schema",
+        "description": "Generated from the extension's settings types; do not edit by hand.",
+        "type": "object",
+        "properties": { SETTINGS_SECTION: section },
+        "definitions": definitions,
+    })
+}
+
+/// A settings value the schema rejects.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaViolation {
+    /// JSON pointer relative to the section, e.g. `/chat/max_history_messages`.
+    pub pointer: String,
+    pub message: String,
+}
+
+/// Checks raw settings against the schema before they are deserialized.
+/// Unknown fields are not violations: serde ignores them, and diagnostics
+/// report them as warnings.
+pub struct SettingsSchema {
+    compiled: JSONSchema,
+}
+
+impl SettingsSchema {
+    pub fn new() -> Self {
+        let compiled = JSONSchema::options()
+            .with_draft(jsonschema::Draft::Draft7)
+            .compile(&section_schema())
+            .expect("generated settings schema is valid");
+        SettingsSchema { compiled }
+    }
+
+    pub fn global() -> &'static SettingsSchema {
+        static GLOBAL: OnceLock<SettingsSchema> = OnceLock::new();
+        GLOBAL.get_or_init(Self::new)
+    }
+
+    /// Every violation in `section`, ordered by pointer.
+    pub fn validate(&self, section: &Value) -> Vec<SchemaViolation> {
+        let section = without_nulls(section);
+        let Err(errors) = self.compiled.validate(&section) else {
+            return Vec::new();
+        };
+        let mut violations: Vec<SchemaViolation> = errors
+            .filter(|error| !matches!(error.kind, ValidationErrorKind::AdditionalProperties { .. }))
+            .map(|error| SchemaViolation {
+                pointer: error.instance_path.to_string(),
+                message: error.to_string(),
+            })
+            .collect();
+        violations.sort_by(|a, b| a.pointer.cmp(&b.pointer));
+        violations.dedup();
+        violations
+    }
+}
+
+fn without_nulls(value: &Value) -> Value {
+    match value {
+        Value::Object(fields) => Value::Object(
+            fields
+                .iter()
+                .filter(|(_, field)| !field.is_null())
+                .map(|(key, field)| (key.clone(), without_nulls(field)))
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.iter().map(without_nulls).collect()),
+        other => other.clone(),
+    }
+}
+
+impl Default for SettingsSchema {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checked_in_schema_is_current() {
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join(SCHEMA_PATH);
+        let generated = serde_json::to_string_pretty(&settings_schema()).unwrap() + "\n";
+        if std::env::var_os("UPDATE_SETTINGS_SCHEMA").is_some() {
+            std::fs::write(&path, &generated).unwrap();
+        }
+
+        let checked_in = std::fs::read_to_string(&path).unwrap();
+        assert!(
+            checked_in == generated,
+            "{} is stale; regenerate it with UPDATE_SETTINGS_SCHEMA=1 cargo test schema",
+            SCHEMA_PATH
+        );
+    }
+
+    #[test]
+    fn test_schema_carries_defaults_and_bounds() {
+        let schema = settings_schema();
+        let chat = &schema["definitions"]["ChatConfig"]["properties"];
+        assert_eq!(chat["max_history_messages"]["default"], 50);
+        assert_eq!(chat["max_history_messages"]["minimum"], 1.0);
+        let openai = &schema["definitions"]["OpenAiConfig"];
+        assert_eq!(openai["required"], json!(["api_key"]));
+        assert_eq!(openai["properties"]["model"]["default"], "gpt-4");
+        let task = &schema["definitions"]["TaskKind"]["enum"];
+        assert!(task.as_array().unwrap().contains(&json!("commit_message")));
+    }
+
+    #[test]
+    fn test_validate_reports_every_violation() {
+        let violations = SettingsSchema::global().validate(&json!({
+            "enabled": "yes",
+            "openai": {"api_key": "sk", "timeout_secs": 0},
+            "chat": {"max_history_messages": 0},
+            "profiles": {"fast": {"provider": "openai", "temperature": 2.5}},
+            "routing": {"tasks": {"chat": ""}}
+        }));
+        let pointers: Vec<&str> = violations.iter().map(|v| v.pointer.as_str()).collect();
+        assert_eq!(
+            pointers,
+            vec![
+                "/chat/max_history_messages",
+                "/enabled",
+                "/openai/timeout_secs",
+                "/profiles/fast/temperature",
+                "/routing/tasks/chat",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_allows_unknown_fields_and_references() {
+        let violations = SettingsSchema::global().validate(&json!({
+            "provider": "custom",
+            "custom": {"api_key": "${CUSTOM_KEY}", "region": "eu"},
+            "openai": {"api_key": "${OPENAI_API_KEY}", "modle": "gpt-4o"},
+            "network": {"proxy": "${HTTPS_PROXY}"},
+            "routing": {"tasks": {"chat": "fast", "review": {"model": "o3"}}},
+            "cache": null
+        }));
+        assert!(violations.is_empty(), "{:?}", violations);
+    }
+}
@@ -0,0 +1,473 @@
+use crate::config::errors::{ConfigError, ConfigResult};
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use zeroize::Zeroizing;
+
+/// Store file name, relative to the extension's work directory.
+pub const DEFAULT_STORE_FILE: &str = "secrets.enc";
+
+const FORMAT_VERSION: u32 = 1;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+
+/// Upper bounds on [`KdfParams`], so a damaged or planted store file cannot
+/// make opening it take gigabytes of memory or minutes of CPU.
+const MAX_MEMORY_KIB: u32 = 1024 * 1024;
+const MAX_ITERATIONS: u32 = 16;
+const MAX_PARALLELISM: u32 = 16;
+
+/// Argon2id cost parameters, stored alongside the ciphertext so they can be
+/// raised later without breaking existing stores.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KdfParams {
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+impl KdfParams {
+    fn check(self) -> Result<(), String> {
+        let limits = [
+            (
+                "memory_kib",
+                self.memory_kib,
+                8u32.saturating_mul(self.parallelism.max(1)),
+                MAX_MEMORY_KIB,
+            ),
+            ("iterations", self.iterations, 1, MAX_ITERATIONS),
+            ("parallelism", self.parallelism, 1, MAX_PARALLELISM),
+        ];
+        for (name, value, min, max) in limits {
+            if !(min..=max).contains(&value) {
+                return Err(format!(
+                    "key parameter {} must be between {} and {} (got {})",
+                    name, min, max, value
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for KdfParams {
+    /// The OWASP minimum for Argon2id: 19 MiB, two passes, one lane.
+    fn default() -> Self {
+        KdfParams {
+            memory_kib: 19 * 1024,
+            iterations: 2,
+            parallelism: 1,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct StoreFile {
+    version: u32,
+    kdf: KdfParams,
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+/// API keys and other credentials, kept in a file encrypted with
+/// XChaCha20-Poly1305 under a key derived from a passphrase with Argon2id.
+/// The whole name-to-value map is one ciphertext, so not even secret names
+/// are readable without the passphrase. Every change rewrites the file with
+/// a fresh nonce.
+pub struct SecretStore {
+    path: PathBuf,
+    params: KdfParams,
+    salt: [u8; SALT_LEN],
+    key: Zeroizing<[u8; 32]>,
+    secrets: BTreeMap<String, String>,
+}
+
+impl SecretStore {
+    /// Opens the store at `path`, or starts an empty one if the file does
+    /// not exist yet. Nothing is written until a secret is set.
+    pub fn open(path: impl Into<PathBuf>, passphrase: &str) -> ConfigResult<Self> {
+        Self::open_with_params(path, passphrase, KdfParams::default())
+    }
+
+    /// Like [`open`](Self::open), using `params` if a new store is created.
+    pub fn open_with_params(
+        path: impl Into<PathBuf>,
+        passphrase: &str,
+        params: KdfParams,
+    ) -> ConfigResult<Self> {
+        let path = path.into();
+        if passphrase.is_empty() {
+            return Err(ConfigError::ValidationError(
+                "Secret store passphrase must not be empty".to_string(),
+            ));
+        }
+
+        let content = match std::fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                params.check().map_err(|e| store_error(&path, e))?;
+                let salt = random_bytes::<SALT_LEN>()?;
+                let key = derive_key(passphrase, &salt, params)?;
+                return Ok(SecretStore {
+                    path,
+                    params,
+                    salt,
+                    key,
+                    secrets: BTreeMap::new(),
+                });
+            }
+            Err(e) => return Err(store_error(&path, e)),
+        };
+
+        let file: StoreFile = serde_json::from_str(&content).map_err(|e| store_error(&path, e))?;
+        if file.version != FORMAT_VERSION {
+            return Err(store_error(
+                &path,
+                format!("unsupported format version {}", file.version),
+            ));
+        }
+        file.kdf.check().map_err(|e| store_error(&path, e))?;
+        let salt: [u8; SALT_LEN] =
+            decode_hex(&file.salt).ok_or_else(|| store_error(&path, "bad salt"))?;
+        let nonce: [u8; NONCE_LEN] =
+            decode_hex(&file.nonce).ok_or_else(|| store_error(&path, "bad nonce"))?;
+        let ciphertext: Vec<u8> =
+            decode_hex(&file.ciphertext).ok_or_else(|| store_error(&path, "bad ciphertext"))?;
+
+        let key = derive_key(passphrase, &salt, file.kdf)?;
+        let plaintext = XChaCha20Poly1305::new(&(*key).into())
+            .decrypt(
+                XNonce::from_slice(&nonce),
+                Payload {
+                    msg: &ciphertext,
+                    aad: &associated_data(file.kdf, &salt),
+                },
+            )
+            .map(Zeroizing::new)
+            .map_err(|_| store_error(&path, "wrong passphrase or the file has been modified"))?;
+        let secrets = serde_json::from_slice(&plaintext).map_err(|e| store_error(&path, e))?;
+
+        Ok(SecretStore {
+            path,
+            params: file.kdf,
+            salt,
+            key,
+            secrets,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.secrets.get(name).map(String::as_str)
+    }
+
+    /// Secret names, sorted. Values are only available through `get`.
+    pub fn names(&self) -> Vec<&str> {
+        self.secrets.keys().map(String::as_str).collect()
+    }
+
+    /// Adds or replaces a secret and saves the store.
+    pub fn set(&mut self, name: &str, value: &str) -> ConfigResult<()> {
+        if name.is_empty() || name.contains('}') {
+            return Err(ConfigError::ValidationError(format!(
+                "Invalid secret name '{}'",
+                name
+            )));
+        }
+        self.secrets.insert(name.to_string(), value.to_string());
+        self.save()
+    }
+
+    /// Replaces the value of an existing secret, e.g. after the provider
+    /// issued a new key.
+    pub fn rotate(&mut self, name: &str, value: &str) -> ConfigResult<()> {
+        if !self.secrets.contains_key(name) {
+            return Err(ConfigError::ValidationError(format!(
+                "Unknown secret '{}'",
+                name
+            )));
+        }
+        self.set(name, value)
+    }
+
+    /// Removes a secret, returning whether it existed.
+    pub fn delete(&mut self, name: &str) -> ConfigResult<bool> {
+        let existed = self.secrets.remove(name).is_some();
+        if existed {
+            self.save()?;
+        }
+        Ok(existed)
+    }
+
+    /// Re-encrypts the store under a new passphrase and salt.
+    pub fn change_passphrase(&mut self, passphrase: &str) -> ConfigResult<()> {
+        if passphrase.is_empty() {
+            return Err(ConfigError::ValidationError(
+                "Secret store passphrase must not be empty".to_string(),
+            ));
+        }
+        self.salt = random_bytes::<SALT_LEN>()?;
+        self.key = derive_key(passphrase, &self.salt, self.params)?;
+        self.save()
+    }
+
+    fn save(&self) -> ConfigResult<()> {
+        let plaintext = Zeroizing::new(serde_json::to_vec(&self.secrets)?);
+        let nonce = random_bytes::<NONCE_LEN>()?;
+        let ciphertext = XChaCha20Poly1305::new(&(*self.key).into())
+            .encrypt(
+                XNonce::from_slice(&nonce),
+                Payload {
+                    msg: &plaintext,
+                    aad: &associated_data(self.params, &self.salt),
+                },
+            )
+            .map_err(|_| store_error(&self.path, "encryption failed"))?;
+
+        let file = StoreFile {
+            version: FORMAT_VERSION,
+            kdf: self.params,
+            salt: encode_hex(&self.salt),
+            nonce: encode_hex(&nonce),
+            ciphertext: encode_hex(&ciphertext),
+        };
+        let content = serde_json::to_string_pretty(&file)?;
+
+        if let Some(dir) = self.path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir).map_err(|e| store_error(&self.path, e))?;
+        }
+        let tmp = self.path.with_extension("tmp");
+        std::fs::write(&tmp, content).map_err(|e| store_error(&self.path, e))?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&tmp, std::fs::Permissions::from_mode(0o600))
+                .map_err(|e| store_error(&self.path, e))?;
+        }
+        std::fs::rename(&tmp, &self.path).map_err(|e| store_error(&self.path, e))
+    }
+}
+
+impl fmt::Debug for SecretStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SecretStore")
+            .field("path", &self.path)
+            .field("names", &self.names())
+            .finish_non_exhaustive()
+    }
+}
+
+fn store_error(path: &Path, error: impl fmt::Display) -> ConfigError {
+    ConfigError::ValidationError(format!("Secret store {}: {}", path.display(), error))
+}
+
+/// Binds the ciphertext to the KDF settings it was written with.
+fn associated_data(params: KdfParams, salt: &[u8]) -> Vec<u8> {
+    format!(
+        "zed-copilot-secrets:v{}:{}:{}:{}:{}",
+        FORMAT_VERSION,
+        params.memory_kib,
+        params.iterations,
+        params.parallelism,
+        encode_hex(salt)
+    )
+    .into_bytes()
+}
+
+fn derive_key(
+    passphrase: &str,
+    salt: &[u8],
+    params: KdfParams,
+) -> ConfigResult<Zeroizing<[u8; 32]>> {
+    let invalid = |e: argon2::Error| {
+        ConfigError::ValidationError(format!("Invalid secret store key parameters: {}", e))
+    };
+    let params = Params::new(
+        params.memory_kib,
+        params.iterations,
+        params.parallelism,
+        Some(32),
+    )
+    .map_err(invalid)?;
+
+    let mut key = Zeroizing::new([0u8; 32]);
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), salt, &mut *key)
+        .map_err(invalid)?;
+    Ok(key)
+}
+
+fn random_bytes<const N: usize>() -> ConfigResult<[u8; N]> {
+    let mut bytes = [0u8; N];
+    getrandom::getrandom(&mut bytes).map_err(|e| {
+        ConfigError::ValidationError(format!("No secure random source available: {}", e))
+    })?;
+    Ok(bytes)
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn decode_hex<T: TryFrom<Vec<u8>>>(text: &str) -> Option<T> {
+    if !text.len().is_multiple_of(2) {
+        return None;
+    }
+    let bytes = (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok())
+        .collect::<Option<Vec<u8>>>()?;
+    T::try_from(bytes).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Cheap parameters so tests do not spend seconds in the KDF.
+    const TEST_PARAMS: KdfParams = KdfParams {
+        memory_kib: 64,
+        iterations: 1,
+        parallelism: 1,
+    };
+
+    fn store_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir()
+            .join(format!("zed-copilot-secrets-{}", std::process::id()))
+            .join(format!("{}.enc", name));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    fn open(path: &Path, passphrase: &str) -> ConfigResult<SecretStore> {
+        SecretStore::open_with_params(path, passphrase, TEST_PARAMS)
+    }
+
+    #[test]
+    fn test_set_get_and_reopen() {
+        let path = store_path("reopen");
+        let mut store = open(&path, "correct horse").unwrap();
+        assert!(store.names().is_empty());
+        assert!(!path.exists());
+
+        store.set("openai", "sk-secret-value").unwrap();
+        store.set("anthropic", "sk-ant-value").unwrap();
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(!content.contains("sk-secret-value"));
+        assert!(!content.contains("openai"));
+
+        let reopened = open(&path, "correct horse").unwrap();
+        assert_eq!(reopened.names(), vec!["anthropic", "openai"]);
+        assert_eq!(reopened.get("openai"), Some("sk-secret-value"));
+        assert_eq!(reopened.get("missing"), None);
+    }
+
+    #[test]
+    fn test_wrong_passphrase_is_rejected() {
+        let path = store_path("wrong");
+        open(&path, "right").unwrap().set("openai", "sk").unwrap();
+
+        match open(&path, "wrong") {
+            Err(ConfigError::ValidationError(msg)) => assert!(msg.contains("wrong passphrase")),
+            other => panic!("Expected ValidationError, got {:?}", other),
+        }
+        assert!(open(&path, "").is_err());
+    }
+
+    #[test]
+    fn test_tampered_file_is_rejected() {
+        let path = store_path("tampered");
+        open(&path, "pass").unwrap().set("openai", "sk").unwrap();
+
+        let mut file: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        file["kdf"]["iterations"] = 2.into();
+        std::fs::write(&path, file.to_string()).unwrap();
+        assert!(open(&path, "pass").is_err());
+    }
+
+    #[test]
+    fn test_unbounded_key_parameters_are_rejected() {
+        let path = store_path("kdf-bounds");
+        open(&path, "pass").unwrap().set("openai", "sk").unwrap();
+
+        let mut file: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        file["kdf"]["memory_kib"] = (64 * 1024 * 1024).into();
+        std::fs::write(&path, file.to_string()).unwrap();
+        match open(&path, "pass") {
+            Err(ConfigError::ValidationError(msg)) => assert!(msg.contains("memory_kib")),
+            other => panic!("Expected ValidationError, got {:?}", other),
+        }
+
+        let too_slow = KdfParams {
+            iterations: 1000,
+            ..TEST_PARAMS
+        };
+        assert!(SecretStore::open_with_params(store_path("kdf-new"), "pass", too_slow).is_err());
+    }
+
+    #[test]
+    fn test_rotate_and_delete() {
+        let path = store_path("rotate");
+        let mut store = open(&path, "pass").unwrap();
+        assert!(store.rotate("openai", "sk-new").is_err());
+
+        store.set("openai", "sk-old").unwrap();
+        store.rotate("openai", "sk-new").unwrap();
+        assert_eq!(open(&path, "pass").unwrap().get("openai"), Some("sk-new"));
+
+        assert!(store.delete("openai").unwrap());
+        assert!(!store.delete("openai").unwrap());
+        assert!(open(&path, "pass").unwrap().names().is_empty());
+    }
+
+    #[test]
+    fn test_change_passphrase() {
+        let path = store_path("passphrase");
+        let mut store = open(&path, "old").unwrap();
+        store.set("openai", "sk").unwrap();
+        store.change_passphrase("new").unwrap();
+
+        assert!(open(&path, "old").is_err());
+        assert_eq!(open(&path, "new").unwrap().get("openai"), Some("sk"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_store_file_is_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = store_path("private");
+        open(&path, "pass").unwrap().set("openai", "sk").unwrap();
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    #[test]
+    fn test_debug_hides_values() {
+        let path = store_path("debug");
+        let mut store = open(&path, "pass").unwrap();
+        store.set("openai", "sk-hidden").unwrap();
+        let debug = format!("{:?}", store);
+        assert!(debug.contains("openai"));
+        assert!(!debug.contains("sk-hidden"));
+    }
+
+    #[test]
+    fn test_invalid_names() {
+        let path = store_path("names");
+        let mut store = open(&path, "pass").unwrap();
+        assert!(store.set("", "x").is_err());
+        assert!(store.set("a}b", "x").is_err());
+    }
+}
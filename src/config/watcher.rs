@@ -0,0 +1,376 @@
+use crate::config::errors::{ConfigError, ConfigResult};
+use crate::config::loader::ZedSettingsPaths;
+use crate::config::manager::ConfigManager;
+use crate::config::structs::RootConfig;
+use serde_json::Value;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+/// Files up to this size are always hashed, since an edit that keeps the
+/// length within the timestamp granularity leaves the metadata unchanged.
+/// Settings files are a few KB, so this covers them in practice.
+const ALWAYS_HASH_BELOW: u64 = 64 * 1024;
+
+/// Settings paths whose values differ between two configurations, as dotted
+/// paths such as `openai.model`. Values are never recorded, so the event is
+/// safe to log even when an API key changed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConfigChange {
+    pub changed: Vec<String>,
+}
+
+impl ConfigChange {
+    pub fn between(old: &RootConfig, new: &RootConfig) -> Self {
+        let old = serde_json::to_value(old).unwrap_or(Value::Null);
+        let new = serde_json::to_value(new).unwrap_or(Value::Null);
+        let mut changed = Vec::new();
+        diff_values("", &old, &new, &mut changed);
+        ConfigChange { changed }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changed.is_empty()
+    }
+
+    /// Whether `path` or anything below it changed.
+    pub fn touches(&self, path: &str) -> bool {
+        self.changed.iter().any(|changed| {
+            changed == path
+                || changed
+                    .strip_prefix(path)
+                    .is_some_and(|rest| rest.starts_with('.'))
+        })
+    }
+}
+
+fn diff_values(path: &str, old: &Value, new: &Value, changed: &mut Vec<String>) {
+    match (old, new) {
+        (Value::Object(old_fields), Value::Object(new_fields)) => {
+            let mut keys: Vec<&String> = old_fields.keys().chain(new_fields.keys()).collect();
+            keys.sort();
+            keys.dedup();
+            for key in keys {
+                let child = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", path, key)
+                };
+                let old = old_fields.get(key).unwrap_or(&Value::Null);
+                let new = new_fields.get(key).unwrap_or(&Value::Null);
+                diff_values(&child, old, new, changed);
+            }
+        }
+        _ if old != new => changed.push(path.to_string()),
+        _ => {}
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Fingerprint {
+    modified: Option<SystemTime>,
+    len: u64,
+    hash: u64,
+}
+
+#[derive(Debug, Clone)]
+struct WatchedFile {
+    path: PathBuf,
+    /// `None` while the file is missing.
+    last: Option<Fingerprint>,
+}
+
+impl WatchedFile {
+    /// Whether the file was created, removed or edited since the last call.
+    fn changed(&mut self) -> ConfigResult<bool> {
+        let metadata = match std::fs::metadata(&self.path) {
+            Ok(metadata) => metadata,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(self.last.take().is_some());
+            }
+            Err(e) => return Err(read_error(e)),
+        };
+        let modified = metadata.modified().ok();
+        let len = metadata.len();
+
+        if let Some(last) = self.last {
+            if len > ALWAYS_HASH_BELOW
+                && modified.is_some()
+                && last.modified == modified
+                && last.len == len
+            {
+                return Ok(false);
+            }
+        }
+
+        let content = std::fs::read_to_string(&self.path).map_err(read_error)?;
+        let mut hasher = DefaultHasher::new();
+        content.hash(&mut hasher);
+        let hash = hasher.finish();
+
+        let unchanged = self.last.is_some_and(|last| last.hash == hash);
+        self.last = Some(Fingerprint {
+            modified,
+            len,
+            hash,
+        });
+        Ok(!unchanged)
+    }
+}
+
+fn read_error(e: std::io::Error) -> ConfigError {
+    ConfigError::ParseError(format!("Failed to read config file: {}", e))
+}
+
+/// Detects edits to the user and project settings files by polling. Files
+/// under 64 KiB are read and hashed on every poll. Larger ones are only read
+/// when their modification time or size differ or the time is unavailable,
+/// as on wasm hosts without file timestamps, so a same-length edit within
+/// the timestamp granularity can go unnoticed for them. Creating or removing
+/// a file counts as a change.
+#[derive(Debug, Clone)]
+pub struct SettingsWatcher {
+    paths: ZedSettingsPaths,
+    files: Vec<WatchedFile>,
+    polled: bool,
+}
+
+impl SettingsWatcher {
+    pub fn new(paths: ZedSettingsPaths) -> Self {
+        let files = [&paths.user, &paths.project]
+            .into_iter()
+            .flatten()
+            .map(|path| WatchedFile {
+                path: path.clone(),
+                last: None,
+            })
+            .collect();
+        SettingsWatcher {
+            paths,
+            files,
+            polled: false,
+        }
+    }
+
+    pub fn paths(&self) -> &ZedSettingsPaths {
+        &self.paths
+    }
+
+    /// Whether any settings file changed since the last poll. The first
+    /// poll always reports a change.
+    pub fn poll(&mut self) -> ConfigResult<bool> {
+        let mut changed = !self.polled;
+        for file in &mut self.files {
+            changed |= file.changed()?;
+        }
+        self.polled = true;
+        Ok(changed)
+    }
+
+    /// Polls the files and reloads `manager` from all settings layers when
+    /// one changed. Settings that fail to load or validate leave `manager`
+    /// untouched and are not retried until a file changes again.
+    pub fn poll_reload(
+        &mut self,
+        manager: &mut ConfigManager,
+    ) -> ConfigResult<Option<ConfigChange>> {
+        if !self.poll()? {
+            return Ok(None);
+        }
+        let change = manager.reload_layered(&self.paths, std::env::vars())?;
+        Ok((!change.is_empty()).then_some(change))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::layers::ConfigSource;
+    use crate::config::loader::ConfigLoader;
+    use std::path::Path;
+
+    const SETTINGS: &str = r#"{
+        "enabled": true,
+        "provider": "openai",
+        "openai": {"api_key": "sk-test-key", "model": "gpt-4"}
+    }"#;
+
+    /// User and project settings files under a fresh directory, with the
+    /// user file holding `SETTINGS`.
+    fn settings_paths(name: &str) -> ZedSettingsPaths {
+        let root =
+            std::env::temp_dir().join(format!("zed-copilot-watch-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(&root).unwrap();
+        let paths = ZedSettingsPaths {
+            user: Some(root.join("user.json")),
+            project: Some(root.join("project.json")),
+        };
+        write_section(paths.user.as_ref().unwrap(), SETTINGS);
+        paths
+    }
+
+    fn write_section(path: &Path, section: &str) {
+        std::fs::write(path, format!(r#"{{"zed_copilot": {}}}"#, section)).unwrap();
+    }
+
+    fn remove(paths: &ZedSettingsPaths) {
+        let root = paths.user.as_ref().unwrap().parent().unwrap();
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_change_lists_dotted_paths() {
+        let old = ConfigLoader::load_from_json_string(SETTINGS).unwrap();
+        let new = ConfigLoader::load_from_json_string(
+            r#"{
+                "enabled": true,
+                "provider": "openai",
+                "openai": {"api_key": "sk-other", "model": "gpt-4"},
+                "middleware": {"log_requests": true}
+            }"#,
+        )
+        .unwrap();
+
+        let change = ConfigChange::between(&old, &new);
+        assert_eq!(change.changed, vec!["middleware", "openai.api_key"]);
+        assert!(change.touches("openai"));
+        assert!(!change.touches("open"));
+        assert!(ConfigChange::between(&old, &old).is_empty());
+    }
+
+    #[test]
+    fn test_poll_reports_first_read_and_changes_only() {
+        let paths = settings_paths("poll");
+        let user = paths.user.clone().unwrap();
+        let mut watcher = SettingsWatcher::new(paths.clone());
+
+        assert!(watcher.poll().unwrap());
+        assert!(!watcher.poll().unwrap());
+
+        write_section(&user, r#"{"enabled": true}"#);
+        assert!(watcher.poll().unwrap());
+        assert!(!watcher.poll().unwrap());
+
+        remove(&paths);
+    }
+
+    #[test]
+    fn test_poll_ignores_touch_without_content_change() {
+        let paths = settings_paths("touch");
+        let mut watcher = SettingsWatcher::new(paths.clone());
+        watcher.poll().unwrap();
+
+        write_section(paths.user.as_ref().unwrap(), SETTINGS);
+        assert!(!watcher.poll().unwrap());
+
+        remove(&paths);
+    }
+
+    #[test]
+    fn test_poll_detects_same_length_edit_with_same_mtime() {
+        let paths = settings_paths("same-length");
+        let user = paths.user.clone().unwrap();
+        let mut watcher = SettingsWatcher::new(paths.clone());
+        watcher.poll().unwrap();
+        let modified = std::fs::metadata(&user).unwrap().modified().unwrap();
+
+        write_section(&user, &SETTINGS.replace("gpt-4", "gpt-5"));
+        std::fs::File::options()
+            .write(true)
+            .open(&user)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+        assert!(watcher.poll().unwrap());
+
+        remove(&paths);
+    }
+
+    #[test]
+    fn test_poll_reports_created_and_removed_files() {
+        let paths = settings_paths("created");
+        let project = paths.project.clone().unwrap();
+        let mut watcher = SettingsWatcher::new(paths.clone());
+        watcher.poll().unwrap();
+
+        write_section(&project, "{}");
+        assert!(watcher.poll().unwrap());
+        assert!(!watcher.poll().unwrap());
+
+        std::fs::remove_file(&project).unwrap();
+        assert!(watcher.poll().unwrap());
+        assert!(!watcher.poll().unwrap());
+
+        remove(&paths);
+    }
+
+    #[test]
+    fn test_poll_reload_swaps_valid_config() {
+        let paths = settings_paths("reload");
+        let mut manager = ConfigManager::initialize_layered(&paths, Vec::new()).unwrap();
+        let mut watcher = SettingsWatcher::new(paths.clone());
+        assert_eq!(watcher.poll_reload(&mut manager).unwrap(), None);
+
+        write_section(
+            paths.user.as_ref().unwrap(),
+            &SETTINGS.replace("gpt-4", "gpt-4o"),
+        );
+        let change = watcher.poll_reload(&mut manager).unwrap().unwrap();
+        assert_eq!(change.changed, vec!["openai.model"]);
+        assert_eq!(manager.get_active_provider().unwrap().model(), "gpt-4o");
+
+        remove(&paths);
+    }
+
+    #[test]
+    fn test_poll_reload_merges_project_settings() {
+        let paths = settings_paths("layers");
+        let project = paths.project.clone().unwrap();
+        let mut manager = ConfigManager::initialize_layered(&paths, Vec::new()).unwrap();
+        let mut watcher = SettingsWatcher::new(paths.clone());
+        watcher.poll().unwrap();
+
+        write_section(&project, r#"{"openai": {"model": "gpt-4o-mini"}}"#);
+        let change = watcher.poll_reload(&mut manager).unwrap().unwrap();
+        assert_eq!(change.changed, vec!["openai.model"]);
+        let provider = manager.get_active_provider().unwrap();
+        assert_eq!(provider.model(), "gpt-4o-mini");
+        assert_eq!(provider.api_key(), "sk-test-key");
+        assert_eq!(
+            manager.source_of("/openai/model"),
+            Some(&ConfigSource::Project(project.clone()))
+        );
+
+        std::fs::remove_file(&project).unwrap();
+        let change = watcher.poll_reload(&mut manager).unwrap().unwrap();
+        assert_eq!(change.changed, vec!["openai.model"]);
+        assert_eq!(manager.get_active_provider().unwrap().model(), "gpt-4");
+
+        remove(&paths);
+    }
+
+    #[test]
+    fn test_poll_reload_keeps_last_good_config() {
+        let paths = settings_paths("invalid");
+        let user = paths.user.clone().unwrap();
+        let mut manager = ConfigManager::initialize_layered(&paths, Vec::new()).unwrap();
+        let mut watcher = SettingsWatcher::new(paths.clone());
+        watcher.poll().unwrap();
+
+        write_section(&user, &SETTINGS.replace("sk-test-key", ""));
+        assert!(watcher.poll_reload(&mut manager).is_err());
+        assert_eq!(
+            manager.get_active_provider().unwrap().api_key(),
+            "sk-test-key"
+        );
+        assert_eq!(watcher.poll_reload(&mut manager).unwrap(), None);
+
+        std::fs::write(&user, r#"{"zed_copilot": {"enabled": true"#).unwrap();
+        assert!(watcher.poll_reload(&mut manager).is_err());
+        assert_eq!(manager.get_active_provider().unwrap().model(), "gpt-4");
+
+        remove(&paths);
+    }
+}
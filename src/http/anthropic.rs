@@ -1,47 +1,145 @@
+use crate::http::metrics::{self, MetricLabels, Metrics};
+use crate::http::resumable::{resumable_stream, Resumption};
+use crate::http::retry::RetryPolicy;
+use crate::http::wire_log::{SdkWireLog, WireLog};
 use crate::providers::error::{ProviderError, ProviderResult};
 use anthropic_rust::{
     client::Client,
-    types::{ContentBlock, Model},
+    types::{ChatRequest, ContentBlock, Model},
 };
 use futures::Stream;
 use std::pin::Pin;
+use std::time::{Instant, SystemTime};
 
 pub struct AnthropicHttpClient {
     client: Client,
+    api_base: String,
+    http_client: Option<reqwest::Client>,
+    retry_policy: RetryPolicy,
+    wire_log: Option<SdkWireLog>,
+    metrics: Option<Metrics>,
+    temperature: Option<f32>,
+    max_tokens: Option<u32>,
 }
 
 impl AnthropicHttpClient {
-    pub fn new(_api_base: String) -> ProviderResult<Self> {
-        let client = Client::builder()
+    pub fn new(api_base: String) -> ProviderResult<Self> {
+        Ok(AnthropicHttpClient {
+            client: Self::build_client(None, None)?,
+            api_base,
+            http_client: None,
+            retry_policy: RetryPolicy::default(),
+            wire_log: None,
+            metrics: None,
+            temperature: None,
+            max_tokens: None,
+        })
+    }
+
+    pub fn with_http_client(self, http_client: reqwest::Client) -> ProviderResult<Self> {
+        Ok(AnthropicHttpClient {
+            client: Self::build_client(Some(http_client.clone()), self.max_tokens)?,
+            http_client: Some(http_client),
+            ..self
+        })
+    }
+
+    /// Overrides the vendor defaults for temperature and completion length.
+    pub fn with_generation(
+        self,
+        temperature: Option<f32>,
+        max_tokens: Option<u32>,
+    ) -> ProviderResult<Self> {
+        Ok(AnthropicHttpClient {
+            client: Self::build_client(self.http_client.clone(), max_tokens)?,
+            temperature,
+            max_tokens,
+            ..self
+        })
+    }
+
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    pub fn with_metrics(mut self, metrics: Metrics) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Logs each messages call to `wire_log`.
+    pub fn with_wire_log(mut self, wire_log: WireLog) -> Self {
+        let url = format!("{}/messages", self.api_base.trim_end_matches('/'));
+        self.wire_log = Some(SdkWireLog::new(wire_log, url));
+        self
+    }
+
+    fn build_client(
+        http_client: Option<reqwest::Client>,
+        max_tokens: Option<u32>,
+    ) -> ProviderResult<Client> {
+        let mut builder = Client::builder()
             .api_key("sk-ant-test-key")
-            .model(Model::Claude35Sonnet20241022)
-            .build()
-            .map_err(|e| {
-                ProviderError::ConfigError(format!("Failed to create Anthropic client: {}", e))
-            })?;
+            .model(Model::Claude35Sonnet20241022);
 
-        Ok(AnthropicHttpClient { client })
+        if let Some(http_client) = http_client {
+            builder = builder.http_client(http_client);
+        }
+        if let Some(max_tokens) = max_tokens {
+            builder = builder.max_tokens(max_tokens);
+        }
+
+        builder.build().map_err(|e| {
+            ProviderError::ConfigError(format!("Failed to create Anthropic client: {}", e))
+        })
     }
 
     pub async fn complete(
         &self,
         prompt: &str,
-        _model: &str,
-        _api_key: &str,
+        model: &str,
+        api_key: &str,
     ) -> ProviderResult<String> {
-        let request = self
+        let labels = MetricLabels::new("anthropic", model);
+        let started = Instant::now();
+        if let Some(metrics) = &self.metrics {
+            metrics.record_request(&labels);
+        }
+
+        let mut builder = self
             .client
             .chat_builder()
-            .user_message(ContentBlock::text(prompt.to_string()))
-            .build();
+            .user_message(ContentBlock::text(prompt.to_string()));
+        if let Some(temperature) = self.temperature {
+            builder = builder.temperature(temperature);
+        }
+        let request = builder.build();
+        let started_at = SystemTime::now();
 
-        let response = self
-            .client
-            .execute_chat(request)
-            .await
-            .map_err(|e| map_anthropic_error(e))?;
+        let result = self.client.execute_chat(request.clone()).await;
 
-        response
+        if let Some(wire_log) = &self.wire_log {
+            let outcome = match &result {
+                Ok(response) => Ok(serde_json::to_string(response).unwrap_or_default()),
+                Err(e) => Err(e.to_string()),
+            };
+            wire_log.record(&request, api_key, started_at, started.elapsed(), outcome);
+        }
+        let result = result.map_err(map_anthropic_error);
+
+        if let Some(metrics) = &self.metrics {
+            metrics.record_outcome(&labels, started, &result);
+            if let Ok(response) = &result {
+                metrics.record_tokens(
+                    &labels,
+                    response.usage.input_tokens.into(),
+                    response.usage.output_tokens.into(),
+                );
+            }
+        }
+
+        result?
             .content
             .first()
             .and_then(|block| {
@@ -59,41 +157,89 @@ impl AnthropicHttpClient {
     pub async fn complete_stream(
         &self,
         prompt: &str,
-        _model: &str,
-        _api_key: &str,
+        model: &str,
+        api_key: &str,
     ) -> ProviderResult<Pin<Box<dyn Stream<Item = ProviderResult<String>> + Send>>> {
-        let request = self
-            .client
-            .chat_builder()
-            .user_message(ContentBlock::text(prompt.to_string()))
-            .build();
+        let client = self.client.clone();
+        let wire_log = self.wire_log.clone();
+        let prompt = prompt.to_string();
+        let api_key = api_key.to_string();
+        let temperature = self.temperature;
 
-        let stream = self
-            .client
-            .stream_chat(request)
-            .await
-            .map_err(|e| map_anthropic_error(e))?;
-
-        let boxed_stream = Box::pin(futures::stream::unfold(stream, |mut stream| async move {
-            match futures::StreamExt::next(&mut stream).await {
-                Some(Ok(event)) => {
-                    use anthropic_rust::ContentDelta;
-                    use anthropic_rust::StreamEvent;
-
-                    match event {
-                        StreamEvent::ContentBlockDelta { delta, .. } => match delta {
-                            ContentDelta::TextDelta { text } => Some((Ok(text), stream)),
-                        },
-                        _ => Some((Ok(String::new()), stream)),
-                    }
+        let labels = MetricLabels::new("anthropic", model);
+        let open = metrics::count_reopens(self.metrics.clone(), labels.clone(), move |prefill| {
+            let request = build_stream_request(&client, prompt.clone(), prefill, temperature);
+            open_stream(client.clone(), request, wire_log.clone(), api_key.clone())
+        });
+
+        metrics::observe_stream(
+            self.metrics.clone(),
+            labels,
+            resumable_stream(self.retry_policy.clone(), Resumption::Prefill, open),
+        )
+        .await
+    }
+}
+
+fn build_stream_request(
+    client: &Client,
+    prompt: String,
+    prefill: String,
+    temperature: Option<f32>,
+) -> ChatRequest {
+    let mut builder = client
+        .chat_builder()
+        .user_message(ContentBlock::text(prompt));
+
+    if let Some(temperature) = temperature {
+        builder = builder.temperature(temperature);
+    }
+
+    if !prefill.is_empty() {
+        builder = builder.assistant_message(ContentBlock::text(prefill));
+    }
+    builder.build()
+}
+
+async fn open_stream(
+    client: Client,
+    request: ChatRequest,
+    wire_log: Option<SdkWireLog>,
+    api_key: String,
+) -> ProviderResult<Pin<Box<dyn Stream<Item = ProviderResult<String>> + Send>>> {
+    let started_at = SystemTime::now();
+    let timer = Instant::now();
+
+    let result = client.stream_chat(request.clone()).await;
+
+    if let Some(wire_log) = &wire_log {
+        let outcome = match &result {
+            Ok(_) => Ok(String::new()),
+            Err(e) => Err(e.to_string()),
+        };
+        wire_log.record(&request, &api_key, started_at, timer.elapsed(), outcome);
+    }
+    let stream = result.map_err(map_anthropic_error)?;
+
+    let boxed_stream = Box::pin(futures::stream::unfold(stream, |mut stream| async move {
+        match futures::StreamExt::next(&mut stream).await {
+            Some(Ok(event)) => {
+                use anthropic_rust::ContentDelta;
+                use anthropic_rust::StreamEvent;
+
+                match event {
+                    StreamEvent::ContentBlockDelta { delta, .. } => match delta {
+                        ContentDelta::TextDelta { text } => Some((Ok(text), stream)),
+                    },
+                    _ => Some((Ok(String::new()), stream)),
                 }
-                Some(Err(e)) => Some((Err(map_anthropic_error(e)), stream)),
-                None => None,
             }
-        }));
+            Some(Err(e)) => Some((Err(map_anthropic_error(e)), stream)),
+            None => None,
+        }
+    }));
 
-        Ok(boxed_stream)
-    }
+    Ok(boxed_stream)
 }
 
 fn map_anthropic_error(error: anthropic_rust::Error) -> ProviderError {
@@ -123,6 +269,14 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_anthropic_http_client_with_http_client() {
+        let result = AnthropicHttpClient::new("https://api.anthropic.com/v1".to_string())
+            .unwrap()
+            .with_http_client(reqwest::Client::new());
+        assert!(result.is_ok());
+    }
+
     #[test]
     fn test_map_anthropic_error_network() {
         let error = anthropic_rust::Error::Network("connection failed".to_string());
@@ -0,0 +1,292 @@
+use crate::providers::error::{ProviderError, ProviderResult};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, AUTHORIZATION};
+use reqwest::{Request, Url};
+use std::fmt;
+use std::sync::Arc;
+
+const REDACTED: &str = "[REDACTED]";
+const LOGGABLE_HEADERS: &[&str] = &[
+    "accept",
+    "anthropic-version",
+    "content-length",
+    "content-type",
+    "date",
+    "request-id",
+    "retry-after",
+    "user-agent",
+    "x-request-id",
+];
+
+/// Computes request signatures for gateways that authenticate whole requests
+/// rather than a static credential. Runs after all other headers are set.
+pub trait RequestSigner: Send + Sync {
+    fn sign(&self, request: &mut Request, api_key: &str) -> ProviderResult<()>;
+}
+
+#[derive(Clone, Default)]
+pub enum AuthScheme {
+    #[default]
+    Bearer,
+    Header(String),
+    QueryParam(String),
+    None,
+    Signed(Arc<dyn RequestSigner>),
+}
+
+impl AuthScheme {
+    pub fn anthropic() -> Self {
+        AuthScheme::Header("x-api-key".to_string())
+    }
+
+    pub fn azure() -> Self {
+        AuthScheme::Header("api-key".to_string())
+    }
+
+    pub fn gemini() -> Self {
+        AuthScheme::QueryParam("key".to_string())
+    }
+
+    pub fn apply(&self, request: &mut Request, api_key: &str) -> ProviderResult<()> {
+        match self {
+            AuthScheme::Bearer => {
+                let value = header_value(&format!("Bearer {}", api_key))?;
+                request.headers_mut().insert(AUTHORIZATION, value);
+            }
+            AuthScheme::Header(name) => {
+                let name = header_name(name)?;
+                request.headers_mut().insert(name, header_value(api_key)?);
+            }
+            AuthScheme::QueryParam(name) => {
+                request
+                    .url_mut()
+                    .query_pairs_mut()
+                    .append_pair(name, api_key);
+            }
+            AuthScheme::None => {}
+            AuthScheme::Signed(signer) => signer.sign(request, api_key)?,
+        }
+
+        Ok(())
+    }
+
+    fn query_param(&self) -> Option<&str> {
+        match self {
+            AuthScheme::QueryParam(name) => Some(name),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Debug for AuthScheme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuthScheme::Bearer => write!(f, "Bearer"),
+            AuthScheme::Header(name) => f.debug_tuple("Header").field(name).finish(),
+            AuthScheme::QueryParam(name) => f.debug_tuple("QueryParam").field(name).finish(),
+            AuthScheme::None => write!(f, "None"),
+            AuthScheme::Signed(_) => write!(f, "Signed"),
+        }
+    }
+}
+
+pub fn header_name(name: &str) -> ProviderResult<HeaderName> {
+    HeaderName::from_bytes(name.as_bytes())
+        .map_err(|e| ProviderError::ConfigError(format!("Invalid header name '{}': {}", name, e)))
+}
+
+pub fn header_value(value: &str) -> ProviderResult<HeaderValue> {
+    let mut value = HeaderValue::from_str(value)
+        .map_err(|e| ProviderError::ConfigError(format!("Invalid header value: {}", e)))?;
+    value.set_sensitive(true);
+    Ok(value)
+}
+
+pub fn redact_headers(headers: &HeaderMap) -> String {
+    redacted_header_pairs(headers)
+        .iter()
+        .map(|(name, value)| format!("{}: {}", name, value))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Header name/value pairs with everything outside a small allowlist of
+/// non-sensitive headers replaced by `[REDACTED]`.
+pub fn redacted_header_pairs(headers: &HeaderMap) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            let shown = if LOGGABLE_HEADERS.contains(&name.as_str()) && !value.is_sensitive() {
+                value.to_str().unwrap_or(REDACTED)
+            } else {
+                REDACTED
+            };
+            (name.to_string(), shown.to_string())
+        })
+        .collect()
+}
+
+/// Replaces every occurrence of `secret` in `text`, for payloads that may
+/// echo the credential back.
+pub fn redact_secret(text: &str, secret: &str) -> String {
+    if secret.is_empty() {
+        return text.to_string();
+    }
+    text.replace(secret, REDACTED)
+}
+
+pub fn redact_url(url: &Url, auth_scheme: &AuthScheme) -> String {
+    let Some(param) = auth_scheme.query_param() else {
+        return url.to_string();
+    };
+
+    let mut redacted = url.clone();
+    let pairs: Vec<(String, String)> = url
+        .query_pairs()
+        .map(|(key, value)| {
+            let value = if key == param {
+                REDACTED.to_string()
+            } else {
+                value.into_owned()
+            };
+            (key.into_owned(), value)
+        })
+        .collect();
+    redacted.query_pairs_mut().clear().extend_pairs(pairs);
+    redacted.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::CONTENT_TYPE;
+    use reqwest::Method;
+
+    fn request(url: &str) -> Request {
+        Request::new(Method::POST, Url::parse(url).unwrap())
+    }
+
+    #[test]
+    fn test_bearer_scheme() {
+        let mut req = request("https://api.openai.com/v1/chat/completions");
+        AuthScheme::Bearer.apply(&mut req, "sk-test").unwrap();
+        assert_eq!(req.headers()["authorization"], "Bearer sk-test");
+    }
+
+    #[test]
+    fn test_default_scheme_is_bearer() {
+        assert!(matches!(AuthScheme::default(), AuthScheme::Bearer));
+    }
+
+    #[test]
+    fn test_header_scheme() {
+        let mut req = request("https://api.anthropic.com/v1/messages");
+        AuthScheme::anthropic().apply(&mut req, "sk-ant").unwrap();
+        assert_eq!(req.headers()["x-api-key"], "sk-ant");
+        assert!(req.headers().get("authorization").is_none());
+    }
+
+    #[test]
+    fn test_azure_scheme() {
+        let mut req = request("https://example.openai.azure.com/openai/deployments/gpt-4");
+        AuthScheme::azure().apply(&mut req, "azure-key").unwrap();
+        assert_eq!(req.headers()["api-key"], "azure-key");
+    }
+
+    #[test]
+    fn test_query_param_scheme() {
+        let mut req = request("https://generativelanguage.googleapis.com/v1/models?alt=sse");
+        AuthScheme::gemini().apply(&mut req, "g-key").unwrap();
+        assert_eq!(req.url().query(), Some("alt=sse&key=g-key"));
+        assert!(req.headers().is_empty());
+    }
+
+    #[test]
+    fn test_none_scheme() {
+        let mut req = request("http://localhost:11434/api/chat");
+        AuthScheme::None.apply(&mut req, "ignored").unwrap();
+        assert!(req.headers().is_empty());
+        assert_eq!(req.url().query(), None);
+    }
+
+    #[test]
+    fn test_signed_scheme() {
+        struct PathSigner;
+
+        impl RequestSigner for PathSigner {
+            fn sign(&self, request: &mut Request, api_key: &str) -> ProviderResult<()> {
+                let signature = format!("{}:{}", api_key, request.url().path());
+                request
+                    .headers_mut()
+                    .insert("x-signature", header_value(&signature)?);
+                Ok(())
+            }
+        }
+
+        let mut req = request("https://gateway.example.com/v1/chat");
+        AuthScheme::Signed(Arc::new(PathSigner))
+            .apply(&mut req, "secret")
+            .unwrap();
+        assert_eq!(req.headers()["x-signature"], "secret:/v1/chat");
+    }
+
+    #[test]
+    fn test_invalid_header_name() {
+        let mut req = request("https://api.example.com");
+        let result = AuthScheme::Header("bad header".to_string()).apply(&mut req, "key");
+        assert!(matches!(result, Err(ProviderError::ConfigError(_))));
+    }
+
+    #[test]
+    fn test_invalid_header_value() {
+        let mut req = request("https://api.example.com");
+        let result = AuthScheme::Bearer.apply(&mut req, "key\nInjected: yes");
+        assert!(matches!(result, Err(ProviderError::ConfigError(_))));
+    }
+
+    #[test]
+    fn test_redact_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        headers.insert(AUTHORIZATION, HeaderValue::from_static("Bearer sk-secret"));
+        headers.insert("x-gateway-tenant", HeaderValue::from_static("team-a"));
+
+        let redacted = redact_headers(&headers);
+        assert!(redacted.contains("content-type: application/json"));
+        assert!(redacted.contains("authorization: [REDACTED]"));
+        assert!(redacted.contains("x-gateway-tenant: [REDACTED]"));
+        assert!(!redacted.contains("sk-secret"));
+        assert!(!redacted.contains("team-a"));
+    }
+
+    #[test]
+    fn test_redact_secret() {
+        assert_eq!(
+            redact_secret("{\"key\":\"sk-secret\"}", "sk-secret"),
+            "{\"key\":\"[REDACTED]\"}"
+        );
+        assert_eq!(redact_secret("unchanged", ""), "unchanged");
+    }
+
+    #[test]
+    fn test_redact_url_query_param() {
+        let url = Url::parse("https://example.com/v1?alt=sse&key=g-secret").unwrap();
+        let redacted = redact_url(&url, &AuthScheme::gemini());
+        assert!(redacted.contains("alt=sse"));
+        assert!(!redacted.contains("g-secret"));
+    }
+
+    #[test]
+    fn test_redact_url_without_query_auth() {
+        let url = Url::parse("https://example.com/v1?key=visible").unwrap();
+        assert_eq!(redact_url(&url, &AuthScheme::Bearer), url.to_string());
+    }
+
+    #[test]
+    fn test_debug_does_not_expose_signer() {
+        assert_eq!(
+            format!("{:?}", AuthScheme::anthropic()),
+            "Header(\"x-api-key\")"
+        );
+        assert_eq!(format!("{:?}", AuthScheme::None), "None");
+    }
+}
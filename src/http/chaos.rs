@@ -0,0 +1,678 @@
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, CONTENT_TYPE};
+use reqwest::{Method, StatusCode};
+use std::io;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+const HOP_BY_HOP: &[&str] = &[
+    "connection",
+    "content-length",
+    "host",
+    "keep-alive",
+    "transfer-encoding",
+];
+
+/// Faults applied to a single proxied request. Faults combine: a response can
+/// be delayed, corrupted, truncated and dripped at the same time.
+#[derive(Debug, Clone, Default)]
+pub struct Faults {
+    latency: Option<Duration>,
+    reset: bool,
+    status: Option<u16>,
+    malformed_json: bool,
+    truncate_after: Option<usize>,
+    drip: Option<(usize, Duration)>,
+}
+
+impl Faults {
+    pub fn none() -> Self {
+        Self::default()
+    }
+
+    /// Delays the response headers.
+    pub fn latency(mut self, latency: Duration) -> Self {
+        self.latency = Some(latency);
+        self
+    }
+
+    /// Resets the connection without sending a response.
+    pub fn reset(mut self) -> Self {
+        self.reset = true;
+        self
+    }
+
+    /// Answers with `status` instead of forwarding upstream. 429 responses
+    /// carry `Retry-After: 1`.
+    pub fn status(mut self, status: u16) -> Self {
+        self.status = Some(status);
+        self
+    }
+
+    /// Cuts every JSON document in the body (or every SSE `data:` payload)
+    /// in half.
+    pub fn malformed_json(mut self) -> Self {
+        self.malformed_json = true;
+        self
+    }
+
+    /// Announces the full `Content-Length` but closes the connection after
+    /// `bytes` bytes of body.
+    pub fn truncate_after(mut self, bytes: usize) -> Self {
+        self.truncate_after = Some(bytes);
+        self
+    }
+
+    /// Writes the body `chunk_size` bytes at a time, pausing `interval`
+    /// between chunks.
+    pub fn slow_drip(mut self, chunk_size: usize, interval: Duration) -> Self {
+        self.drip = Some((chunk_size.max(1), interval));
+        self
+    }
+}
+
+/// Per-request fault schedule. Request `n` gets the `n`th scripted entry;
+/// once the script is exhausted every request gets the fallback, which
+/// defaults to passing traffic through untouched.
+#[derive(Debug, Clone, Default)]
+pub struct ChaosPlan {
+    script: Vec<Faults>,
+    fallback: Faults,
+}
+
+impl ChaosPlan {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn always(faults: Faults) -> Self {
+        ChaosPlan {
+            script: Vec::new(),
+            fallback: faults,
+        }
+    }
+
+    pub fn then(mut self, faults: Faults) -> Self {
+        self.script.push(faults);
+        self
+    }
+
+    /// Schedules `count` consecutive requests with the same faults, e.g. a
+    /// burst of 503s.
+    pub fn burst(mut self, faults: Faults, count: usize) -> Self {
+        self.script.extend(std::iter::repeat_n(faults, count));
+        self
+    }
+
+    pub fn otherwise(mut self, faults: Faults) -> Self {
+        self.fallback = faults;
+        self
+    }
+
+    fn faults_for(&self, request: usize) -> &Faults {
+        self.script.get(request).unwrap_or(&self.fallback)
+    }
+}
+
+/// Local HTTP/1.1 proxy that forwards to `upstream` and injects faults at
+/// the socket level. Point a provider's `api_base` at `uri()` to exercise
+/// the real client stack — reqwest, the vendor SDKs and our error mapping.
+pub struct ChaosProxy {
+    addr: SocketAddr,
+    requests: Arc<AtomicUsize>,
+    handle: JoinHandle<()>,
+}
+
+impl ChaosProxy {
+    pub async fn start(upstream: &str, plan: ChaosPlan) -> io::Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let requests = Arc::new(AtomicUsize::new(0));
+
+        let upstream = Arc::new(upstream.trim_end_matches('/').to_string());
+        let plan = Arc::new(plan);
+        let client = reqwest::Client::builder()
+            .no_proxy()
+            .build()
+            .map_err(io::Error::other)?;
+        let counter = Arc::clone(&requests);
+
+        let handle = tokio::spawn(async move {
+            while let Ok((socket, _)) = listener.accept().await {
+                let index = counter.fetch_add(1, Ordering::SeqCst);
+                let faults = plan.faults_for(index).clone();
+                let upstream = Arc::clone(&upstream);
+                let client = client.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = handle_connection(socket, &client, &upstream, faults).await {
+                        log::debug!("Chaos proxy connection ended: {}", e);
+                    }
+                });
+            }
+        });
+
+        Ok(ChaosProxy {
+            addr,
+            requests,
+            handle,
+        })
+    }
+
+    pub fn uri(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// Number of connections accepted so far, i.e. requests attempted.
+    pub fn requests(&self) -> usize {
+        self.requests.load(Ordering::SeqCst)
+    }
+}
+
+impl Drop for ChaosProxy {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+struct ProxiedRequest {
+    method: Method,
+    path: String,
+    headers: HeaderMap,
+    body: Vec<u8>,
+}
+
+struct ProxiedResponse {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Vec<u8>,
+}
+
+async fn handle_connection(
+    mut socket: TcpStream,
+    client: &reqwest::Client,
+    upstream: &str,
+    faults: Faults,
+) -> io::Result<()> {
+    let request = read_request(&mut socket).await?;
+
+    if let Some(latency) = faults.latency {
+        tokio::time::sleep(latency).await;
+    }
+
+    if faults.reset {
+        // A zero linger turns the close into a TCP RST; it cannot block on
+        // drop, which is what the deprecation warns about.
+        #[allow(deprecated)]
+        socket.set_linger(Some(Duration::ZERO))?;
+        return Ok(());
+    }
+
+    let mut response = match faults.status {
+        Some(status) => injected_status(status),
+        None => forward(client, upstream, request).await?,
+    };
+
+    if faults.malformed_json {
+        let is_sse = response
+            .headers
+            .get(CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| v.starts_with("text/event-stream"));
+        response.body = corrupt_json(&response.body, is_sse);
+    }
+
+    write_response(&mut socket, &response, &faults).await
+}
+
+async fn read_request(socket: &mut TcpStream) -> io::Result<ProxiedRequest> {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 4096];
+    let head_end = loop {
+        if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos + 4;
+        }
+        let n = socket.read(&mut chunk).await?;
+        if n == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "connection closed before request head",
+            ));
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    };
+
+    let head = String::from_utf8_lossy(&buf[..head_end]).into_owned();
+    let mut lines = head.split("\r\n");
+    let mut request_line = lines.next().unwrap_or_default().split_whitespace();
+    let method = Method::from_bytes(request_line.next().unwrap_or("GET").as_bytes())
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    let path = request_line.next().unwrap_or("/").to_string();
+
+    let mut headers = HeaderMap::new();
+    for line in lines.filter(|line| !line.is_empty()) {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        if let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(name.trim().as_bytes()),
+            HeaderValue::from_str(value.trim()),
+        ) {
+            headers.append(name, value);
+        }
+    }
+
+    let content_length = headers
+        .get("content-length")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<usize>().ok())
+        .unwrap_or(0);
+    let mut body = buf[head_end..].to_vec();
+    while body.len() < content_length {
+        let n = socket.read(&mut chunk).await?;
+        if n == 0 {
+            break;
+        }
+        body.extend_from_slice(&chunk[..n]);
+    }
+
+    Ok(ProxiedRequest {
+        method,
+        path,
+        headers,
+        body,
+    })
+}
+
+async fn forward(
+    client: &reqwest::Client,
+    upstream: &str,
+    request: ProxiedRequest,
+) -> io::Result<ProxiedResponse> {
+    let mut headers = request.headers;
+    for name in HOP_BY_HOP {
+        headers.remove(*name);
+    }
+
+    let response = client
+        .request(request.method, format!("{}{}", upstream, request.path))
+        .headers(headers)
+        .body(request.body)
+        .send()
+        .await
+        .map_err(io::Error::other)?;
+
+    let status = response.status();
+    let headers = response.headers().clone();
+    let body = response.bytes().await.map_err(io::Error::other)?.to_vec();
+
+    Ok(ProxiedResponse {
+        status,
+        headers,
+        body,
+    })
+}
+
+fn injected_status(status: u16) -> ProxiedResponse {
+    let status = StatusCode::from_u16(status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+    let mut headers = HeaderMap::new();
+    headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    if status == StatusCode::TOO_MANY_REQUESTS {
+        headers.insert("retry-after", HeaderValue::from_static("1"));
+    }
+
+    let body = serde_json::json!({
+        "error": {
+            "message": format!("Injected {} from chaos proxy", status),
+            "type": "chaos_injected",
+        }
+    });
+
+    ProxiedResponse {
+        status,
+        headers,
+        body: body.to_string().into_bytes(),
+    }
+}
+
+fn corrupt_json(body: &[u8], is_sse: bool) -> Vec<u8> {
+    let text = String::from_utf8_lossy(body);
+    if !is_sse {
+        return truncate_in_half(&text).into_bytes();
+    }
+
+    text.split_inclusive('\n')
+        .map(|line| match line.strip_prefix("data: ") {
+            Some(data) if data.trim_start().starts_with('{') => {
+                let newline = &data[data.trim_end().len()..];
+                format!("data: {}{}", truncate_in_half(data.trim_end()), newline)
+            }
+            _ => line.to_string(),
+        })
+        .collect::<String>()
+        .into_bytes()
+}
+
+fn truncate_in_half(text: &str) -> String {
+    let mut cut = text.len() / 2;
+    while !text.is_char_boundary(cut) {
+        cut -= 1;
+    }
+    text[..cut].to_string()
+}
+
+async fn write_response(
+    socket: &mut TcpStream,
+    response: &ProxiedResponse,
+    faults: &Faults,
+) -> io::Result<()> {
+    let mut head = format!(
+        "HTTP/1.1 {} {}\r\n",
+        response.status.as_u16(),
+        response.status.canonical_reason().unwrap_or("Unknown")
+    );
+    for (name, value) in &response.headers {
+        if HOP_BY_HOP.contains(&name.as_str()) {
+            continue;
+        }
+        head.push_str(&format!(
+            "{}: {}\r\n",
+            name,
+            String::from_utf8_lossy(value.as_bytes())
+        ));
+    }
+    head.push_str(&format!(
+        "content-length: {}\r\nconnection: close\r\n\r\n",
+        response.body.len()
+    ));
+    socket.write_all(head.as_bytes()).await?;
+
+    let sent = faults
+        .truncate_after
+        .map_or(response.body.len(), |n| n.min(response.body.len()));
+    let body = &response.body[..sent];
+
+    match faults.drip {
+        Some((chunk_size, interval)) => {
+            for chunk in body.chunks(chunk_size) {
+                socket.write_all(chunk).await?;
+                socket.flush().await?;
+                tokio::time::sleep(interval).await;
+            }
+        }
+        None => socket.write_all(body).await?,
+    }
+
+    socket.flush().await?;
+    socket.shutdown().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::openai::OpenAiHttpClient;
+    use crate::http::{HttpClient, RetryPolicy};
+    use crate::providers::error::ProviderError;
+    use futures::StreamExt;
+    use std::time::Instant;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn sse_chunk(text: &str) -> String {
+        format!(
+            "data: {}\n\n",
+            serde_json::json!({
+                "id": "chatcmpl-1",
+                "object": "chat.completion.chunk",
+                "created": 1,
+                "model": "gpt-4",
+                "choices": [{"index": 0, "delta": {"content": text}, "finish_reason": null}]
+            })
+        )
+    }
+
+    async fn upstream_json() -> MockServer {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "id": "chatcmpl-1",
+                "object": "chat.completion",
+                "created": 1,
+                "model": "gpt-4",
+                "choices": [{
+                    "index": 0,
+                    "message": {"role": "assistant", "content": "pong"},
+                    "finish_reason": "stop"
+                }]
+            })))
+            .mount(&server)
+            .await;
+        server
+    }
+
+    async fn upstream_sse(chunks: &[&str]) -> MockServer {
+        let server = MockServer::start().await;
+        let mut body: String = chunks.iter().map(|c| sse_chunk(c)).collect();
+        body.push_str("data: [DONE]\n\n");
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(body, "text/event-stream"))
+            .mount(&server)
+            .await;
+        server
+    }
+
+    fn fast_retries(max_retries: u32) -> RetryPolicy {
+        RetryPolicy::new(max_retries, 0, 0)
+    }
+
+    #[test]
+    fn test_plan_script_then_fallback() {
+        let plan = ChaosPlan::new()
+            .burst(Faults::none().status(503), 2)
+            .then(Faults::none().reset())
+            .otherwise(Faults::none().status(429));
+
+        assert_eq!(plan.faults_for(0).status, Some(503));
+        assert_eq!(plan.faults_for(1).status, Some(503));
+        assert!(plan.faults_for(2).reset);
+        assert_eq!(plan.faults_for(3).status, Some(429));
+        assert_eq!(plan.faults_for(99).status, Some(429));
+    }
+
+    #[test]
+    fn test_corrupt_json() {
+        assert_eq!(corrupt_json(br#"{"a":"bcd"}"#, false), br#"{"a":"#.to_vec());
+
+        let sse = b"event: x\ndata: {\"a\":\"bcd\"}\n\ndata: [DONE]\n\n";
+        assert_eq!(
+            String::from_utf8(corrupt_json(sse, true)).unwrap(),
+            "event: x\ndata: {\"a\":\n\ndata: [DONE]\n\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_pass_through() {
+        let upstream = upstream_json().await;
+        let proxy = ChaosProxy::start(&upstream.uri(), ChaosPlan::new())
+            .await
+            .unwrap();
+
+        let body = HttpClient::default()
+            .post(&proxy.uri(), serde_json::json!({}), "sk-test")
+            .await
+            .unwrap();
+
+        assert!(body.contains("pong"));
+        assert_eq!(proxy.requests(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_server_error_burst_is_retried() {
+        let upstream = upstream_json().await;
+        let plan = ChaosPlan::new().burst(Faults::none().status(503), 2);
+        let proxy = ChaosProxy::start(&upstream.uri(), plan).await.unwrap();
+
+        let client = HttpClient::default().with_retry_policy(fast_retries(3));
+        let result = client
+            .post(&proxy.uri(), serde_json::json!({}), "sk-test")
+            .await;
+
+        assert!(result.is_ok());
+        assert_eq!(proxy.requests(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_server_error_burst_exhausts_retries() {
+        let upstream = upstream_json().await;
+        let plan = ChaosPlan::always(Faults::none().status(502));
+        let proxy = ChaosProxy::start(&upstream.uri(), plan).await.unwrap();
+
+        let client = HttpClient::default().with_retry_policy(fast_retries(2));
+        let result = client
+            .post(&proxy.uri(), serde_json::json!({}), "sk-test")
+            .await;
+
+        match result {
+            Err(ProviderError::ApiError(msg)) => assert!(msg.contains("502")),
+            other => panic!("Expected ApiError, got {:?}", other),
+        }
+        assert_eq!(proxy.requests(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_is_reported_as_client_error() {
+        let upstream = upstream_json().await;
+        let plan = ChaosPlan::new().burst(Faults::none().status(429), 3);
+        let proxy = ChaosProxy::start(&upstream.uri(), plan).await.unwrap();
+
+        let client = HttpClient::default().with_retry_policy(fast_retries(3));
+        let result = client
+            .post(&proxy.uri(), serde_json::json!({}), "sk-test")
+            .await;
+
+        match result {
+            Err(ProviderError::ApiError(msg)) => assert!(msg.contains("429")),
+            other => panic!("Expected ApiError, got {:?}", other),
+        }
+        assert_eq!(proxy.requests(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_connection_reset_is_retried() {
+        let upstream = upstream_json().await;
+        let plan = ChaosPlan::new().then(Faults::none().reset());
+        let proxy = ChaosProxy::start(&upstream.uri(), plan).await.unwrap();
+
+        let client = HttpClient::default().with_retry_policy(fast_retries(2));
+        let result = client
+            .post(&proxy.uri(), serde_json::json!({}), "sk-test")
+            .await;
+
+        assert!(result.is_ok());
+        assert_eq!(proxy.requests(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_latency() {
+        let upstream = upstream_json().await;
+        let plan = ChaosPlan::always(Faults::none().latency(Duration::from_millis(150)));
+        let proxy = ChaosProxy::start(&upstream.uri(), plan).await.unwrap();
+
+        let started = Instant::now();
+        HttpClient::default()
+            .post(&proxy.uri(), serde_json::json!({}), "sk-test")
+            .await
+            .unwrap();
+
+        assert!(started.elapsed() >= Duration::from_millis(150));
+    }
+
+    #[tokio::test]
+    async fn test_truncated_body_is_a_network_error() {
+        let upstream = upstream_json().await;
+        let plan = ChaosPlan::always(Faults::none().truncate_after(10));
+        let proxy = ChaosProxy::start(&upstream.uri(), plan).await.unwrap();
+
+        let client = HttpClient::default().with_retry_policy(fast_retries(1));
+        let result = client
+            .post(&proxy.uri(), serde_json::json!({}), "sk-test")
+            .await;
+
+        assert!(matches!(result, Err(ProviderError::NetworkError(_))));
+        assert_eq!(proxy.requests(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_malformed_json_is_a_parse_error() {
+        let upstream = upstream_json().await;
+        let plan = ChaosPlan::always(Faults::none().malformed_json());
+        let proxy = ChaosProxy::start(&upstream.uri(), plan).await.unwrap();
+
+        let client = OpenAiHttpClient::new(proxy.uri())
+            .unwrap()
+            .with_retry_policy(fast_retries(2));
+        let result = client.complete("ping", "gpt-4", "sk-test").await;
+
+        assert!(matches!(result, Err(ProviderError::ParseError(_))));
+        assert_eq!(proxy.requests(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_slow_drip_stream_is_reassembled() {
+        let upstream = upstream_sse(&["Hello", ", ", "world"]).await;
+        let plan = ChaosPlan::always(Faults::none().slow_drip(7, Duration::from_millis(1)));
+        let proxy = ChaosProxy::start(&upstream.uri(), plan).await.unwrap();
+
+        let client = OpenAiHttpClient::new(proxy.uri()).unwrap();
+        let stream = client
+            .complete_stream("Hi", "gpt-4", "sk-test")
+            .await
+            .unwrap();
+
+        let output: Vec<String> = stream.map(|item| item.unwrap()).collect().await;
+        assert_eq!(output.concat(), "Hello, world");
+    }
+
+    #[tokio::test]
+    async fn test_truncated_stream_keeps_partial_response() {
+        let upstream = upstream_sse(&["Hello", " world"]).await;
+        let cut = sse_chunk("Hello").len() + 20;
+        let plan = ChaosPlan::always(Faults::none().truncate_after(cut));
+        let proxy = ChaosProxy::start(&upstream.uri(), plan).await.unwrap();
+
+        let client = OpenAiHttpClient::new(proxy.uri())
+            .unwrap()
+            .with_retry_policy(fast_retries(2));
+        let stream = client
+            .complete_stream("Hi", "gpt-4", "sk-test")
+            .await
+            .unwrap();
+
+        let items: Vec<_> = stream.collect().await;
+        let error = items.last().unwrap().as_ref().unwrap_err();
+        assert_eq!(error.partial_response(), Some("Hello"));
+    }
+
+    #[tokio::test]
+    async fn test_stream_truncated_before_first_token_is_retried() {
+        let upstream = upstream_sse(&["Hello", " world"]).await;
+        let plan = ChaosPlan::new().then(Faults::none().truncate_after(10));
+        let proxy = ChaosProxy::start(&upstream.uri(), plan).await.unwrap();
+
+        let client = OpenAiHttpClient::new(proxy.uri())
+            .unwrap()
+            .with_retry_policy(fast_retries(2));
+        let stream = client
+            .complete_stream("Hi", "gpt-4", "sk-test")
+            .await
+            .unwrap();
+
+        let output: Vec<String> = stream.map(|item| item.unwrap()).collect().await;
+        assert_eq!(output.concat(), "Hello world");
+        assert_eq!(proxy.requests(), 2);
+    }
+}
@@ -1,13 +1,23 @@
+use crate::config::NetworkConfig;
+use crate::http::auth::{self, AuthScheme};
+use crate::http::metrics::{self, MetricLabels, Metrics};
 use crate::http::retry::RetryPolicy;
+use crate::http::transport;
+use crate::http::wire_log::{WireLog, WireLogEntry, WireRequest, WireResponse};
 use crate::providers::error::{ProviderError, ProviderResult};
-use reqwest::Client;
-use std::time::Duration;
+use reqwest::header::{HeaderMap, CONTENT_TYPE};
+use reqwest::{Client, Request, StatusCode};
+use std::time::{Duration, Instant, SystemTime};
 
 #[derive(Clone)]
 pub struct HttpClient {
     client: Client,
     timeout: Duration,
     retry_policy: RetryPolicy,
+    auth_scheme: AuthScheme,
+    default_headers: HeaderMap,
+    metrics: Option<(Metrics, MetricLabels)>,
+    wire_log: Option<WireLog>,
 }
 
 impl Default for HttpClient {
@@ -16,6 +26,10 @@ impl Default for HttpClient {
             client: Client::new(),
             timeout: Duration::from_secs(30),
             retry_policy: RetryPolicy::default(),
+            auth_scheme: AuthScheme::default(),
+            default_headers: HeaderMap::new(),
+            metrics: None,
+            wire_log: None,
         }
     }
 }
@@ -30,6 +44,10 @@ impl HttpClient {
             client,
             timeout,
             retry_policy,
+            auth_scheme: AuthScheme::default(),
+            default_headers: HeaderMap::new(),
+            metrics: None,
+            wire_log: None,
         })
     }
 
@@ -38,24 +56,81 @@ impl HttpClient {
         self
     }
 
+    pub fn with_network_config(mut self, network: &NetworkConfig) -> ProviderResult<Self> {
+        self.client = transport::build_http_client(network)?;
+        self.wire_log = WireLog::from_network(network)?;
+        Ok(self)
+    }
+
+    pub fn with_auth_scheme(mut self, auth_scheme: AuthScheme) -> Self {
+        self.auth_scheme = auth_scheme;
+        self
+    }
+
+    pub fn with_default_header(mut self, name: &str, value: &str) -> ProviderResult<Self> {
+        self.default_headers
+            .insert(auth::header_name(name)?, auth::header_value(value)?);
+        Ok(self)
+    }
+
+    pub fn with_metrics(mut self, metrics: Metrics, labels: MetricLabels) -> Self {
+        self.metrics = Some((metrics, labels));
+        self
+    }
+
+    pub fn with_wire_log(mut self, wire_log: WireLog) -> Self {
+        self.wire_log = Some(wire_log);
+        self
+    }
+
     pub async fn post(
         &self,
         url: &str,
         body: serde_json::Value,
         api_key: &str,
+    ) -> ProviderResult<String> {
+        let started = Instant::now();
+        if let Some((metrics, labels)) = &self.metrics {
+            metrics.record_request(labels);
+        }
+
+        let result = self.post_with_retry(url, &body, api_key).await;
+
+        if let Some((metrics, labels)) = &self.metrics {
+            metrics.record_outcome(labels, started, &result);
+            let usage = result
+                .as_ref()
+                .ok()
+                .and_then(|response| serde_json::from_str(response).ok())
+                .and_then(|body| metrics::usage_from_response(&body));
+            if let Some((input, output)) = usage {
+                metrics.record_tokens(labels, input, output);
+            }
+        }
+
+        result
+    }
+
+    async fn post_with_retry(
+        &self,
+        url: &str,
+        body: &serde_json::Value,
+        api_key: &str,
     ) -> ProviderResult<String> {
         let mut attempt = 0;
 
         loop {
-            match self.execute_post_request(url, &body, api_key).await {
+            match self.execute_post_request(url, body, api_key).await {
                 Ok(response) => return Ok(response),
                 Err(e) => {
                     let is_transient = is_transient_error(&e);
 
                     if self.retry_policy.should_retry(attempt, is_transient) {
-                        let backoff = self.retry_policy.calculate_backoff(attempt);
-                        tokio::time::sleep(backoff).await;
+                        self.retry_policy.backoff(attempt).await;
                         attempt += 1;
+                        if let Some((metrics, labels)) = &self.metrics {
+                            metrics.record_retry(labels);
+                        }
                     } else {
                         return Err(e);
                     }
@@ -64,34 +139,63 @@ impl HttpClient {
         }
     }
 
-    async fn execute_post_request(
+    fn build_post_request(
         &self,
         url: &str,
         body: &serde_json::Value,
         api_key: &str,
-    ) -> ProviderResult<String> {
-        let response = self
+    ) -> ProviderResult<Request> {
+        let mut request = self
             .client
             .post(url)
-            .header("Authorization", format!("Bearer {}", api_key))
-            .header("Content-Type", "application/json")
+            .headers(self.default_headers.clone())
+            .header(CONTENT_TYPE, "application/json")
             .json(body)
-            .send()
-            .await
-            .map_err(|e| {
-                if e.is_timeout() {
-                    ProviderError::NetworkError("Request timeout".to_string())
-                } else {
-                    ProviderError::NetworkError(format!("HTTP request failed: {}", e))
+            .build()
+            .map_err(|e| ProviderError::ConfigError(format!("Invalid request: {}", e)))?;
+
+        self.auth_scheme.apply(&mut request, api_key)?;
+
+        log::debug!(
+            "POST {} [{}]",
+            auth::redact_url(request.url(), &self.auth_scheme),
+            auth::redact_headers(request.headers())
+        );
+
+        Ok(request)
+    }
+
+    async fn execute_post_request(
+        &self,
+        url: &str,
+        body: &serde_json::Value,
+        api_key: &str,
+    ) -> ProviderResult<String> {
+        let request = self.build_post_request(url, body, api_key)?;
+        let capture = self.wire_log.as_ref().map(|wire_log| {
+            let request = WireRequest::capture(&request, &self.auth_scheme, api_key);
+            (wire_log, request, SystemTime::now(), Instant::now())
+        });
+
+        let result = self.send(request).await;
+
+        if let Some((wire_log, request, started_at, timer)) = capture {
+            let outcome = match &result {
+                Ok((status, headers, body)) => {
+                    Ok(WireResponse::capture(*status, headers, body, api_key))
                 }
-            })?;
+                Err(e) => Err(auth::redact_secret(&e.to_string(), api_key)),
+            };
+            let entry = WireLogEntry::new(request, started_at, timer.elapsed(), outcome);
+            if let Err(e) = wire_log.record(&entry) {
+                log::warn!("Failed to write wire log: {}", e);
+            }
+        }
 
-        let status = response.status();
+        let (status, _, body) = result?;
 
         if status.is_success() {
-            response.text().await.map_err(|e| {
-                ProviderError::NetworkError(format!("Failed to read response body: {}", e))
-            })
+            Ok(body)
         } else if status.is_server_error() {
             Err(ProviderError::ApiError(format!("Server error: {}", status)))
         } else if status.is_client_error() {
@@ -104,6 +208,36 @@ impl HttpClient {
         }
     }
 
+    /// reqwest errors name the request URL, which holds the API key under
+    /// [`AuthScheme::QueryParam`], so it is stripped from them.
+    async fn send(&self, request: Request) -> ProviderResult<(StatusCode, HeaderMap, String)> {
+        let response = self.client.execute(request).await.map_err(|e| {
+            let e = e.without_url();
+            if e.is_timeout() {
+                ProviderError::NetworkError("Request timeout".to_string())
+            } else {
+                ProviderError::NetworkError(format!("HTTP request failed: {}", e))
+            }
+        })?;
+
+        let status = response.status();
+        let headers = response.headers().clone();
+        let body = match response.text().await {
+            Ok(body) => body,
+            Err(e) if status.is_success() => {
+                return Err(ProviderError::NetworkError(format!(
+                    "Failed to read response body: {}",
+                    e.without_url()
+                )))
+            }
+            // The status already decides the error; the body is only kept
+            // for the wire log.
+            Err(_) => String::new(),
+        };
+
+        Ok((status, headers, body))
+    }
+
     pub fn timeout(&self) -> Duration {
         self.timeout
     }
@@ -111,9 +245,25 @@ impl HttpClient {
     pub fn retry_policy(&self) -> &RetryPolicy {
         &self.retry_policy
     }
+
+    pub fn auth_scheme(&self) -> &AuthScheme {
+        &self.auth_scheme
+    }
+
+    pub fn default_headers(&self) -> &HeaderMap {
+        &self.default_headers
+    }
+
+    pub fn metrics(&self) -> Option<&Metrics> {
+        self.metrics.as_ref().map(|(metrics, _)| metrics)
+    }
+
+    pub fn wire_log(&self) -> Option<&WireLog> {
+        self.wire_log.as_ref()
+    }
 }
 
-fn is_transient_error(error: &ProviderError) -> bool {
+pub(crate) fn is_transient_error(error: &ProviderError) -> bool {
     match error {
         ProviderError::NetworkError(_) => true,
         ProviderError::ApiError(msg) => msg.contains("Server error") || msg.contains("timeout"),
@@ -145,6 +295,289 @@ mod tests {
         assert_eq!(client.retry_policy().max_retries(), 5);
     }
 
+    #[test]
+    fn test_http_client_with_network_config() {
+        let network = NetworkConfig {
+            proxy: Some("http://proxy.corp:3128".to_string()),
+            ..Default::default()
+        };
+        let client = HttpClient::default().with_network_config(&network);
+        assert!(client.is_ok());
+    }
+
+    #[test]
+    fn test_http_client_with_invalid_network_config() {
+        let network = NetworkConfig {
+            ca_certificates: vec!["/nonexistent/ca.pem".to_string()],
+            ..Default::default()
+        };
+        let client = HttpClient::default().with_network_config(&network);
+        assert!(client.is_err());
+    }
+
+    #[test]
+    fn test_build_post_request_default_bearer() {
+        let client = HttpClient::default();
+        let request = client
+            .build_post_request(
+                "https://api.openai.com/v1/chat/completions",
+                &serde_json::json!({"model": "gpt-4"}),
+                "sk-test",
+            )
+            .unwrap();
+
+        assert_eq!(request.headers()["authorization"], "Bearer sk-test");
+        assert_eq!(request.headers()["content-type"], "application/json");
+    }
+
+    #[test]
+    fn test_build_post_request_custom_scheme_and_headers() {
+        let client = HttpClient::default()
+            .with_auth_scheme(AuthScheme::anthropic())
+            .with_default_header("anthropic-version", "2023-06-01")
+            .unwrap()
+            .with_default_header("x-gateway-tenant", "team-a")
+            .unwrap();
+
+        let request = client
+            .build_post_request(
+                "https://api.anthropic.com/v1/messages",
+                &serde_json::json!({}),
+                "sk-ant",
+            )
+            .unwrap();
+
+        assert_eq!(request.headers()["x-api-key"], "sk-ant");
+        assert_eq!(request.headers()["anthropic-version"], "2023-06-01");
+        assert_eq!(request.headers()["x-gateway-tenant"], "team-a");
+        assert!(request.headers().get("authorization").is_none());
+    }
+
+    #[test]
+    fn test_build_post_request_query_param() {
+        let client = HttpClient::default().with_auth_scheme(AuthScheme::gemini());
+        let request = client
+            .build_post_request(
+                "https://generativelanguage.googleapis.com/v1/models/gemini-pro:generateContent",
+                &serde_json::json!({}),
+                "g-key",
+            )
+            .unwrap();
+
+        assert_eq!(request.url().query(), Some("key=g-key"));
+        assert!(request.headers().get("authorization").is_none());
+    }
+
+    #[test]
+    fn test_with_default_header_invalid_name() {
+        let result = HttpClient::default().with_default_header("bad header", "value");
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_post_sends_configured_auth() {
+        use wiremock::matchers::{header, method, query_param};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(header("api-key", "azure-key"))
+            .and(header("x-extra", "1"))
+            .and(query_param("api-version", "2024-02-01"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("ok"))
+            .mount(&server)
+            .await;
+
+        let client = HttpClient::default()
+            .with_auth_scheme(AuthScheme::azure())
+            .with_default_header("x-extra", "1")
+            .unwrap();
+        let url = format!("{}/openai/chat?api-version=2024-02-01", server.uri());
+
+        let response = client
+            .post(&url, serde_json::json!({}), "azure-key")
+            .await
+            .unwrap();
+        assert_eq!(response, "ok");
+    }
+
+    #[tokio::test]
+    async fn test_post_records_metrics() {
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(503))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "usage": {"prompt_tokens": 12, "completion_tokens": 34}
+            })))
+            .mount(&server)
+            .await;
+
+        let metrics = Metrics::new();
+        let client = HttpClient::default()
+            .with_retry_policy(RetryPolicy::new(2, 1, 1))
+            .with_metrics(metrics.clone(), MetricLabels::new("openai", "gpt-4"));
+
+        client
+            .post(&server.uri(), serde_json::json!({}), "sk-test")
+            .await
+            .unwrap();
+
+        let snapshot = metrics.snapshot();
+        let recorded = snapshot.get("openai", "gpt-4").unwrap();
+        assert_eq!(recorded.requests, 1);
+        assert_eq!(recorded.retries, 1);
+        assert_eq!(recorded.input_tokens, 12);
+        assert_eq!(recorded.output_tokens, 34);
+        assert_eq!(recorded.latency_seconds.count, 1);
+        assert!(recorded.errors.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_post_retry_schedule_on_virtual_clock() {
+        use crate::http::clock::{FixedJitter, VirtualClock};
+        use std::sync::Arc;
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(503))
+            .expect(4)
+            .mount(&server)
+            .await;
+
+        let clock = VirtualClock::new();
+        let policy = RetryPolicy::new(3, 1000, 32000)
+            .with_clock(Arc::new(clock.clone()))
+            .with_jitter(Arc::new(FixedJitter(0.5)));
+        let client = HttpClient::default().with_retry_policy(policy);
+
+        let result = client
+            .post(&server.uri(), serde_json::json!({}), "sk-test")
+            .await;
+
+        assert!(result.is_err());
+        assert_eq!(
+            clock.sleeps(),
+            vec![Duration::from_millis(1000), Duration::from_millis(2000)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_post_records_final_error() {
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(401))
+            .mount(&server)
+            .await;
+
+        let metrics = Metrics::new();
+        let client = HttpClient::default()
+            .with_metrics(metrics.clone(), MetricLabels::new("openai", "gpt-4"));
+
+        assert!(client
+            .post(&server.uri(), serde_json::json!({}), "bad")
+            .await
+            .is_err());
+
+        let snapshot = metrics.snapshot();
+        let recorded = snapshot.get("openai", "gpt-4").unwrap();
+        assert_eq!(recorded.errors["api_error"], 1);
+        assert_eq!(recorded.retries, 0);
+    }
+
+    #[tokio::test]
+    async fn test_post_writes_redacted_wire_log() {
+        use crate::http::wire_log::WireLogEntry;
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(
+                ResponseTemplate::new(400)
+                    .insert_header("x-request-id", "req-42")
+                    .set_body_string(r#"{"error":"bad model"}"#),
+            )
+            .mount(&server)
+            .await;
+
+        let path = std::env::temp_dir().join(format!(
+            "zed-copilot-client-wire-{}.jsonl",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+        let client = HttpClient::default().with_wire_log(WireLog::new(&path, 1024 * 1024));
+
+        let result = client
+            .post(
+                &server.uri(),
+                serde_json::json!({"model": "gpt-5"}),
+                "sk-secret",
+            )
+            .await;
+        assert!(matches!(result, Err(ProviderError::ApiError(_))));
+
+        let log = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(!log.contains("sk-secret"));
+
+        let entry: WireLogEntry = serde_json::from_str(log.lines().next().unwrap()).unwrap();
+        assert_eq!(entry.request.method, "POST");
+        assert_eq!(entry.request.body, r#"{"model":"gpt-5"}"#);
+        assert!(entry
+            .request
+            .headers
+            .iter()
+            .any(|h| h.name == "authorization" && h.value == "[REDACTED]"));
+        let response = entry.response.unwrap();
+        assert_eq!(response.status, 400);
+        assert_eq!(response.body, r#"{"error":"bad model"}"#);
+        assert!(response
+            .headers
+            .iter()
+            .any(|h| h.name == "x-request-id" && h.value == "req-42"));
+    }
+
+    #[tokio::test]
+    async fn test_refused_connection_keeps_query_key_out_of_errors() {
+        let path = std::env::temp_dir().join(format!(
+            "zed-copilot-client-refused-{}.jsonl",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/v1/generate", listener.local_addr().unwrap());
+        drop(listener);
+        let client = HttpClient::default()
+            .with_retry_policy(RetryPolicy::new(0, 1, 1))
+            .with_auth_scheme(AuthScheme::QueryParam("key".to_string()))
+            .with_wire_log(WireLog::new(&path, 1024 * 1024));
+
+        let error = client
+            .post(&url, serde_json::json!({}), "AIza-secret")
+            .await
+            .unwrap_err();
+        assert!(matches!(error, ProviderError::NetworkError(_)));
+        assert!(!error.to_string().contains("AIza-secret"));
+
+        let log = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(!log.contains("AIza-secret"));
+        let entry: WireLogEntry = serde_json::from_str(log.lines().next().unwrap()).unwrap();
+        assert!(entry.error.is_some());
+    }
+
     #[test]
     fn test_is_transient_error_network() {
         let error = ProviderError::NetworkError("Connection reset".to_string());
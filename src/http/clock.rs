@@ -0,0 +1,168 @@
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+pub type Sleep = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+/// Monotonic time source used for backoff sleeps and rate limiting, so both
+/// can run on virtual time in tests.
+pub trait Clock: Send + Sync + fmt::Debug {
+    fn now(&self) -> Instant;
+    fn sleep(&self, duration: Duration) -> Sleep;
+}
+
+/// Source of jitter for retry backoff. Returns a value in `[0, 1)`.
+pub trait JitterSource: Send + Sync + fmt::Debug {
+    fn sample(&self) -> f64;
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn sleep(&self, duration: Duration) -> Sleep {
+        Box::pin(tokio::time::sleep(duration))
+    }
+}
+
+/// Clock that only moves when told to. `sleep` completes immediately,
+/// advances the clock by the requested duration and is recorded so tests can
+/// assert the exact schedule.
+#[derive(Debug, Clone)]
+pub struct VirtualClock {
+    start: Instant,
+    state: Arc<Mutex<VirtualState>>,
+}
+
+#[derive(Debug, Default)]
+struct VirtualState {
+    elapsed: Duration,
+    sleeps: Vec<Duration>,
+}
+
+impl VirtualClock {
+    pub fn new() -> Self {
+        VirtualClock {
+            start: Instant::now(),
+            state: Arc::new(Mutex::new(VirtualState::default())),
+        }
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        self.lock().elapsed += duration;
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.lock().elapsed
+    }
+
+    pub fn sleeps(&self) -> Vec<Duration> {
+        self.lock().sleeps.clone()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, VirtualState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Default for VirtualClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for VirtualClock {
+    fn now(&self) -> Instant {
+        self.start + self.elapsed()
+    }
+
+    fn sleep(&self, duration: Duration) -> Sleep {
+        let mut state = self.lock();
+        state.elapsed += duration;
+        state.sleeps.push(duration);
+        Box::pin(std::future::ready(()))
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RandomJitter;
+
+impl JitterSource for RandomJitter {
+    fn sample(&self) -> f64 {
+        let mut bytes = [0u8; 8];
+        if getrandom::getrandom(&mut bytes).is_err() {
+            return 0.5;
+        }
+        (u64::from_le_bytes(bytes) >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// Always returns the same sample; `FixedJitter(0.5)` yields the un-jittered
+/// delay.
+#[derive(Debug, Clone, Copy)]
+pub struct FixedJitter(pub f64);
+
+impl JitterSource for FixedJitter {
+    fn sample(&self) -> f64 {
+        self.0.clamp(0.0, 1.0)
+    }
+}
+
+pub fn system_clock() -> Arc<dyn Clock> {
+    Arc::new(SystemClock)
+}
+
+pub fn random_jitter() -> Arc<dyn JitterSource> {
+    Arc::new(RandomJitter)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_virtual_clock_sleep_advances_time() {
+        let clock = VirtualClock::new();
+        let before = clock.now();
+
+        clock.sleep(Duration::from_secs(30)).await;
+        clock.sleep(Duration::from_millis(250)).await;
+
+        assert_eq!(clock.now() - before, Duration::from_millis(30_250));
+        assert_eq!(
+            clock.sleeps(),
+            vec![Duration::from_secs(30), Duration::from_millis(250)]
+        );
+    }
+
+    #[test]
+    fn test_virtual_clock_advance_is_shared_by_clones() {
+        let clock = VirtualClock::new();
+        let before = clock.now();
+        clock.clone().advance(Duration::from_secs(5));
+
+        assert_eq!(clock.now() - before, Duration::from_secs(5));
+        assert!(clock.sleeps().is_empty());
+    }
+
+    #[test]
+    fn test_random_jitter_in_range() {
+        for _ in 0..100 {
+            let sample = RandomJitter.sample();
+            assert!((0.0..1.0).contains(&sample));
+        }
+    }
+
+    #[test]
+    fn test_fixed_jitter_is_clamped() {
+        assert_eq!(FixedJitter(0.25).sample(), 0.25);
+        assert_eq!(FixedJitter(3.0).sample(), 1.0);
+        assert_eq!(FixedJitter(-1.0).sample(), 0.0);
+    }
+}
@@ -0,0 +1,705 @@
+use crate::http::resumable::TextStream;
+use crate::providers::error::{ProviderError, ProviderResult};
+use futures::StreamExt;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::future::Future;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+use std::time::{Duration, Instant};
+
+const LATENCY_BUCKETS: &[f64] = &[0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0];
+const TTFT_BUCKETS: &[f64] = &[0.05, 0.1, 0.25, 0.5, 1.0, 2.0, 5.0, 10.0];
+const PREFIX: &str = "zed_copilot";
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+pub struct MetricLabels {
+    pub provider: String,
+    pub model: String,
+}
+
+impl MetricLabels {
+    pub fn new(provider: impl Into<String>, model: impl Into<String>) -> Self {
+        MetricLabels {
+            provider: provider.into(),
+            model: model.into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HistogramSnapshot {
+    pub buckets: Vec<(f64, u64)>,
+    pub count: u64,
+    pub sum: f64,
+}
+
+#[derive(Debug, Clone)]
+struct Histogram {
+    bounds: &'static [f64],
+    counts: Vec<u64>,
+    count: u64,
+    sum: f64,
+}
+
+impl Histogram {
+    fn new(bounds: &'static [f64]) -> Self {
+        Histogram {
+            bounds,
+            counts: vec![0; bounds.len()],
+            count: 0,
+            sum: 0.0,
+        }
+    }
+
+    fn observe(&mut self, value: Duration) {
+        let seconds = value.as_secs_f64();
+        for (bound, count) in self.bounds.iter().zip(self.counts.iter_mut()) {
+            if seconds <= *bound {
+                *count += 1;
+            }
+        }
+        self.count += 1;
+        self.sum += seconds;
+    }
+
+    fn snapshot(&self) -> HistogramSnapshot {
+        HistogramSnapshot {
+            buckets: self
+                .bounds
+                .iter()
+                .copied()
+                .zip(self.counts.iter().copied())
+                .collect(),
+            count: self.count,
+            sum: self.sum,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct ProviderMetrics {
+    requests: u64,
+    errors: BTreeMap<&'static str, u64>,
+    retries: u64,
+    input_tokens: u64,
+    output_tokens: u64,
+    latency: Histogram,
+    time_to_first_token: Histogram,
+}
+
+impl Default for ProviderMetrics {
+    fn default() -> Self {
+        ProviderMetrics {
+            requests: 0,
+            errors: BTreeMap::new(),
+            retries: 0,
+            input_tokens: 0,
+            output_tokens: 0,
+            latency: Histogram::new(LATENCY_BUCKETS),
+            time_to_first_token: Histogram::new(TTFT_BUCKETS),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ProviderMetricsSnapshot {
+    pub provider: String,
+    pub model: String,
+    pub requests: u64,
+    pub errors: BTreeMap<String, u64>,
+    pub retries: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub latency_seconds: HistogramSnapshot,
+    pub time_to_first_token_seconds: HistogramSnapshot,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct MetricsSnapshot {
+    pub providers: Vec<ProviderMetricsSnapshot>,
+}
+
+/// Shared, cheaply cloneable store of per-provider/model request metrics.
+#[derive(Debug, Clone, Default)]
+pub struct Metrics {
+    inner: Arc<Mutex<BTreeMap<MetricLabels, ProviderMetrics>>>,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn global() -> &'static Metrics {
+        static GLOBAL: OnceLock<Metrics> = OnceLock::new();
+        GLOBAL.get_or_init(Metrics::new)
+    }
+
+    pub fn record_request(&self, labels: &MetricLabels) {
+        self.update(labels, |m| m.requests += 1);
+    }
+
+    pub fn record_error(&self, labels: &MetricLabels, error: &ProviderError) {
+        self.update(labels, |m| *m.errors.entry(error.kind()).or_default() += 1);
+    }
+
+    pub fn record_retry(&self, labels: &MetricLabels) {
+        self.update(labels, |m| m.retries += 1);
+    }
+
+    pub fn record_latency(&self, labels: &MetricLabels, latency: Duration) {
+        self.update(labels, |m| m.latency.observe(latency));
+    }
+
+    pub fn record_time_to_first_token(&self, labels: &MetricLabels, ttft: Duration) {
+        self.update(labels, |m| m.time_to_first_token.observe(ttft));
+    }
+
+    pub fn record_tokens(&self, labels: &MetricLabels, input: u64, output: u64) {
+        self.update(labels, |m| {
+            m.input_tokens += input;
+            m.output_tokens += output;
+        });
+    }
+
+    /// Records the latency of a finished request and, if it failed, the
+    /// error kind.
+    pub fn record_outcome<T>(
+        &self,
+        labels: &MetricLabels,
+        started: Instant,
+        result: &ProviderResult<T>,
+    ) {
+        self.record_latency(labels, started.elapsed());
+        if let Err(e) = result {
+            self.record_error(labels, e);
+        }
+    }
+
+    pub fn reset(&self) {
+        self.lock().clear();
+    }
+
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        let providers = self
+            .lock()
+            .iter()
+            .map(|(labels, m)| ProviderMetricsSnapshot {
+                provider: labels.provider.clone(),
+                model: labels.model.clone(),
+                requests: m.requests,
+                errors: m
+                    .errors
+                    .iter()
+                    .map(|(kind, count)| (kind.to_string(), *count))
+                    .collect(),
+                retries: m.retries,
+                input_tokens: m.input_tokens,
+                output_tokens: m.output_tokens,
+                latency_seconds: m.latency.snapshot(),
+                time_to_first_token_seconds: m.time_to_first_token.snapshot(),
+            })
+            .collect();
+
+        MetricsSnapshot { providers }
+    }
+
+    fn update(&self, labels: &MetricLabels, f: impl FnOnce(&mut ProviderMetrics)) {
+        let mut metrics = self.lock();
+        if let Some(entry) = metrics.get_mut(labels) {
+            f(entry);
+        } else {
+            f(metrics.entry(labels.clone()).or_default());
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, BTreeMap<MetricLabels, ProviderMetrics>> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl MetricsSnapshot {
+    pub fn get(&self, provider: &str, model: &str) -> Option<&ProviderMetricsSnapshot> {
+        self.providers
+            .iter()
+            .find(|p| p.provider == provider && p.model == model)
+    }
+
+    pub fn to_json(&self) -> ProviderResult<String> {
+        serde_json::to_string_pretty(self).map_err(|e| {
+            ProviderError::ParseError(format!("Failed to serialize metrics snapshot: {}", e))
+        })
+    }
+
+    pub fn to_prometheus(&self) -> String {
+        let mut out = String::new();
+
+        write_counter(
+            &mut out,
+            "requests_total",
+            "Total provider requests.",
+            self.providers.iter().map(|p| (labels(p, &[]), p.requests)),
+        );
+        write_counter(
+            &mut out,
+            "errors_total",
+            "Failed provider requests by error kind.",
+            self.providers.iter().flat_map(|p| {
+                p.errors
+                    .iter()
+                    .map(move |(kind, count)| (labels(p, &[("kind", kind)]), *count))
+            }),
+        );
+        write_counter(
+            &mut out,
+            "retries_total",
+            "Retried provider requests.",
+            self.providers.iter().map(|p| (labels(p, &[]), p.retries)),
+        );
+        write_counter(
+            &mut out,
+            "tokens_total",
+            "Tokens sent to and received from providers.",
+            self.providers.iter().flat_map(|p| {
+                [
+                    (labels(p, &[("direction", "input")]), p.input_tokens),
+                    (labels(p, &[("direction", "output")]), p.output_tokens),
+                ]
+            }),
+        );
+        write_histogram(
+            &mut out,
+            "request_duration_seconds",
+            "Total provider request latency.",
+            self.providers
+                .iter()
+                .map(|p| (labels(p, &[]), &p.latency_seconds)),
+        );
+        write_histogram(
+            &mut out,
+            "time_to_first_token_seconds",
+            "Time until the first streamed token arrived.",
+            self.providers
+                .iter()
+                .map(|p| (labels(p, &[]), &p.time_to_first_token_seconds)),
+        );
+
+        out
+    }
+}
+
+fn labels(provider: &ProviderMetricsSnapshot, extra: &[(&str, &str)]) -> String {
+    [
+        ("provider", provider.provider.as_str()),
+        ("model", provider.model.as_str()),
+    ]
+    .iter()
+    .chain(extra)
+    .map(|(name, value)| format!("{}=\"{}\"", name, escape_label(value)))
+    .collect::<Vec<_>>()
+    .join(",")
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn write_counter(
+    out: &mut String,
+    name: &str,
+    help: &str,
+    samples: impl Iterator<Item = (String, u64)>,
+) {
+    let _ = writeln!(out, "# HELP {}_{} {}", PREFIX, name, help);
+    let _ = writeln!(out, "# TYPE {}_{} counter", PREFIX, name);
+    for (labels, value) in samples {
+        let _ = writeln!(out, "{}_{}{{{}}} {}", PREFIX, name, labels, value);
+    }
+}
+
+fn write_histogram<'a>(
+    out: &mut String,
+    name: &str,
+    help: &str,
+    samples: impl Iterator<Item = (String, &'a HistogramSnapshot)>,
+) {
+    let _ = writeln!(out, "# HELP {}_{} {}", PREFIX, name, help);
+    let _ = writeln!(out, "# TYPE {}_{} histogram", PREFIX, name);
+    for (labels, histogram) in samples {
+        for (bound, count) in &histogram.buckets {
+            let _ = writeln!(
+                out,
+                "{}_{}_bucket{{{},le=\"{}\"}} {}",
+                PREFIX, name, labels, bound, count
+            );
+        }
+        let _ = writeln!(
+            out,
+            "{}_{}_bucket{{{},le=\"+Inf\"}} {}",
+            PREFIX, name, labels, histogram.count
+        );
+        let _ = writeln!(
+            out,
+            "{}_{}_sum{{{}}} {}",
+            PREFIX, name, labels, histogram.sum
+        );
+        let _ = writeln!(
+            out,
+            "{}_{}_count{{{}}} {}",
+            PREFIX, name, labels, histogram.count
+        );
+    }
+}
+
+/// Extracts `(input, output)` token counts from an OpenAI- or
+/// Anthropic-style `usage` object in a response body.
+pub fn usage_from_response(body: &serde_json::Value) -> Option<(u64, u64)> {
+    let usage = body.get("usage")?;
+    let field = |names: [&str; 2]| {
+        names
+            .iter()
+            .find_map(|name| usage.get(*name).and_then(|v| v.as_u64()))
+    };
+
+    let input = field(["prompt_tokens", "input_tokens"]);
+    let output = field(["completion_tokens", "output_tokens"]);
+    if input.is_none() && output.is_none() {
+        return None;
+    }
+
+    Some((input.unwrap_or(0), output.unwrap_or(0)))
+}
+
+struct InstrumentState {
+    inner: TextStream,
+    metrics: Metrics,
+    labels: MetricLabels,
+    started: Instant,
+    first_token_seen: bool,
+    finished: bool,
+}
+
+impl InstrumentState {
+    fn finish(&mut self) {
+        if !self.finished {
+            self.finished = true;
+            self.metrics
+                .record_latency(&self.labels, self.started.elapsed());
+        }
+    }
+}
+
+/// Covers streams dropped before they end, such as a cancelled completion.
+impl Drop for InstrumentState {
+    fn drop(&mut self) {
+        self.finish();
+    }
+}
+
+/// Records time-to-first-token, total latency and any terminal error for a
+/// streamed completion. `started` is when the request was issued. Latency is
+/// recorded once, when the stream ends, fails or is dropped.
+pub fn instrument_stream(
+    stream: TextStream,
+    metrics: Metrics,
+    labels: MetricLabels,
+    started: Instant,
+) -> TextStream {
+    let state = InstrumentState {
+        inner: stream,
+        metrics,
+        labels,
+        started,
+        first_token_seen: false,
+        finished: false,
+    };
+
+    Box::pin(futures::stream::unfold(state, |mut state| async move {
+        match state.inner.next().await {
+            Some(Ok(text)) => {
+                if !state.first_token_seen && !text.is_empty() {
+                    state.first_token_seen = true;
+                    state
+                        .metrics
+                        .record_time_to_first_token(&state.labels, state.started.elapsed());
+                }
+                Some((Ok(text), state))
+            }
+            Some(Err(e)) => {
+                state.metrics.record_error(&state.labels, &e);
+                state.finish();
+                Some((Err(e), state))
+            }
+            None => {
+                state.finish();
+                None
+            }
+        }
+    }))
+}
+
+/// Wraps a `resumable_stream` opener so every call after the first is
+/// counted as a retry.
+pub(crate) fn count_reopens<F, Fut>(
+    metrics: Option<Metrics>,
+    labels: MetricLabels,
+    open: F,
+) -> impl Fn(String) -> Fut + Send + Sync + 'static
+where
+    F: Fn(String) -> Fut + Send + Sync + 'static,
+{
+    let opens = AtomicU32::new(0);
+    move |prefill| {
+        if opens.fetch_add(1, Ordering::Relaxed) > 0 {
+            if let Some(metrics) = &metrics {
+                metrics.record_retry(&labels);
+            }
+        }
+        open(prefill)
+    }
+}
+
+/// Counts a streamed request and instruments the stream once it is open;
+/// a failure to open is recorded as the request's outcome.
+pub(crate) async fn observe_stream<Fut>(
+    metrics: Option<Metrics>,
+    labels: MetricLabels,
+    opening: Fut,
+) -> ProviderResult<TextStream>
+where
+    Fut: Future<Output = ProviderResult<TextStream>>,
+{
+    let Some(metrics) = metrics else {
+        return opening.await;
+    };
+
+    let started = Instant::now();
+    metrics.record_request(&labels);
+    match opening.await {
+        Ok(stream) => Ok(instrument_stream(stream, metrics, labels, started)),
+        Err(e) => {
+            let result = Err(e);
+            metrics.record_outcome(&labels, started, &result);
+            result
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn openai() -> MetricLabels {
+        MetricLabels::new("openai", "gpt-4")
+    }
+
+    #[test]
+    fn test_counters_are_per_provider_and_model() {
+        let metrics = Metrics::new();
+        metrics.record_request(&openai());
+        metrics.record_request(&openai());
+        metrics.record_request(&MetricLabels::new("anthropic", "claude-3-haiku"));
+        metrics.record_retry(&openai());
+        metrics.record_tokens(&openai(), 10, 20);
+        metrics.record_tokens(&openai(), 5, 1);
+
+        let snapshot = metrics.snapshot();
+        let gpt = snapshot.get("openai", "gpt-4").unwrap();
+        assert_eq!(gpt.requests, 2);
+        assert_eq!(gpt.retries, 1);
+        assert_eq!(gpt.input_tokens, 15);
+        assert_eq!(gpt.output_tokens, 21);
+        assert_eq!(
+            snapshot
+                .get("anthropic", "claude-3-haiku")
+                .unwrap()
+                .requests,
+            1
+        );
+    }
+
+    #[test]
+    fn test_errors_by_kind() {
+        let metrics = Metrics::new();
+        metrics.record_error(&openai(), &ProviderError::NetworkError("x".to_string()));
+        metrics.record_error(&openai(), &ProviderError::NetworkError("y".to_string()));
+        metrics.record_error(&openai(), &ProviderError::ApiError("z".to_string()));
+
+        let snapshot = metrics.snapshot();
+        let errors = &snapshot.get("openai", "gpt-4").unwrap().errors;
+        assert_eq!(errors["network_error"], 2);
+        assert_eq!(errors["api_error"], 1);
+    }
+
+    #[test]
+    fn test_histogram_buckets_are_cumulative() {
+        let metrics = Metrics::new();
+        metrics.record_latency(&openai(), Duration::from_millis(200));
+        metrics.record_latency(&openai(), Duration::from_millis(700));
+        metrics.record_latency(&openai(), Duration::from_secs(90));
+
+        let snapshot = metrics.snapshot();
+        let latency = &snapshot.get("openai", "gpt-4").unwrap().latency_seconds;
+        assert_eq!(latency.count, 3);
+        assert!((latency.sum - 90.9).abs() < 1e-9);
+        assert_eq!(latency.buckets[0], (0.1, 0));
+        assert_eq!(latency.buckets[1], (0.25, 1));
+        assert_eq!(latency.buckets[3], (1.0, 2));
+        assert_eq!(latency.buckets.last(), Some(&(60.0, 2)));
+    }
+
+    #[test]
+    fn test_reset() {
+        let metrics = Metrics::new();
+        metrics.record_request(&openai());
+        metrics.reset();
+        assert!(metrics.snapshot().providers.is_empty());
+    }
+
+    #[test]
+    fn test_clones_share_state() {
+        let metrics = Metrics::new();
+        metrics.clone().record_request(&openai());
+        assert_eq!(
+            metrics.snapshot().get("openai", "gpt-4").unwrap().requests,
+            1
+        );
+    }
+
+    #[test]
+    fn test_prometheus_export() {
+        let metrics = Metrics::new();
+        metrics.record_request(&openai());
+        metrics.record_error(&openai(), &ProviderError::ParseError("bad".to_string()));
+        metrics.record_tokens(&openai(), 3, 4);
+        metrics.record_latency(&openai(), Duration::from_millis(300));
+
+        let text = metrics.snapshot().to_prometheus();
+        assert!(text.contains("# TYPE zed_copilot_requests_total counter"));
+        assert!(text.contains("zed_copilot_requests_total{provider=\"openai\",model=\"gpt-4\"} 1"));
+        assert!(text.contains(
+            "zed_copilot_errors_total{provider=\"openai\",model=\"gpt-4\",kind=\"parse_error\"} 1"
+        ));
+        assert!(text.contains(
+            "zed_copilot_tokens_total{provider=\"openai\",model=\"gpt-4\",direction=\"output\"} 4"
+        ));
+        assert!(text.contains("# TYPE zed_copilot_request_duration_seconds histogram"));
+        assert!(text.contains(
+            "zed_copilot_request_duration_seconds_bucket{provider=\"openai\",model=\"gpt-4\",le=\"0.5\"} 1"
+        ));
+        assert!(text.contains(
+            "zed_copilot_request_duration_seconds_bucket{provider=\"openai\",model=\"gpt-4\",le=\"+Inf\"} 1"
+        ));
+        assert!(text.contains(
+            "zed_copilot_request_duration_seconds_count{provider=\"openai\",model=\"gpt-4\"} 1"
+        ));
+    }
+
+    #[test]
+    fn test_prometheus_escapes_label_values() {
+        let metrics = Metrics::new();
+        metrics.record_request(&MetricLabels::new("custom", "my \"model\"\\v2"));
+
+        let text = metrics.snapshot().to_prometheus();
+        assert!(text.contains("model=\"my \\\"model\\\"\\\\v2\""));
+    }
+
+    #[test]
+    fn test_json_export() {
+        let metrics = Metrics::new();
+        metrics.record_request(&openai());
+
+        let json: serde_json::Value =
+            serde_json::from_str(&metrics.snapshot().to_json().unwrap()).unwrap();
+        assert_eq!(json["providers"][0]["provider"], "openai");
+        assert_eq!(json["providers"][0]["requests"], 1);
+        assert_eq!(json["providers"][0]["latency_seconds"]["count"], 0);
+    }
+
+    #[test]
+    fn test_usage_from_response() {
+        let openai_body = serde_json::json!({
+            "usage": {"prompt_tokens": 10, "completion_tokens": 20, "total_tokens": 30}
+        });
+        let anthropic_body = serde_json::json!({
+            "usage": {"input_tokens": 7, "output_tokens": 9}
+        });
+
+        assert_eq!(usage_from_response(&openai_body), Some((10, 20)));
+        assert_eq!(usage_from_response(&anthropic_body), Some((7, 9)));
+        assert_eq!(usage_from_response(&serde_json::json!({})), None);
+        assert_eq!(usage_from_response(&serde_json::json!({"usage": {}})), None);
+    }
+
+    #[tokio::test]
+    async fn test_instrument_stream() {
+        let metrics = Metrics::new();
+        let inner = Box::pin(futures::stream::iter(vec![
+            Ok(String::new()),
+            Ok("Hi".to_string()),
+            Ok(" there".to_string()),
+        ]));
+
+        let stream = instrument_stream(inner, metrics.clone(), openai(), Instant::now());
+        let output: Vec<_> = stream.collect().await;
+        assert_eq!(output.len(), 3);
+
+        let snapshot = metrics.snapshot();
+        let gpt = snapshot.get("openai", "gpt-4").unwrap();
+        assert_eq!(gpt.time_to_first_token_seconds.count, 1);
+        assert_eq!(gpt.latency_seconds.count, 1);
+        assert!(gpt.errors.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_instrument_stream_records_error() {
+        let metrics = Metrics::new();
+        let inner = Box::pin(futures::stream::iter(vec![Err(
+            ProviderError::StreamInterrupted {
+                partial: "a".to_string(),
+                reason: "reset".to_string(),
+            },
+        )]));
+
+        let stream = instrument_stream(inner, metrics.clone(), openai(), Instant::now());
+        let _: Vec<_> = stream.collect().await;
+
+        let snapshot = metrics.snapshot();
+        let gpt = snapshot.get("openai", "gpt-4").unwrap();
+        assert_eq!(gpt.errors["stream_interrupted"], 1);
+        assert_eq!(gpt.time_to_first_token_seconds.count, 0);
+        assert_eq!(gpt.latency_seconds.count, 1);
+    }
+
+    #[tokio::test]
+    async fn test_instrument_stream_dropped_midway() {
+        let metrics = Metrics::new();
+        let inner = Box::pin(
+            futures::stream::iter(vec![Ok("Hi".to_string())]).chain(futures::stream::pending()),
+        );
+
+        let mut stream = instrument_stream(inner, metrics.clone(), openai(), Instant::now());
+        assert_eq!(stream.next().await.unwrap().unwrap(), "Hi");
+        assert_eq!(
+            metrics
+                .snapshot()
+                .get("openai", "gpt-4")
+                .unwrap()
+                .latency_seconds
+                .count,
+            0
+        );
+        drop(stream);
+
+        let snapshot = metrics.snapshot();
+        let gpt = snapshot.get("openai", "gpt-4").unwrap();
+        assert_eq!(gpt.time_to_first_token_seconds.count, 1);
+        assert_eq!(gpt.latency_seconds.count, 1);
+        assert!(gpt.errors.is_empty());
+    }
+}
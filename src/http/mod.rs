@@ -1,9 +1,31 @@
 pub mod anthropic;
+pub mod auth;
+#[cfg(test)]
+pub mod chaos;
 pub mod client;
+pub mod clock;
+pub mod metrics;
+pub mod ndjson;
 pub mod openai;
 pub mod rate_limiter;
+pub mod resumable;
 pub mod retry;
+pub mod sse;
+pub mod stream;
+pub mod transport;
+pub mod wire_log;
 
+pub use auth::{AuthScheme, RequestSigner};
+#[cfg(test)]
+pub use chaos::{ChaosPlan, ChaosProxy, Faults};
 pub use client::HttpClient;
+pub use clock::{Clock, FixedJitter, JitterSource, SystemClock, VirtualClock};
+pub use metrics::{MetricLabels, Metrics, MetricsSnapshot};
+pub use ndjson::NdjsonDecoder;
 pub use rate_limiter::RateLimiter;
+pub use resumable::{resumable_stream, Resumption};
 pub use retry::RetryPolicy;
+pub use sse::{SseDecoder, SseEvent};
+pub use stream::{decode_stream, DecodeError, DecodedStream};
+pub use transport::build_http_client;
+pub use wire_log::{SdkWireLog, WireLog, WireLogEntry};
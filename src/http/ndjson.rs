@@ -0,0 +1,160 @@
+use crate::http::stream::DecodeError;
+use crate::providers::error::{ProviderError, ProviderResult};
+use bytes::{Buf, BytesMut};
+use serde::de::DeserializeOwned;
+use std::marker::PhantomData;
+use tokio_util::codec::Decoder;
+
+/// Incremental decoder for newline-delimited JSON bodies. Blank lines are
+/// skipped and a final record without a trailing newline is accepted at EOF.
+#[derive(Debug)]
+pub struct NdjsonDecoder<T = serde_json::Value> {
+    next_index: usize,
+    _record: PhantomData<fn() -> T>,
+}
+
+impl<T> NdjsonDecoder<T> {
+    pub fn new() -> Self {
+        NdjsonDecoder {
+            next_index: 0,
+            _record: PhantomData,
+        }
+    }
+}
+
+impl<T> Default for NdjsonDecoder<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn parse_line<T: DeserializeOwned>(line: &[u8]) -> ProviderResult<Option<T>> {
+    let line = line.strip_suffix(b"\r").unwrap_or(line);
+    if line.iter().all(u8::is_ascii_whitespace) {
+        return Ok(None);
+    }
+
+    serde_json::from_slice(line)
+        .map(Some)
+        .map_err(|e| ProviderError::ParseError(format!("Invalid NDJSON record: {}", e)))
+}
+
+impl<T: DeserializeOwned> Decoder for NdjsonDecoder<T> {
+    type Item = T;
+    type Error = DecodeError;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<T>, DecodeError> {
+        loop {
+            let Some(offset) = src[self.next_index..].iter().position(|&b| b == b'\n') else {
+                self.next_index = src.len();
+                return Ok(None);
+            };
+
+            let line = src.split_to(self.next_index + offset);
+            src.advance(1);
+            self.next_index = 0;
+
+            if let Some(record) = parse_line(&line)? {
+                return Ok(Some(record));
+            }
+        }
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> Result<Option<T>, DecodeError> {
+        if let Some(record) = self.decode(src)? {
+            return Ok(Some(record));
+        }
+
+        let line = src.split();
+        self.next_index = 0;
+        Ok(parse_line(&line)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+    use serde_json::json;
+
+    fn decode_chunks(chunks: &[&[u8]]) -> ProviderResult<Vec<serde_json::Value>> {
+        let mut decoder = NdjsonDecoder::new();
+        let mut buffer = BytesMut::new();
+        let mut records = Vec::new();
+
+        for chunk in chunks {
+            buffer.extend_from_slice(chunk);
+            while let Some(record) = decoder.decode(&mut buffer)? {
+                records.push(record);
+            }
+        }
+
+        while let Some(record) = decoder.decode_eof(&mut buffer)? {
+            records.push(record);
+        }
+
+        Ok(records)
+    }
+
+    #[test]
+    fn test_decode_records() {
+        let records = decode_chunks(&[b"{\"a\":1}\n{\"a\":2}\n"]).unwrap();
+        assert_eq!(records, vec![json!({"a": 1}), json!({"a": 2})]);
+    }
+
+    #[test]
+    fn test_record_split_across_chunks() {
+        let records = decode_chunks(&[b"{\"message\":", b"\"hel", b"lo\"}\n"]).unwrap();
+        assert_eq!(records, vec![json!({"message": "hello"})]);
+    }
+
+    #[test]
+    fn test_crlf_and_blank_lines() {
+        let records = decode_chunks(&[b"\r\n{\"a\":1}\r\n\n  \n{\"a\":2}\r", b"\n"]).unwrap();
+        assert_eq!(records, vec![json!({"a": 1}), json!({"a": 2})]);
+    }
+
+    #[test]
+    fn test_final_record_without_newline() {
+        let records = decode_chunks(&[b"{\"a\":1}\n{\"done\":true}"]).unwrap();
+        assert_eq!(records, vec![json!({"a": 1}), json!({"done": true})]);
+    }
+
+    #[test]
+    fn test_byte_by_byte() {
+        let input = "{\"text\":\"世界\"}\n{\"text\":\"→\"}\n".as_bytes();
+        let chunks: Vec<&[u8]> = input.chunks(1).collect();
+        let records = decode_chunks(&chunks).unwrap();
+        assert_eq!(records, vec![json!({"text": "世界"}), json!({"text": "→"})]);
+    }
+
+    #[test]
+    fn test_malformed_record() {
+        let result = decode_chunks(&[b"{\"a\":1}\n{oops}\n"]);
+        match result {
+            Err(ProviderError::ParseError(msg)) => assert!(msg.contains("NDJSON")),
+            _ => panic!("Expected ParseError"),
+        }
+    }
+
+    #[test]
+    fn test_typed_records() {
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Chunk {
+            response: String,
+            done: bool,
+        }
+
+        let mut decoder = NdjsonDecoder::<Chunk>::new();
+        let mut buffer = BytesMut::from(&b"{\"response\":\"Hi\",\"done\":false}\n"[..]);
+
+        let chunk = decoder.decode(&mut buffer).unwrap().unwrap();
+        assert_eq!(
+            chunk,
+            Chunk {
+                response: "Hi".to_string(),
+                done: false,
+            }
+        );
+    }
+}
@@ -1,52 +1,107 @@
+use crate::http::metrics::{self, MetricLabels, Metrics};
+use crate::http::resumable::{resumable_stream, Resumption};
+use crate::http::retry::RetryPolicy;
+use crate::http::wire_log::{SdkWireLog, WireLog};
 use crate::providers::error::{ProviderError, ProviderResult};
 use async_openai::{
     config::OpenAIConfig,
-    types::{ChatCompletionRequestUserMessageArgs, CreateChatCompletionRequestArgs},
+    types::{
+        ChatCompletionRequestUserMessageArgs, CreateChatCompletionRequest,
+        CreateChatCompletionRequestArgs, CreateChatCompletionResponse,
+    },
     Client,
 };
 use futures::Stream;
 use std::pin::Pin;
+use std::time::{Instant, SystemTime};
+
+/// Default sampling temperature sent with chat completion requests.
+pub const TEMPERATURE: f32 = 0.7;
+
+/// Default completion token limit.
+pub const MAX_TOKENS: u32 = 1024;
 
 pub struct OpenAiHttpClient {
     client: Client<OpenAIConfig>,
+    api_base: String,
+    retry_policy: RetryPolicy,
+    wire_log: Option<SdkWireLog>,
+    metrics: Option<Metrics>,
+    temperature: f32,
+    max_tokens: u32,
 }
 
 impl OpenAiHttpClient {
     pub fn new(api_base: String) -> ProviderResult<Self> {
-        let config = OpenAIConfig::new().with_api_base(api_base);
+        let config = OpenAIConfig::new().with_api_base(&api_base);
 
         Ok(OpenAiHttpClient {
             client: Client::with_config(config),
+            api_base,
+            retry_policy: RetryPolicy::default(),
+            wire_log: None,
+            metrics: None,
+            temperature: TEMPERATURE,
+            max_tokens: MAX_TOKENS,
         })
     }
 
+    pub fn with_http_client(self, http_client: reqwest::Client) -> Self {
+        OpenAiHttpClient {
+            client: self.client.with_http_client(http_client),
+            ..self
+        }
+    }
+
+    pub fn with_generation(mut self, temperature: f32, max_tokens: u32) -> Self {
+        self.temperature = temperature;
+        self.max_tokens = max_tokens;
+        self
+    }
+
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    pub fn with_metrics(mut self, metrics: Metrics) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Logs each chat completion call to `wire_log`.
+    pub fn with_wire_log(mut self, wire_log: WireLog) -> Self {
+        let url = format!("{}/chat/completions", self.api_base.trim_end_matches('/'));
+        self.wire_log = Some(SdkWireLog::new(wire_log, url));
+        self
+    }
+
     pub async fn complete(
         &self,
         prompt: &str,
         model: &str,
-        _api_key: &str,
+        api_key: &str,
     ) -> ProviderResult<String> {
-        let user_message = ChatCompletionRequestUserMessageArgs::default()
-            .content(prompt)
-            .build()
-            .map_err(|e| ProviderError::ConfigError(format!("Failed to build message: {}", e)))?;
-
-        let request = CreateChatCompletionRequestArgs::default()
-            .model(model)
-            .messages([user_message.into()])
-            .temperature(0.7)
-            .max_tokens(1024u32)
-            .build()
-            .map_err(|e| ProviderError::ConfigError(format!("Failed to build request: {}", e)))?;
-
-        let response = self
-            .client
-            .chat()
-            .create(request)
-            .await
-            .map_err(|e| map_openai_error(e))?;
+        let labels = MetricLabels::new("openai", model);
+        let started = Instant::now();
+        if let Some(metrics) = &self.metrics {
+            metrics.record_request(&labels);
+        }
 
-        response
+        let result = self.create_completion(prompt, model, api_key).await;
+
+        if let Some(metrics) = &self.metrics {
+            metrics.record_outcome(&labels, started, &result);
+            if let Ok(Some(usage)) = result.as_ref().map(|response| response.usage.as_ref()) {
+                metrics.record_tokens(
+                    &labels,
+                    usage.prompt_tokens.into(),
+                    usage.completion_tokens.into(),
+                );
+            }
+        }
+
+        result?
             .choices
             .first()
             .and_then(|choice| choice.message.content.as_ref())
@@ -56,53 +111,121 @@ impl OpenAiHttpClient {
             })
     }
 
+    async fn create_completion(
+        &self,
+        prompt: &str,
+        model: &str,
+        api_key: &str,
+    ) -> ProviderResult<CreateChatCompletionResponse> {
+        let request = build_request(prompt, model, self.temperature, self.max_tokens)?;
+        let started_at = SystemTime::now();
+        let timer = Instant::now();
+
+        let result = self.client.chat().create(request.clone()).await;
+
+        if let Some(wire_log) = &self.wire_log {
+            let outcome = match &result {
+                Ok(response) => Ok(serde_json::to_string(response).unwrap_or_default()),
+                Err(e) => Err(e.to_string()),
+            };
+            wire_log.record(&request, api_key, started_at, timer.elapsed(), outcome);
+        }
+        result.map_err(map_openai_error)
+    }
+
     pub async fn complete_stream(
         &self,
         prompt: &str,
         model: &str,
-        _api_key: &str,
+        api_key: &str,
     ) -> ProviderResult<Pin<Box<dyn Stream<Item = ProviderResult<String>> + Send>>> {
-        let user_message = ChatCompletionRequestUserMessageArgs::default()
-            .content(prompt)
-            .build()
-            .map_err(|e| ProviderError::ConfigError(format!("Failed to build message: {}", e)))?;
-
-        let request = CreateChatCompletionRequestArgs::default()
-            .model(model)
-            .messages([user_message.into()])
-            .temperature(0.7)
-            .max_tokens(1024u32)
-            .build()
-            .map_err(|e| ProviderError::ConfigError(format!("Failed to build request: {}", e)))?;
-
-        let stream = self
-            .client
-            .chat()
-            .create_stream(request)
-            .await
-            .map_err(|e| map_openai_error(e))?;
-
-        let boxed_stream = Box::pin(futures::stream::unfold(stream, |mut stream| async move {
-            match futures::StreamExt::next(&mut stream).await {
-                Some(Ok(response)) => {
-                    let content = response
-                        .choices
-                        .first()
-                        .and_then(|choice| choice.delta.content.as_ref())
-                        .map(|s| s.to_string());
-
-                    match content {
-                        Some(text) => Some((Ok(text), stream)),
-                        None => Some((Ok(String::new()), stream)),
-                    }
+        let client = self.client.clone();
+        let wire_log = self.wire_log.clone();
+        let prompt = prompt.to_string();
+        let model = model.to_string();
+        let api_key = api_key.to_string();
+        let (temperature, max_tokens) = (self.temperature, self.max_tokens);
+
+        let labels = MetricLabels::new("openai", model.clone());
+        let open = metrics::count_reopens(self.metrics.clone(), labels.clone(), move |_prefill| {
+            let request = build_request(&prompt, &model, temperature, max_tokens);
+            open_stream(client.clone(), request, wire_log.clone(), api_key.clone())
+        });
+
+        // Chat completions cannot continue from an assistant prefill, so a
+        // mid-stream drop is reported with the partial text instead.
+        metrics::observe_stream(
+            self.metrics.clone(),
+            labels,
+            resumable_stream(self.retry_policy.clone(), Resumption::Unsupported, open),
+        )
+        .await
+    }
+}
+
+fn build_request(
+    prompt: &str,
+    model: &str,
+    temperature: f32,
+    max_tokens: u32,
+) -> ProviderResult<CreateChatCompletionRequest> {
+    let user_message = ChatCompletionRequestUserMessageArgs::default()
+        .content(prompt)
+        .build()
+        .map_err(|e| ProviderError::ConfigError(format!("Failed to build message: {}", e)))?;
+
+    CreateChatCompletionRequestArgs::default()
+        .model(model)
+        .messages([user_message.into()])
+        .temperature(temperature)
+        .max_tokens(max_tokens)
+        .build()
+        .map_err(|e| ProviderError::ConfigError(format!("Failed to build request: {}", e)))
+}
+
+async fn open_stream(
+    client: Client<OpenAIConfig>,
+    request: ProviderResult<CreateChatCompletionRequest>,
+    wire_log: Option<SdkWireLog>,
+    api_key: String,
+) -> ProviderResult<Pin<Box<dyn Stream<Item = ProviderResult<String>> + Send>>> {
+    let mut request = request?;
+    // Set by the SDK as well; done here so the wire log shows it.
+    request.stream = Some(true);
+    let started_at = SystemTime::now();
+    let timer = Instant::now();
+
+    let result = client.chat().create_stream(request.clone()).await;
+
+    if let Some(wire_log) = &wire_log {
+        let outcome = match &result {
+            Ok(_) => Ok(String::new()),
+            Err(e) => Err(e.to_string()),
+        };
+        wire_log.record(&request, &api_key, started_at, timer.elapsed(), outcome);
+    }
+    let stream = result.map_err(map_openai_error)?;
+
+    let boxed_stream = Box::pin(futures::stream::unfold(stream, |mut stream| async move {
+        match futures::StreamExt::next(&mut stream).await {
+            Some(Ok(response)) => {
+                let content = response
+                    .choices
+                    .first()
+                    .and_then(|choice| choice.delta.content.as_ref())
+                    .map(|s| s.to_string());
+
+                match content {
+                    Some(text) => Some((Ok(text), stream)),
+                    None => Some((Ok(String::new()), stream)),
                 }
-                Some(Err(e)) => Some((Err(map_openai_error(e)), stream)),
-                None => None,
             }
-        }));
+            Some(Err(e)) => Some((Err(map_openai_error(e)), stream)),
+            None => None,
+        }
+    }));
 
-        Ok(boxed_stream)
-    }
+    Ok(boxed_stream)
 }
 
 fn map_openai_error(error: async_openai::error::OpenAIError) -> ProviderError {
@@ -124,13 +247,35 @@ fn map_openai_error(error: async_openai::error::OpenAIError) -> ProviderError {
         OpenAIError::InvalidArgument(msg) => {
             ProviderError::ConfigError(format!("OpenAI invalid argument: {}", msg))
         }
-        OpenAIError::StreamError(msg) => {
-            ProviderError::ApiError(format!("OpenAI streaming error: {}", msg))
+        OpenAIError::JSONDeserialize(e) => {
+            ProviderError::ParseError(format!("Invalid OpenAI response: {}", e))
         }
+        OpenAIError::StreamError(msg) => match msg.strip_prefix("Invalid status code: ") {
+            Some(status) if status.starts_with('5') => {
+                ProviderError::ApiError(format!("Server error: {}", status))
+            }
+            _ if is_transport_failure(&msg) => {
+                ProviderError::NetworkError(format!("OpenAI stream connection error: {}", msg))
+            }
+            _ => ProviderError::ApiError(format!("OpenAI streaming error: {}", msg)),
+        },
         _ => ProviderError::ApiError(format!("OpenAI error: {}", error)),
     }
 }
 
+// Stream errors arrive as plain strings; transport failures carry reqwest's
+// own messages.
+fn is_transport_failure(msg: &str) -> bool {
+    [
+        "error sending request",
+        "error decoding response body",
+        "request or response body error",
+        "connection",
+    ]
+    .iter()
+    .any(|marker| msg.starts_with(marker))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -147,6 +292,148 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_openai_http_client_with_http_client() {
+        use async_openai::config::Config;
+
+        let client = OpenAiHttpClient::new("https://api.openai.com/v1".to_string())
+            .unwrap()
+            .with_http_client(reqwest::Client::new());
+        assert_eq!(
+            client.client.config().api_base(),
+            "https://api.openai.com/v1"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_complete_stream_retries_until_stream_opens() {
+        use futures::StreamExt;
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        let chunk = |text: &str| {
+            format!(
+                "data: {}\n\n",
+                serde_json::json!({
+                    "id": "chatcmpl-1",
+                    "object": "chat.completion.chunk",
+                    "created": 1,
+                    "model": "gpt-4",
+                    "choices": [{"index": 0, "delta": {"content": text}, "finish_reason": null}]
+                })
+            )
+        };
+        let body = format!("{}{}data: [DONE]\n\n", chunk("Hello"), chunk(" world"));
+
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(ResponseTemplate::new(503))
+            .up_to_n_times(1)
+            .with_priority(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(body, "text/event-stream"))
+            .mount(&server)
+            .await;
+
+        let metrics = Metrics::new();
+        let client = OpenAiHttpClient::new(server.uri())
+            .unwrap()
+            .with_retry_policy(RetryPolicy::new(2, 0, 0))
+            .with_metrics(metrics.clone());
+        let stream = client
+            .complete_stream("Hi", "gpt-4", "sk-test")
+            .await
+            .unwrap();
+
+        let output: Vec<String> = stream.map(|item| item.unwrap()).collect().await;
+        assert_eq!(output.concat(), "Hello world");
+
+        let snapshot = metrics.snapshot();
+        let recorded = snapshot.get("openai", "gpt-4").unwrap();
+        assert_eq!(recorded.requests, 1);
+        assert_eq!(recorded.retries, 1);
+        assert_eq!(recorded.time_to_first_token_seconds.count, 1);
+        assert_eq!(recorded.latency_seconds.count, 1);
+    }
+
+    #[tokio::test]
+    async fn test_complete_records_token_usage() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "id": "chatcmpl-1",
+                "object": "chat.completion",
+                "created": 1,
+                "model": "gpt-4",
+                "choices": [{
+                    "index": 0,
+                    "message": {"role": "assistant", "content": "Hi!"},
+                    "finish_reason": "stop"
+                }],
+                "usage": {"prompt_tokens": 9, "completion_tokens": 3, "total_tokens": 12}
+            })))
+            .mount(&server)
+            .await;
+
+        let metrics = Metrics::new();
+        let client = OpenAiHttpClient::new(server.uri())
+            .unwrap()
+            .with_metrics(metrics.clone());
+
+        assert_eq!(
+            client.complete("Hi", "gpt-4", "sk-test").await.unwrap(),
+            "Hi!"
+        );
+
+        let snapshot = metrics.snapshot();
+        let recorded = snapshot.get("openai", "gpt-4").unwrap();
+        assert_eq!(recorded.requests, 1);
+        assert_eq!(recorded.input_tokens, 9);
+        assert_eq!(recorded.output_tokens, 3);
+        assert!(recorded.errors.is_empty());
+    }
+
+    #[test]
+    fn test_map_openai_error_stream_transport_error_is_network() {
+        let error = async_openai::error::OpenAIError::StreamError(
+            "error decoding response body".to_string(),
+        );
+        assert!(matches!(
+            map_openai_error(error),
+            ProviderError::NetworkError(_)
+        ));
+    }
+
+    #[test]
+    fn test_map_openai_error_json_deserialize_is_parse_error() {
+        let json_error = serde_json::from_str::<serde_json::Value>("{").unwrap_err();
+        let error = async_openai::error::OpenAIError::JSONDeserialize(json_error);
+        assert!(matches!(
+            map_openai_error(error),
+            ProviderError::ParseError(_)
+        ));
+    }
+
+    #[test]
+    fn test_map_openai_error_stream_server_error_is_transient() {
+        let error = async_openai::error::OpenAIError::StreamError(
+            "Invalid status code: 502 Bad Gateway".to_string(),
+        );
+
+        match map_openai_error(error) {
+            ProviderError::ApiError(msg) => assert_eq!(msg, "Server error: 502 Bad Gateway"),
+            _ => panic!("Expected ApiError"),
+        }
+    }
+
     #[test]
     fn test_map_openai_error_api_error() {
         let error = async_openai::error::OpenAIError::InvalidArgument("test error".to_string());
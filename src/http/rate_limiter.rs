@@ -1,26 +1,33 @@
+use crate::http::clock::{self, Clock};
 use std::sync::Arc;
-use std::time::{Duration, SystemTime};
+use std::time::{Duration, Instant};
 use tokio::sync::Mutex;
 
 #[derive(Clone)]
 pub struct RateLimiter {
     state: Arc<Mutex<RateLimiterState>>,
     requests_per_minute: u32,
+    clock: Arc<dyn Clock>,
 }
 
 struct RateLimiterState {
     tokens: f64,
-    last_refill: SystemTime,
+    last_refill: Instant,
 }
 
 impl RateLimiter {
     pub fn new(requests_per_minute: u32) -> Self {
+        Self::with_clock(requests_per_minute, clock::system_clock())
+    }
+
+    pub fn with_clock(requests_per_minute: u32, clock: Arc<dyn Clock>) -> Self {
         RateLimiter {
             state: Arc::new(Mutex::new(RateLimiterState {
                 tokens: requests_per_minute as f64,
-                last_refill: SystemTime::now(),
+                last_refill: clock.now(),
             })),
             requests_per_minute,
+            clock,
         }
     }
 
@@ -35,10 +42,8 @@ impl RateLimiter {
     pub async fn acquire(&self) -> Duration {
         let mut state = self.state.lock().await;
 
-        let now = SystemTime::now();
-        let elapsed = now
-            .duration_since(state.last_refill)
-            .unwrap_or(Duration::from_secs(0));
+        let now = self.clock.now();
+        let elapsed = now.saturating_duration_since(state.last_refill);
 
         let elapsed_minutes = elapsed.as_secs_f64() / 60.0;
         let tokens_to_add = elapsed_minutes * self.requests_per_minute as f64;
@@ -56,6 +61,18 @@ impl RateLimiter {
         }
     }
 
+    /// Acquires a token, sleeping on the limiter's clock until one is
+    /// available.
+    pub async fn wait(&self) {
+        loop {
+            let wait = self.acquire().await;
+            if wait.is_zero() {
+                return;
+            }
+            self.clock.sleep(wait).await;
+        }
+    }
+
     pub fn requests_per_minute(&self) -> u32 {
         self.requests_per_minute
     }
@@ -64,6 +81,13 @@ impl RateLimiter {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::http::clock::VirtualClock;
+
+    fn virtual_limiter(requests_per_minute: u32) -> (RateLimiter, VirtualClock) {
+        let clock = VirtualClock::new();
+        let limiter = RateLimiter::with_clock(requests_per_minute, Arc::new(clock.clone()));
+        (limiter, clock)
+    }
 
     #[tokio::test]
     async fn test_rate_limiter_new() {
@@ -124,4 +148,42 @@ mod tests {
         assert_eq!(wait2, Duration::from_secs(0));
         assert!(wait3 > Duration::from_secs(0));
     }
+
+    #[tokio::test]
+    async fn test_acquire_exact_wait_on_virtual_clock() {
+        let (limiter, clock) = virtual_limiter(2);
+
+        limiter.acquire().await;
+        limiter.acquire().await;
+        assert_eq!(limiter.acquire().await, Duration::from_secs(30));
+
+        clock.advance(Duration::from_secs(10));
+        assert_eq!(limiter.acquire().await, Duration::from_secs(20));
+
+        clock.advance(Duration::from_secs(20));
+        assert_eq!(limiter.acquire().await, Duration::ZERO);
+    }
+
+    #[tokio::test]
+    async fn test_refill_is_capped_at_burst_size() {
+        let (limiter, clock) = virtual_limiter(2);
+
+        clock.advance(Duration::from_secs(3600));
+        assert_eq!(limiter.acquire().await, Duration::ZERO);
+        assert_eq!(limiter.acquire().await, Duration::ZERO);
+        assert!(limiter.acquire().await > Duration::ZERO);
+    }
+
+    #[tokio::test]
+    async fn test_wait_sleeps_until_token_available() {
+        let (limiter, clock) = virtual_limiter(60);
+
+        for _ in 0..60 {
+            limiter.wait().await;
+        }
+        assert!(clock.sleeps().is_empty());
+
+        limiter.wait().await;
+        assert_eq!(clock.sleeps(), vec![Duration::from_secs(1)]);
+    }
 }
@@ -0,0 +1,455 @@
+use crate::http::client::is_transient_error;
+use crate::http::retry::RetryPolicy;
+use crate::providers::error::{ProviderError, ProviderResult};
+use futures::{Stream, StreamExt};
+use std::future::Future;
+use std::pin::Pin;
+
+pub(crate) type TextStream = Pin<Box<dyn Stream<Item = ProviderResult<String>> + Send>>;
+
+/// Whether a vendor can continue a response from an assistant prefill.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resumption {
+    Prefill,
+    Unsupported,
+}
+
+struct ResumeState<F> {
+    open: F,
+    retry_policy: RetryPolicy,
+    resumption: Resumption,
+    inner: TextStream,
+    partial: String,
+    attempt: u32,
+    overlap: String,
+    finished: bool,
+}
+
+/// Opens a streaming completion, retrying connection setup under
+/// `retry_policy`. `open` receives the text streamed so far (empty on the
+/// first call) with trailing whitespace removed, since vendors reject
+/// prefills that end in whitespace.
+///
+/// If the stream fails mid-way and the vendor supports prefill, the request
+/// is reissued and only the continuation is yielded. Otherwise the stream
+/// ends with `ProviderError::StreamInterrupted` carrying the partial text.
+pub async fn resumable_stream<F, Fut>(
+    retry_policy: RetryPolicy,
+    resumption: Resumption,
+    open: F,
+) -> ProviderResult<TextStream>
+where
+    F: Fn(String) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = ProviderResult<TextStream>> + Send + 'static,
+{
+    let mut attempt = 0;
+    let inner = open_with_retry(&open, &retry_policy, String::new(), &mut attempt).await?;
+
+    let state = ResumeState {
+        open,
+        retry_policy,
+        resumption,
+        inner,
+        partial: String::new(),
+        attempt,
+        overlap: String::new(),
+        finished: false,
+    };
+
+    Ok(Box::pin(futures::stream::unfold(
+        state,
+        |mut state| async move {
+            loop {
+                if state.finished {
+                    return None;
+                }
+
+                match state.inner.next().await {
+                    Some(Ok(text)) => {
+                        let text = strip_overlap(&mut state.overlap, text);
+                        if text.is_empty() {
+                            continue;
+                        }
+
+                        state.partial.push_str(&text);
+                        return Some((Ok(text), state));
+                    }
+                    Some(Err(error)) => match resume(&mut state, error).await {
+                        Ok(()) => continue,
+                        Err(error) => {
+                            state.finished = true;
+                            return Some((Err(error), state));
+                        }
+                    },
+                    None => return None,
+                }
+            }
+        },
+    )))
+}
+
+async fn resume<F, Fut>(state: &mut ResumeState<F>, error: ProviderError) -> ProviderResult<()>
+where
+    F: Fn(String) -> Fut,
+    Fut: Future<Output = ProviderResult<TextStream>>,
+{
+    let can_resume = state.partial.is_empty() || state.resumption == Resumption::Prefill;
+
+    if !can_resume
+        || !state
+            .retry_policy
+            .should_retry(state.attempt, is_transient_error(&error))
+    {
+        return Err(interrupted(&state.partial, error));
+    }
+
+    log::warn!(
+        "Stream interrupted after {} characters, resuming: {}",
+        state.partial.chars().count(),
+        error
+    );
+
+    state.attempt += 1;
+    let prefill = state.partial.trim_end().to_string();
+    state.inner = open_with_retry(
+        &state.open,
+        &state.retry_policy,
+        prefill.clone(),
+        &mut state.attempt,
+    )
+    .await
+    .map_err(|e| interrupted(&state.partial, e))?;
+    state.overlap = state.partial[prefill.len()..].to_string();
+
+    Ok(())
+}
+
+async fn open_with_retry<F, Fut>(
+    open: &F,
+    retry_policy: &RetryPolicy,
+    prefill: String,
+    attempt: &mut u32,
+) -> ProviderResult<TextStream>
+where
+    F: Fn(String) -> Fut,
+    Fut: Future<Output = ProviderResult<TextStream>>,
+{
+    loop {
+        retry_policy.backoff(*attempt).await;
+
+        match open(prefill.clone()).await {
+            Ok(stream) => return Ok(stream),
+            Err(e) => {
+                if retry_policy.should_retry(*attempt, is_transient_error(&e)) {
+                    *attempt += 1;
+                } else {
+                    return Err(e);
+                }
+            }
+        }
+    }
+}
+
+/// Drops the whitespace the continuation repeats after a trimmed prefill.
+fn strip_overlap(overlap: &mut String, text: String) -> String {
+    if overlap.is_empty() {
+        return text;
+    }
+
+    let common = overlap
+        .char_indices()
+        .zip(text.chars())
+        .take_while(|((_, a), b)| a == b)
+        .map(|((i, a), _)| i + a.len_utf8())
+        .last()
+        .unwrap_or(0);
+
+    if common == text.len() && common < overlap.len() {
+        overlap.drain(..common);
+        return String::new();
+    }
+
+    overlap.clear();
+    text[common..].to_string()
+}
+
+fn interrupted(partial: &str, error: ProviderError) -> ProviderError {
+    if partial.is_empty() {
+        return error;
+    }
+
+    ProviderError::StreamInterrupted {
+        partial: partial.to_string(),
+        reason: error.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+    use std::sync::{Arc, Mutex};
+
+    type Script = Vec<ProviderResult<String>>;
+
+    #[derive(Clone, Default)]
+    struct FakeVendor {
+        responses: Arc<Mutex<VecDeque<ProviderResult<Script>>>>,
+        prefills: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl FakeVendor {
+        fn new(responses: Vec<ProviderResult<Script>>) -> Self {
+            FakeVendor {
+                responses: Arc::new(Mutex::new(responses.into())),
+                prefills: Arc::default(),
+            }
+        }
+
+        fn opener(
+            &self,
+        ) -> impl Fn(String) -> Pin<Box<dyn Future<Output = ProviderResult<TextStream>> + Send>>
+               + Send
+               + Sync
+               + 'static {
+            let vendor = self.clone();
+            move |prefill| {
+                vendor.prefills.lock().unwrap().push(prefill);
+                let next = vendor
+                    .responses
+                    .lock()
+                    .unwrap()
+                    .pop_front()
+                    .expect("unexpected request");
+                Box::pin(async move {
+                    next.map(|script| Box::pin(futures::stream::iter(script)) as TextStream)
+                })
+            }
+        }
+
+        fn prefills(&self) -> Vec<String> {
+            self.prefills.lock().unwrap().clone()
+        }
+    }
+
+    fn no_delay(max_retries: u32) -> RetryPolicy {
+        RetryPolicy::new(max_retries, 0, 0)
+    }
+
+    fn text(s: &str) -> ProviderResult<String> {
+        Ok(s.to_string())
+    }
+
+    fn dropped() -> ProviderResult<String> {
+        Err(ProviderError::NetworkError("connection reset".to_string()))
+    }
+
+    async fn collect(stream: TextStream) -> Vec<ProviderResult<String>> {
+        stream.collect().await
+    }
+
+    #[tokio::test]
+    async fn test_stream_without_failures() {
+        let vendor = FakeVendor::new(vec![Ok(vec![text("Hello"), text(" world")])]);
+
+        let stream = resumable_stream(no_delay(3), Resumption::Prefill, vendor.opener())
+            .await
+            .unwrap();
+        let items = collect(stream).await;
+
+        assert_eq!(items.len(), 2);
+        assert_eq!(vendor.prefills(), vec![""]);
+    }
+
+    #[tokio::test]
+    async fn test_connection_setup_is_retried() {
+        let vendor = FakeVendor::new(vec![
+            Err(ProviderError::NetworkError("refused".to_string())),
+            Err(ProviderError::ApiError("Server error: 503".to_string())),
+            Ok(vec![text("ok")]),
+        ]);
+
+        let stream = resumable_stream(no_delay(3), Resumption::Unsupported, vendor.opener())
+            .await
+            .unwrap();
+
+        assert_eq!(collect(stream).await[0].as_deref().unwrap(), "ok");
+        assert_eq!(vendor.prefills().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_connection_setup_gives_up_after_max_retries() {
+        let vendor = FakeVendor::new(vec![
+            Err(ProviderError::NetworkError("refused".to_string())),
+            Err(ProviderError::NetworkError("refused".to_string())),
+        ]);
+
+        let result = resumable_stream(no_delay(1), Resumption::Prefill, vendor.opener()).await;
+
+        assert!(matches!(result, Err(ProviderError::NetworkError(_))));
+        assert_eq!(vendor.prefills().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_connection_setup_does_not_retry_permanent_errors() {
+        let vendor = FakeVendor::new(vec![Err(ProviderError::ApiError(
+            "Client error: 401".to_string(),
+        ))]);
+
+        let result = resumable_stream(no_delay(3), Resumption::Prefill, vendor.opener()).await;
+
+        assert!(result.is_err());
+        assert_eq!(vendor.prefills().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_mid_stream_drop_resumes_with_prefill() {
+        let vendor = FakeVendor::new(vec![
+            Ok(vec![text("The answer"), text(" is "), dropped()]),
+            Ok(vec![text(" 42"), text(".")]),
+        ]);
+
+        let stream = resumable_stream(no_delay(3), Resumption::Prefill, vendor.opener())
+            .await
+            .unwrap();
+        let output: String = collect(stream)
+            .await
+            .into_iter()
+            .map(|item| item.unwrap())
+            .collect();
+
+        assert_eq!(output, "The answer is 42.");
+        assert_eq!(vendor.prefills(), vec!["", "The answer is"]);
+    }
+
+    #[tokio::test]
+    async fn test_resumed_whitespace_only_chunks_are_skipped() {
+        let vendor = FakeVendor::new(vec![
+            Ok(vec![text("fn main() {\n"), dropped()]),
+            Ok(vec![text("\n"), text(""), text("    body\n}")]),
+        ]);
+
+        let stream = resumable_stream(no_delay(3), Resumption::Prefill, vendor.opener())
+            .await
+            .unwrap();
+        let output: Vec<String> = collect(stream)
+            .await
+            .into_iter()
+            .map(|item| item.unwrap())
+            .collect();
+
+        assert_eq!(output, vec!["fn main() {\n", "    body\n}"]);
+    }
+
+    #[test]
+    fn test_strip_overlap() {
+        let mut overlap = "\n\n".to_string();
+        assert_eq!(strip_overlap(&mut overlap, "\n".to_string()), "");
+        assert_eq!(overlap, "\n");
+        assert_eq!(strip_overlap(&mut overlap, "\n  x".to_string()), "  x");
+        assert!(overlap.is_empty());
+
+        let mut overlap = " ".to_string();
+        assert_eq!(strip_overlap(&mut overlap, "word".to_string()), "word");
+        assert!(overlap.is_empty());
+
+        let mut overlap = String::new();
+        assert_eq!(strip_overlap(&mut overlap, " kept".to_string()), " kept");
+    }
+
+    #[tokio::test]
+    async fn test_mid_stream_drop_without_prefill_reports_partial() {
+        let vendor = FakeVendor::new(vec![Ok(vec![text("Partial"), text(" answer"), dropped()])]);
+
+        let stream = resumable_stream(no_delay(3), Resumption::Unsupported, vendor.opener())
+            .await
+            .unwrap();
+        let items = collect(stream).await;
+
+        assert_eq!(items.len(), 3);
+        match &items[2] {
+            Err(ProviderError::StreamInterrupted { partial, reason }) => {
+                assert_eq!(partial, "Partial answer");
+                assert!(reason.contains("connection reset"));
+            }
+            other => panic!("Expected StreamInterrupted, got {:?}", other),
+        }
+        assert_eq!(vendor.prefills().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_drop_before_first_token_restarts_without_prefill() {
+        let vendor = FakeVendor::new(vec![Ok(vec![dropped()]), Ok(vec![text("fresh")])]);
+
+        let stream = resumable_stream(no_delay(3), Resumption::Unsupported, vendor.opener())
+            .await
+            .unwrap();
+        let items = collect(stream).await;
+
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].as_deref().unwrap(), "fresh");
+        assert_eq!(vendor.prefills(), vec!["", ""]);
+    }
+
+    #[tokio::test]
+    async fn test_resume_attempts_share_retry_budget() {
+        let vendor = FakeVendor::new(vec![
+            Ok(vec![text("a"), dropped()]),
+            Ok(vec![text("b"), dropped()]),
+            Ok(vec![text("c"), dropped()]),
+        ]);
+
+        let stream = resumable_stream(no_delay(2), Resumption::Prefill, vendor.opener())
+            .await
+            .unwrap();
+        let items = collect(stream).await;
+
+        match items.last() {
+            Some(Err(ProviderError::StreamInterrupted { partial, .. })) => {
+                assert_eq!(partial, "abc");
+            }
+            other => panic!("Expected StreamInterrupted, got {:?}", other),
+        }
+        assert_eq!(vendor.prefills(), vec!["", "a", "ab"]);
+    }
+
+    #[tokio::test]
+    async fn test_failed_reconnect_reports_partial() {
+        let vendor = FakeVendor::new(vec![
+            Ok(vec![text("half"), dropped()]),
+            Err(ProviderError::ApiError("Client error: 400".to_string())),
+        ]);
+
+        let stream = resumable_stream(no_delay(3), Resumption::Prefill, vendor.opener())
+            .await
+            .unwrap();
+        let items = collect(stream).await;
+
+        match items.last() {
+            Some(Err(ProviderError::StreamInterrupted { partial, reason })) => {
+                assert_eq!(partial, "half");
+                assert!(reason.contains("400"));
+            }
+            other => panic!("Expected StreamInterrupted, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_permanent_mid_stream_error_is_not_retried() {
+        let vendor = FakeVendor::new(vec![Ok(vec![
+            text("x"),
+            Err(ProviderError::ParseError("bad chunk".to_string())),
+        ])]);
+
+        let stream = resumable_stream(no_delay(3), Resumption::Prefill, vendor.opener())
+            .await
+            .unwrap();
+        let items = collect(stream).await;
+
+        assert!(matches!(
+            items.last(),
+            Some(Err(ProviderError::StreamInterrupted { .. }))
+        ));
+        assert_eq!(vendor.prefills().len(), 1);
+    }
+}
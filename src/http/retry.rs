@@ -1,3 +1,5 @@
+use crate::http::clock::{self, Clock, JitterSource};
+use std::sync::Arc;
 use std::time::Duration;
 
 #[derive(Debug, Clone)]
@@ -5,6 +7,8 @@ pub struct RetryPolicy {
     max_retries: u32,
     base_delay_ms: u64,
     max_delay_ms: u64,
+    clock: Arc<dyn Clock>,
+    jitter: Arc<dyn JitterSource>,
 }
 
 impl RetryPolicy {
@@ -13,21 +17,41 @@ impl RetryPolicy {
             max_retries,
             base_delay_ms,
             max_delay_ms,
+            clock: clock::system_clock(),
+            jitter: clock::random_jitter(),
         }
     }
 
     pub fn default_policy() -> Self {
-        RetryPolicy {
-            max_retries: 3,
-            base_delay_ms: 1000,
-            max_delay_ms: 32000,
-        }
+        Self::new(3, 1000, 32000)
+    }
+
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn with_jitter(mut self, jitter: Arc<dyn JitterSource>) -> Self {
+        self.jitter = jitter;
+        self
     }
 
     pub fn max_retries(&self) -> u32 {
         self.max_retries
     }
 
+    pub fn clock(&self) -> &Arc<dyn Clock> {
+        &self.clock
+    }
+
+    /// Waits out `calculate_backoff(attempt)` on the policy's clock.
+    pub async fn backoff(&self, attempt: u32) {
+        let delay = self.calculate_backoff(attempt);
+        if !delay.is_zero() {
+            self.clock.sleep(delay).await;
+        }
+    }
+
     pub fn calculate_backoff(&self, attempt: u32) -> Duration {
         if attempt == 0 {
             return Duration::from_millis(0);
@@ -36,7 +60,7 @@ impl RetryPolicy {
         let exponential_delay = self.base_delay_ms * 2_u64.pow(attempt - 1);
         let capped_delay = exponential_delay.min(self.max_delay_ms);
 
-        let jitter = generate_jitter();
+        let jitter = 0.8 + self.jitter.sample() * 0.4;
         let delay_with_jitter = (capped_delay as f64 * jitter) as u64;
         let final_delay = delay_with_jitter.min(self.max_delay_ms);
 
@@ -48,16 +72,6 @@ impl RetryPolicy {
     }
 }
 
-fn generate_jitter() -> f64 {
-    let rand_val = (std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap_or_default()
-        .as_nanos()
-        % 100) as f64
-        / 100.0;
-    0.8 + (rand_val * 0.4)
-}
-
 impl Default for RetryPolicy {
     fn default() -> Self {
         Self::default_policy()
@@ -67,6 +81,7 @@ impl Default for RetryPolicy {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::http::clock::{FixedJitter, VirtualClock};
 
     #[test]
     fn test_retry_policy_new() {
@@ -126,6 +141,48 @@ mod tests {
         assert!(backoff_1_again.as_millis() >= 800 && backoff_1_again.as_millis() <= 1200);
     }
 
+    #[test]
+    fn test_backoff_with_fixed_jitter_is_exact() {
+        let policy = RetryPolicy::new(5, 1000, 32000);
+
+        let low = policy.clone().with_jitter(Arc::new(FixedJitter(0.0)));
+        let mid = policy.clone().with_jitter(Arc::new(FixedJitter(0.5)));
+        let high = policy.with_jitter(Arc::new(FixedJitter(1.0)));
+
+        assert_eq!(low.calculate_backoff(1), Duration::from_millis(800));
+        assert_eq!(mid.calculate_backoff(1), Duration::from_millis(1000));
+        assert_eq!(mid.calculate_backoff(3), Duration::from_millis(4000));
+        assert_eq!(high.calculate_backoff(2), Duration::from_millis(2400));
+    }
+
+    #[test]
+    fn test_backoff_jitter_never_exceeds_max_delay() {
+        let policy = RetryPolicy::new(10, 1000, 10000).with_jitter(Arc::new(FixedJitter(1.0)));
+        assert_eq!(policy.calculate_backoff(8), Duration::from_millis(10000));
+    }
+
+    #[tokio::test]
+    async fn test_backoff_sleeps_on_policy_clock() {
+        let clock = VirtualClock::new();
+        let policy = RetryPolicy::new(3, 1000, 32000)
+            .with_clock(Arc::new(clock.clone()))
+            .with_jitter(Arc::new(FixedJitter(0.5)));
+
+        for attempt in 0..4 {
+            policy.backoff(attempt).await;
+        }
+
+        assert_eq!(
+            clock.sleeps(),
+            vec![
+                Duration::from_millis(1000),
+                Duration::from_millis(2000),
+                Duration::from_millis(4000),
+            ]
+        );
+        assert_eq!(clock.elapsed(), Duration::from_millis(7000));
+    }
+
     #[test]
     fn test_should_retry_transient_error() {
         let policy = RetryPolicy::default_policy();
@@ -0,0 +1,444 @@
+use crate::http::stream::DecodeError;
+use crate::providers::error::{ProviderError, ProviderResult};
+use bytes::{Buf, BytesMut};
+use serde::de::DeserializeOwned;
+use std::time::Duration;
+use tokio_util::codec::Decoder;
+
+const DEFAULT_EVENT_TYPE: &str = "message";
+const BOM: &[u8] = b"\xEF\xBB\xBF";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SseEvent {
+    pub event: String,
+    pub data: String,
+    pub id: Option<String>,
+    pub retry: Option<Duration>,
+}
+
+impl SseEvent {
+    pub fn is_done(&self) -> bool {
+        self.data == "[DONE]"
+    }
+
+    pub fn json<T: DeserializeOwned>(&self) -> ProviderResult<T> {
+        serde_json::from_str(&self.data).map_err(|e| {
+            ProviderError::ParseError(format!("Invalid JSON in '{}' event: {}", self.event, e))
+        })
+    }
+}
+
+/// Incremental decoder for `text/event-stream` bodies as described by the
+/// WHATWG HTML specification. Chunks may be split at arbitrary byte offsets,
+/// including between a CR and its LF and inside multi-byte UTF-8 sequences.
+#[derive(Debug, Default)]
+pub struct SseDecoder {
+    event_type: String,
+    data: String,
+    retry: Option<Duration>,
+    last_event_id: Option<String>,
+    reconnection_time: Option<Duration>,
+    has_data: bool,
+    skip_line_feed: bool,
+    bom_checked: bool,
+}
+
+impl SseDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn last_event_id(&self) -> Option<&str> {
+        self.last_event_id.as_deref()
+    }
+
+    pub fn reconnection_time(&self) -> Option<Duration> {
+        self.reconnection_time
+    }
+
+    fn process_line(&mut self, line: &[u8]) -> Option<SseEvent> {
+        if line.is_empty() {
+            return self.dispatch();
+        }
+
+        if line[0] == b':' {
+            return None;
+        }
+
+        let line = String::from_utf8_lossy(line);
+        let (field, value) = match line.find(':') {
+            Some(index) => {
+                let value = &line[index + 1..];
+                (&line[..index], value.strip_prefix(' ').unwrap_or(value))
+            }
+            None => (line.as_ref(), ""),
+        };
+
+        match field {
+            "event" => self.event_type = value.to_string(),
+            "data" => {
+                self.data.push_str(value);
+                self.data.push('\n');
+                self.has_data = true;
+            }
+            "id" if !value.contains('\0') => self.last_event_id = Some(value.to_string()),
+            "retry" if !value.is_empty() && value.bytes().all(|b| b.is_ascii_digit()) => {
+                if let Ok(millis) = value.parse::<u64>() {
+                    let retry = Duration::from_millis(millis);
+                    self.retry = Some(retry);
+                    self.reconnection_time = Some(retry);
+                }
+            }
+            _ => {}
+        }
+
+        None
+    }
+
+    fn dispatch(&mut self) -> Option<SseEvent> {
+        let event_type = std::mem::take(&mut self.event_type);
+        let retry = self.retry.take();
+
+        if !self.has_data {
+            return None;
+        }
+
+        self.has_data = false;
+        let mut data = std::mem::take(&mut self.data);
+        data.pop();
+
+        Some(SseEvent {
+            event: if event_type.is_empty() {
+                DEFAULT_EVENT_TYPE.to_string()
+            } else {
+                event_type
+            },
+            data,
+            id: self.last_event_id.clone(),
+            retry,
+        })
+    }
+
+    fn strip_bom(&mut self, src: &mut BytesMut) -> bool {
+        if self.bom_checked {
+            return true;
+        }
+
+        let prefix_len = src.len().min(BOM.len());
+        if src[..prefix_len] != BOM[..prefix_len] {
+            self.bom_checked = true;
+            return true;
+        }
+
+        if prefix_len < BOM.len() {
+            return false;
+        }
+
+        src.advance(BOM.len());
+        self.bom_checked = true;
+        true
+    }
+}
+
+impl Decoder for SseDecoder {
+    type Item = SseEvent;
+    type Error = DecodeError;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<SseEvent>, DecodeError> {
+        if !self.strip_bom(src) {
+            return Ok(None);
+        }
+
+        loop {
+            if self.skip_line_feed && !src.is_empty() {
+                if src[0] == b'\n' {
+                    src.advance(1);
+                }
+                self.skip_line_feed = false;
+            }
+
+            let Some(end) = src.iter().position(|&b| b == b'\n' || b == b'\r') else {
+                return Ok(None);
+            };
+
+            let line = src.split_to(end);
+            if src[0] == b'\r' {
+                self.skip_line_feed = true;
+            }
+            src.advance(1);
+
+            if let Some(event) = self.process_line(&line) {
+                return Ok(Some(event));
+            }
+        }
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> Result<Option<SseEvent>, DecodeError> {
+        if let Some(event) = self.decode(src)? {
+            return Ok(Some(event));
+        }
+
+        // The spec discards an event that was not terminated by a blank line.
+        src.clear();
+        self.event_type.clear();
+        self.data.clear();
+        self.retry = None;
+        self.has_data = false;
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode_chunks(chunks: &[&[u8]]) -> Vec<SseEvent> {
+        let mut decoder = SseDecoder::new();
+        let mut buffer = BytesMut::new();
+        let mut events = Vec::new();
+
+        for chunk in chunks {
+            buffer.extend_from_slice(chunk);
+            while let Some(event) = decoder.decode(&mut buffer).unwrap() {
+                events.push(event);
+            }
+        }
+
+        while let Some(event) = decoder.decode_eof(&mut buffer).unwrap() {
+            events.push(event);
+        }
+
+        events
+    }
+
+    fn decode_all(input: &[u8]) -> Vec<SseEvent> {
+        decode_chunks(&[input])
+    }
+
+    fn message(data: &str) -> SseEvent {
+        SseEvent {
+            event: "message".to_string(),
+            data: data.to_string(),
+            id: None,
+            retry: None,
+        }
+    }
+
+    #[test]
+    fn test_single_event() {
+        let events = decode_all(b"data: hello\n\n");
+        assert_eq!(events, vec![message("hello")]);
+    }
+
+    #[test]
+    fn test_named_event() {
+        let events = decode_all(b"event: content_block_delta\ndata: {}\n\n");
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].event, "content_block_delta");
+        assert_eq!(events[0].data, "{}");
+    }
+
+    #[test]
+    fn test_event_type_resets_between_events() {
+        let events = decode_all(b"event: ping\ndata: a\n\ndata: b\n\n");
+        assert_eq!(events[0].event, "ping");
+        assert_eq!(events[1].event, "message");
+    }
+
+    #[test]
+    fn test_multiline_data() {
+        let events = decode_all(b"data: line one\ndata: line two\ndata:\n\n");
+        assert_eq!(events, vec![message("line one\nline two\n")]);
+    }
+
+    #[test]
+    fn test_comments_are_ignored() {
+        let events = decode_all(b": keep-alive\ndata: x\n: another\n\n: trailing\n\n");
+        assert_eq!(events, vec![message("x")]);
+    }
+
+    #[test]
+    fn test_crlf_line_endings() {
+        let events = decode_all(b"event: a\r\ndata: one\r\n\r\ndata: two\r\n\r\n");
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].event, "a");
+        assert_eq!(events[0].data, "one");
+        assert_eq!(events[1].data, "two");
+    }
+
+    #[test]
+    fn test_cr_only_line_endings() {
+        let events = decode_all(b"data: one\r\rdata: two\r\r");
+        assert_eq!(events, vec![message("one"), message("two")]);
+    }
+
+    #[test]
+    fn test_mixed_line_endings() {
+        let events = decode_all(b"data: a\rdata: b\ndata: c\r\n\n");
+        assert_eq!(events, vec![message("a\nb\nc")]);
+    }
+
+    #[test]
+    fn test_crlf_split_across_chunks() {
+        let events = decode_chunks(&[b"data: one\r", b"\n\r", b"\ndata: two\r\n\r\n"]);
+        assert_eq!(events, vec![message("one"), message("two")]);
+    }
+
+    #[test]
+    fn test_cr_at_chunk_end_followed_by_data() {
+        let events = decode_chunks(&[b"data: one\r", b"\r", b"data: two\n\n"]);
+        assert_eq!(events, vec![message("one"), message("two")]);
+    }
+
+    #[test]
+    fn test_only_one_leading_space_is_stripped() {
+        let events = decode_all(b"data:  padded\n\ndata:tight\n\n");
+        assert_eq!(events, vec![message(" padded"), message("tight")]);
+    }
+
+    #[test]
+    fn test_field_without_colon() {
+        let events = decode_all(b"data\n\n");
+        assert_eq!(events, vec![message("")]);
+    }
+
+    #[test]
+    fn test_empty_data_buffer_does_not_dispatch() {
+        let events = decode_all(b"event: ping\n\nid: 1\n\n");
+        assert!(events.is_empty());
+    }
+
+    #[test]
+    fn test_unknown_fields_are_ignored() {
+        let events = decode_all(b"foo: bar\ndata: x\nbaz\n\n");
+        assert_eq!(events, vec![message("x")]);
+    }
+
+    #[test]
+    fn test_id_persists_across_events() {
+        let events = decode_all(b"id: 7\ndata: a\n\ndata: b\n\nid\ndata: c\n\n");
+        assert_eq!(events[0].id.as_deref(), Some("7"));
+        assert_eq!(events[1].id.as_deref(), Some("7"));
+        assert_eq!(events[2].id.as_deref(), Some(""));
+    }
+
+    #[test]
+    fn test_id_with_null_is_ignored() {
+        let mut decoder = SseDecoder::new();
+        let mut buffer = BytesMut::from(&b"id: 1\ndata: a\n\nid: 2\0\ndata: b\n\n"[..]);
+
+        let first = decoder.decode(&mut buffer).unwrap().unwrap();
+        let second = decoder.decode(&mut buffer).unwrap().unwrap();
+
+        assert_eq!(first.id.as_deref(), Some("1"));
+        assert_eq!(second.id.as_deref(), Some("1"));
+        assert_eq!(decoder.last_event_id(), Some("1"));
+    }
+
+    #[test]
+    fn test_retry_field() {
+        let mut decoder = SseDecoder::new();
+        let mut buffer = BytesMut::from(&b"retry: 1500\ndata: a\n\ndata: b\n\n"[..]);
+
+        let first = decoder.decode(&mut buffer).unwrap().unwrap();
+        let second = decoder.decode(&mut buffer).unwrap().unwrap();
+
+        assert_eq!(first.retry, Some(Duration::from_millis(1500)));
+        assert_eq!(second.retry, None);
+        assert_eq!(
+            decoder.reconnection_time(),
+            Some(Duration::from_millis(1500))
+        );
+    }
+
+    #[test]
+    fn test_invalid_retry_is_ignored() {
+        let mut decoder = SseDecoder::new();
+        let mut buffer = BytesMut::from(&b"retry: 10s\nretry: -1\nretry:\ndata: a\n\n"[..]);
+
+        let event = decoder.decode(&mut buffer).unwrap().unwrap();
+
+        assert_eq!(event.retry, None);
+        assert_eq!(decoder.reconnection_time(), None);
+    }
+
+    #[test]
+    fn test_leading_bom_is_stripped() {
+        let events = decode_all(b"\xEF\xBB\xBFdata: x\n\n");
+        assert_eq!(events, vec![message("x")]);
+    }
+
+    #[test]
+    fn test_bom_split_across_chunks() {
+        let events = decode_chunks(&[b"\xEF", b"\xBB", b"\xBFdata: x\n\n"]);
+        assert_eq!(events, vec![message("x")]);
+    }
+
+    #[test]
+    fn test_unterminated_event_is_discarded_at_eof() {
+        let events = decode_all(b"data: complete\n\ndata: partial\n");
+        assert_eq!(events, vec![message("complete")]);
+
+        let events = decode_all(b"data: no newline");
+        assert!(events.is_empty());
+    }
+
+    #[test]
+    fn test_multibyte_utf8_split_across_chunks() {
+        let input = "data: héllo → 世界\n\n".as_bytes();
+        let split = input.iter().position(|&b| b == 0xE4).unwrap() + 1;
+        let events = decode_chunks(&[&input[..split], &input[split..]]);
+        assert_eq!(events, vec![message("héllo → 世界")]);
+    }
+
+    #[test]
+    fn test_byte_by_byte_matches_whole_input() {
+        let input: &[u8] = b": hi\r\nevent: delta\r\nid: 42\r\ndata: {\"a\":1}\r\ndata: x\r\n\r\nretry: 20\rdata: y\r\r";
+        let expected = decode_all(input);
+        assert_eq!(expected.len(), 2);
+
+        let bytes: Vec<&[u8]> = input.chunks(1).collect();
+        assert_eq!(decode_chunks(&bytes), expected);
+    }
+
+    #[test]
+    fn test_every_two_way_split_matches_whole_input() {
+        let input: &[u8] =
+            b"\xEF\xBB\xBFevent: a\r\ndata: \xC3\xA9t\xC3\xA9\r\n\r\n:c\rdata: b\r\rdata: c\n\n";
+        let expected = decode_all(input);
+        assert_eq!(expected.len(), 3);
+
+        for split in 0..=input.len() {
+            let (left, right) = input.split_at(split);
+            assert_eq!(
+                decode_chunks(&[left, right]),
+                expected,
+                "split at {}",
+                split
+            );
+        }
+    }
+
+    #[test]
+    fn test_openai_style_stream() {
+        let events = decode_all(
+            b"data: {\"choices\":[{\"delta\":{\"content\":\"Hi\"}}]}\n\ndata: [DONE]\n\n",
+        );
+        assert_eq!(events.len(), 2);
+        assert!(!events[0].is_done());
+        assert!(events[1].is_done());
+
+        let chunk: serde_json::Value = events[0].json().unwrap();
+        assert_eq!(chunk["choices"][0]["delta"]["content"], "Hi");
+    }
+
+    #[test]
+    fn test_json_parse_error() {
+        let event = message("not json");
+        let result: ProviderResult<serde_json::Value> = event.json();
+        match result {
+            Err(ProviderError::ParseError(msg)) => assert!(msg.contains("message")),
+            _ => panic!("Expected ParseError"),
+        }
+    }
+}
@@ -0,0 +1,170 @@
+use crate::providers::error::{ProviderError, ProviderResult};
+use bytes::BytesMut;
+use futures::{Stream, StreamExt};
+use std::fmt::Display;
+use std::pin::Pin;
+use tokio_util::codec::Decoder;
+
+pub type DecodedStream<T> = Pin<Box<dyn Stream<Item = ProviderResult<T>> + Send>>;
+
+/// Error of the stream decoders. `Decoder` requires a conversion from
+/// `io::Error`, which only happens when a decoder reads a response body
+/// through a `FramedRead`, so it is a network error here rather than a
+/// blanket conversion on [`ProviderError`].
+#[derive(Debug, Clone)]
+pub struct DecodeError(pub ProviderError);
+
+impl From<std::io::Error> for DecodeError {
+    fn from(err: std::io::Error) -> Self {
+        DecodeError(ProviderError::NetworkError(format!(
+            "Failed to read response stream: {}",
+            err
+        )))
+    }
+}
+
+impl From<ProviderError> for DecodeError {
+    fn from(err: ProviderError) -> Self {
+        DecodeError(err)
+    }
+}
+
+impl From<DecodeError> for ProviderError {
+    fn from(err: DecodeError) -> Self {
+        err.0
+    }
+}
+
+struct DecodeState<S, D> {
+    chunks: S,
+    decoder: D,
+    buffer: BytesMut,
+    finished: bool,
+}
+
+/// Runs `decoder` over a stream of raw body chunks. The stream ends after the
+/// first transport or decoding error.
+pub fn decode_stream<S, B, E, D>(chunks: S, decoder: D) -> DecodedStream<D::Item>
+where
+    S: Stream<Item = Result<B, E>> + Send + Unpin + 'static,
+    B: AsRef<[u8]>,
+    E: Display,
+    D: Decoder<Error = DecodeError> + Send + 'static,
+    D::Item: Send + 'static,
+{
+    let state = DecodeState {
+        chunks,
+        decoder,
+        buffer: BytesMut::new(),
+        finished: false,
+    };
+
+    Box::pin(futures::stream::unfold(state, |mut state| async move {
+        loop {
+            if state.finished {
+                return match state.decoder.decode_eof(&mut state.buffer) {
+                    Ok(Some(item)) => Some((Ok(item), state)),
+                    Ok(None) => None,
+                    Err(e) => {
+                        state.buffer.clear();
+                        Some((Err(e.into()), state))
+                    }
+                };
+            }
+
+            match state.decoder.decode(&mut state.buffer) {
+                Ok(Some(item)) => return Some((Ok(item), state)),
+                Ok(None) => {}
+                Err(e) => {
+                    state.finished = true;
+                    state.buffer.clear();
+                    return Some((Err(e.into()), state));
+                }
+            }
+
+            match state.chunks.next().await {
+                Some(Ok(chunk)) => state.buffer.extend_from_slice(chunk.as_ref()),
+                Some(Err(e)) => {
+                    state.finished = true;
+                    state.buffer.clear();
+                    return Some((
+                        Err(ProviderError::NetworkError(format!(
+                            "Failed to read response stream: {}",
+                            e
+                        ))),
+                        state,
+                    ));
+                }
+                None => state.finished = true,
+            }
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::ndjson::NdjsonDecoder;
+    use crate::http::sse::SseDecoder;
+
+    fn chunks(
+        parts: Vec<&'static str>,
+    ) -> impl Stream<Item = Result<&'static [u8], String>> + Send + Unpin {
+        futures::stream::iter(parts.into_iter().map(|part| Ok(part.as_bytes())))
+    }
+
+    #[tokio::test]
+    async fn test_decode_sse_stream() {
+        let stream = decode_stream(
+            chunks(vec!["data: Hel", "lo\n\nda", "ta: world\n", "\n"]),
+            SseDecoder::new(),
+        );
+
+        let events: Vec<_> = stream.collect().await;
+        let data: Vec<String> = events.into_iter().map(|e| e.unwrap().data).collect();
+        assert_eq!(data, vec!["Hello", "world"]);
+    }
+
+    #[tokio::test]
+    async fn test_decode_ndjson_stream_with_trailing_record() {
+        let stream = decode_stream(
+            chunks(vec!["{\"n\":1}\n{\"n\"", ":2}"]),
+            NdjsonDecoder::<serde_json::Value>::new(),
+        );
+
+        let records: Vec<_> = stream.collect().await;
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[1].as_ref().unwrap()["n"], 2);
+    }
+
+    #[tokio::test]
+    async fn test_transport_error_ends_stream() {
+        let parts: Vec<Result<&'static [u8], String>> = vec![
+            Ok(b"data: a\n\n"),
+            Err("connection reset".to_string()),
+            Ok(b"data: b\n\n"),
+        ];
+        let stream = decode_stream(futures::stream::iter(parts), SseDecoder::new());
+
+        let events: Vec<_> = stream.collect().await;
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].as_ref().unwrap().data, "a");
+        match &events[1] {
+            Err(ProviderError::NetworkError(msg)) => assert!(msg.contains("connection reset")),
+            _ => panic!("Expected NetworkError"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_decode_error_ends_stream() {
+        let stream = decode_stream(
+            chunks(vec!["{\"n\":1}\nnot json\n{\"n\":3}\n"]),
+            NdjsonDecoder::<serde_json::Value>::new(),
+        );
+
+        let records: Vec<_> = stream.collect().await;
+        assert_eq!(records.len(), 2);
+        assert!(records[0].is_ok());
+        assert!(matches!(records[1], Err(ProviderError::ParseError(_))));
+    }
+}
@@ -0,0 +1,451 @@
+use crate::config::{NetworkConfig, WireLogConfig};
+use crate::http::auth::{self, AuthScheme};
+use crate::providers::error::{ProviderError, ProviderResult};
+use reqwest::header::HeaderMap;
+use reqwest::{Request, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const DEFAULT_MAX_FILES: usize = 3;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WireHeader {
+    pub name: String,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WireRequest {
+    pub method: String,
+    pub url: String,
+    pub headers: Vec<WireHeader>,
+    pub body: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WireResponse {
+    pub status: u16,
+    pub headers: Vec<WireHeader>,
+    pub body: String,
+}
+
+/// One request/response exchange, loosely modelled on a HAR entry. Header
+/// values outside the loggable allowlist and every occurrence of the API key
+/// are redacted before the entry is built.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WireLogEntry {
+    pub started_at_ms: u64,
+    pub time_ms: u64,
+    pub request: WireRequest,
+    pub response: Option<WireResponse>,
+    pub error: Option<String>,
+}
+
+impl WireRequest {
+    pub fn capture(request: &Request, auth_scheme: &AuthScheme, api_key: &str) -> Self {
+        let body = request
+            .body()
+            .and_then(|body| body.as_bytes())
+            .map(|bytes| String::from_utf8_lossy(bytes).into_owned())
+            .unwrap_or_default();
+
+        WireRequest {
+            method: request.method().to_string(),
+            url: auth::redact_secret(&auth::redact_url(request.url(), auth_scheme), api_key),
+            headers: wire_headers(request.headers()),
+            body: auth::redact_secret(&body, api_key),
+        }
+    }
+}
+
+impl WireResponse {
+    pub fn capture(status: StatusCode, headers: &HeaderMap, body: &str, api_key: &str) -> Self {
+        WireResponse {
+            status: status.as_u16(),
+            headers: wire_headers(headers),
+            body: auth::redact_secret(body, api_key),
+        }
+    }
+}
+
+impl WireLogEntry {
+    pub fn new(
+        request: WireRequest,
+        started_at: SystemTime,
+        elapsed: Duration,
+        outcome: Result<WireResponse, String>,
+    ) -> Self {
+        let (response, error) = match outcome {
+            Ok(response) => (Some(response), None),
+            Err(error) => (None, Some(error)),
+        };
+
+        WireLogEntry {
+            started_at_ms: started_at
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis() as u64,
+            time_ms: elapsed.as_millis() as u64,
+            request,
+            response,
+            error,
+        }
+    }
+}
+
+fn wire_headers(headers: &HeaderMap) -> Vec<WireHeader> {
+    auth::redacted_header_pairs(headers)
+        .into_iter()
+        .map(|(name, value)| WireHeader { name, value })
+        .collect()
+}
+
+/// Append-only JSONL log of wire exchanges, rotated to `<path>.1`,
+/// `<path>.2`, ... once the current file would exceed `max_bytes`.
+#[derive(Debug, Clone)]
+pub struct WireLog {
+    path: PathBuf,
+    max_bytes: u64,
+    max_files: usize,
+    lock: Arc<Mutex<()>>,
+}
+
+impl WireLog {
+    pub fn new(path: impl Into<PathBuf>, max_bytes: u64) -> Self {
+        WireLog {
+            path: path.into(),
+            max_bytes,
+            max_files: DEFAULT_MAX_FILES,
+            lock: Arc::new(Mutex::new(())),
+        }
+    }
+
+    pub fn with_max_files(mut self, max_files: usize) -> Self {
+        self.max_files = max_files.max(1);
+        self
+    }
+
+    /// Returns `None` when the wire log is disabled. Relative paths are
+    /// resolved against `work_dir`, the extension's working directory.
+    pub fn from_config(config: &WireLogConfig, work_dir: &Path) -> Option<Self> {
+        if !config.enabled {
+            return None;
+        }
+
+        Some(
+            WireLog::new(work_dir.join(&config.path), config.max_size_kb * 1024)
+                .with_max_files(config.max_files),
+        )
+    }
+
+    /// The wire log configured in `network`, with a relative path resolved
+    /// against the current directory. `None` when it is disabled.
+    pub fn from_network(network: &NetworkConfig) -> ProviderResult<Option<Self>> {
+        let Some(config) = network.wire_log.as_ref().filter(|config| config.enabled) else {
+            return Ok(None);
+        };
+        let work_dir = std::env::current_dir().map_err(|e| {
+            ProviderError::ConfigError(format!("Failed to resolve the wire log path: {}", e))
+        })?;
+        Ok(Self::from_config(config, &work_dir))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn record(&self, entry: &WireLogEntry) -> ProviderResult<()> {
+        let mut line = serde_json::to_string(entry).map_err(|e| {
+            ProviderError::ParseError(format!("Failed to serialize wire log entry: {}", e))
+        })?;
+        line.push('\n');
+
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        self.append(&line).map_err(|e| {
+            ProviderError::ConfigError(format!(
+                "Failed to write wire log {}: {}",
+                self.path.display(),
+                e
+            ))
+        })
+    }
+
+    fn append(&self, line: &str) -> io::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let current_size = fs::metadata(&self.path).map(|m| m.len()).unwrap_or(0);
+        if current_size > 0 && current_size + line.len() as u64 > self.max_bytes {
+            self.rotate()?;
+        }
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        file.write_all(line.as_bytes())
+    }
+
+    fn rotate(&self) -> io::Result<()> {
+        if self.max_files <= 1 {
+            return fs::remove_file(&self.path);
+        }
+
+        let oldest = self.rotated_path(self.max_files - 1);
+        if oldest.exists() {
+            fs::remove_file(&oldest)?;
+        }
+        for n in (1..self.max_files - 1).rev() {
+            let from = self.rotated_path(n);
+            if from.exists() {
+                fs::rename(&from, self.rotated_path(n + 1))?;
+            }
+        }
+        fs::rename(&self.path, self.rotated_path(1))
+    }
+
+    fn rotated_path(&self, n: usize) -> PathBuf {
+        let mut name = self.path.as_os_str().to_owned();
+        name.push(format!(".{}", n));
+        PathBuf::from(name)
+    }
+}
+
+/// Wire log for a vendor SDK client, which does not expose its HTTP
+/// exchanges. Each call is logged as a `POST` of the JSON the SDK sends,
+/// without headers, and a response holding the JSON it decoded. Streamed
+/// responses are logged without a body.
+#[derive(Debug, Clone)]
+pub struct SdkWireLog {
+    wire_log: WireLog,
+    url: String,
+}
+
+impl SdkWireLog {
+    pub fn new(wire_log: WireLog, url: impl Into<String>) -> Self {
+        SdkWireLog {
+            wire_log,
+            url: url.into(),
+        }
+    }
+
+    /// Records one call. `outcome` is the serialized response or the error.
+    /// A failed write is logged rather than returned, so it never fails the
+    /// request.
+    pub fn record(
+        &self,
+        request: &impl Serialize,
+        api_key: &str,
+        started_at: SystemTime,
+        elapsed: Duration,
+        outcome: Result<String, String>,
+    ) {
+        let body = serde_json::to_string(request).unwrap_or_default();
+        let request = WireRequest {
+            method: "POST".to_string(),
+            url: auth::redact_secret(&self.url, api_key),
+            headers: Vec::new(),
+            body: auth::redact_secret(&body, api_key),
+        };
+        let outcome = outcome
+            .map(|body| WireResponse {
+                status: StatusCode::OK.as_u16(),
+                headers: Vec::new(),
+                body: auth::redact_secret(&body, api_key),
+            })
+            .map_err(|error| auth::redact_secret(&error, api_key));
+        let entry = WireLogEntry::new(request, started_at, elapsed, outcome);
+        if let Err(e) = self.wire_log.record(&entry) {
+            log::warn!("Failed to write wire log: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::{HeaderValue, AUTHORIZATION, CONTENT_TYPE};
+    use reqwest::{Method, Url};
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "zed-copilot-wire-log-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn entry(body: &str) -> WireLogEntry {
+        WireLogEntry::new(
+            WireRequest {
+                method: "POST".to_string(),
+                url: "https://api.example.com/v1".to_string(),
+                headers: Vec::new(),
+                body: body.to_string(),
+            },
+            SystemTime::now(),
+            Duration::from_millis(12),
+            Ok(WireResponse {
+                status: 200,
+                headers: Vec::new(),
+                body: "{}".to_string(),
+            }),
+        )
+    }
+
+    fn read_entries(path: &Path) -> Vec<WireLogEntry> {
+        fs::read_to_string(path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_capture_request_redacts_credentials() {
+        let mut request = Request::new(
+            Method::POST,
+            Url::parse("https://example.com/v1/models?key=g-secret").unwrap(),
+        );
+        request
+            .headers_mut()
+            .insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        request
+            .headers_mut()
+            .insert(AUTHORIZATION, HeaderValue::from_static("Bearer g-secret"));
+        *request.body_mut() = Some(r#"{"echo":"g-secret","prompt":"hi"}"#.into());
+
+        let captured = WireRequest::capture(&request, &AuthScheme::gemini(), "g-secret");
+        let json = serde_json::to_string(&captured).unwrap();
+
+        assert!(!json.contains("g-secret"));
+        assert_eq!(captured.method, "POST");
+        assert!(captured.body.contains("\"prompt\":\"hi\""));
+        assert!(captured.headers.contains(&WireHeader {
+            name: "content-type".to_string(),
+            value: "application/json".to_string(),
+        }));
+    }
+
+    #[test]
+    fn test_capture_response() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-request-id", HeaderValue::from_static("req-1"));
+        headers.insert("set-cookie", HeaderValue::from_static("session=abc"));
+
+        let response = WireResponse::capture(StatusCode::BAD_REQUEST, &headers, "oops", "sk");
+        assert_eq!(response.status, 400);
+        assert_eq!(response.headers[0].value, "req-1");
+        assert_eq!(response.headers[1].value, "[REDACTED]");
+    }
+
+    #[test]
+    fn test_entry_records_error() {
+        let entry = WireLogEntry::new(
+            entry("").request,
+            SystemTime::now(),
+            Duration::from_millis(5),
+            Err("Network error: connection refused".to_string()),
+        );
+        assert!(entry.response.is_none());
+        assert_eq!(entry.time_ms, 5);
+        assert!(entry.error.unwrap().contains("connection refused"));
+    }
+
+    #[test]
+    fn test_record_appends_jsonl() {
+        let dir = temp_dir("append");
+        let log = WireLog::new(dir.join("nested/wire.jsonl"), 1024 * 1024);
+
+        log.record(&entry("first")).unwrap();
+        log.record(&entry("second")).unwrap();
+
+        let entries = read_entries(log.path());
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1].request.body, "second");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_record_rotates_at_size_cap() {
+        let dir = temp_dir("rotate");
+        let path = dir.join("wire.jsonl");
+        let line_len = serde_json::to_string(&entry("0")).unwrap().len() as u64 + 1;
+        let log = WireLog::new(&path, line_len * 2).with_max_files(3);
+
+        for body in ["0", "1", "2", "3", "4", "5", "6"] {
+            log.record(&entry(body)).unwrap();
+        }
+
+        let bodies = |p: &Path| -> Vec<String> {
+            read_entries(p)
+                .into_iter()
+                .map(|e| e.request.body)
+                .collect()
+        };
+        assert_eq!(bodies(&path), vec!["6"]);
+        assert_eq!(bodies(&dir.join("wire.jsonl.1")), vec!["4", "5"]);
+        assert_eq!(bodies(&dir.join("wire.jsonl.2")), vec!["2", "3"]);
+        assert!(!dir.join("wire.jsonl.3").exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_record_single_file_truncates() {
+        let dir = temp_dir("single");
+        let path = dir.join("wire.jsonl");
+        let log = WireLog::new(&path, 1).with_max_files(1);
+
+        log.record(&entry("old")).unwrap();
+        log.record(&entry("new")).unwrap();
+
+        let entries = read_entries(&path);
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].request.body, "new");
+        assert!(!dir.join("wire.jsonl.1").exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_write_failure_is_not_transient() {
+        let dir = temp_dir("blocked");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("file"), "").unwrap();
+        let log = WireLog::new(dir.join("file").join("wire.jsonl"), 1024);
+
+        let error = log.record(&entry("body")).unwrap_err();
+        assert!(matches!(error, ProviderError::ConfigError(_)));
+        assert!(!crate::http::client::is_transient_error(&error));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_from_config() {
+        let work_dir = Path::new("/work");
+        assert!(WireLog::from_config(&WireLogConfig::default(), work_dir).is_none());
+
+        let config = WireLogConfig {
+            enabled: true,
+            max_size_kb: 2,
+            ..Default::default()
+        };
+        let log = WireLog::from_config(&config, work_dir).unwrap();
+        assert_eq!(log.path(), Path::new("/work/wire-log.jsonl"));
+        assert_eq!(log.max_bytes, 2048);
+
+        let config = WireLogConfig {
+            enabled: true,
+            path: "/var/log/wire.jsonl".to_string(),
+            ..Default::default()
+        };
+        let log = WireLog::from_config(&config, work_dir).unwrap();
+        assert_eq!(log.path(), Path::new("/var/log/wire.jsonl"));
+    }
+}
@@ -13,7 +13,8 @@ pub mod providers;
 pub mod chat;
 pub mod ui;
 
-use chat::ZedContextProvider;
+use chat::{ContextError, PromptContext, ZedContextProvider};
+use config::PrivacyPolicy;
 use ui::ChatPanel;
 
 pub struct ZedCopilot {
@@ -66,6 +67,19 @@ impl ZedCopilot {
     pub fn get_context_provider_mut(&mut self) -> &mut ZedContextProvider {
         &mut self.context_provider
     }
+
+    /// Editor context for the next prompt, with whatever `policy` blocks
+    /// left out and reported in the chat panel.
+    pub fn prompt_context(
+        &mut self,
+        policy: &PrivacyPolicy,
+    ) -> Result<PromptContext, ContextError> {
+        let context = PromptContext::collect(&self.context_provider, policy)?;
+        if let Some(panel) = self.chat_panel.as_mut() {
+            panel.report_withheld_context(&context);
+        }
+        Ok(context)
+    }
 }
 
 impl Default for ZedCopilot {
@@ -208,4 +222,26 @@ mod tests {
 
         assert!(extension.is_chat_panel_open());
     }
+
+    #[test]
+    fn test_prompt_context_reports_blocked_file() {
+        let root = std::env::temp_dir().join(format!("zed-copilot-lib-{}", std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        let env_file = root.join(".env");
+        std::fs::write(&env_file, "API_KEY=sk-live").unwrap();
+
+        let mut extension = ZedCopilot::new();
+        extension.open_chat_panel();
+        *extension.get_context_provider_mut() =
+            ZedContextProvider::new().with_file_path(env_file.clone());
+        let policy = PrivacyPolicy::new(&config::PrivacyConfig::default(), Some(&root)).unwrap();
+
+        let context = extension.prompt_context(&policy).unwrap();
+        assert!(context.is_empty());
+        assert_eq!(context.blocked[0].path, env_file);
+        let messages = extension.get_chat_panel().unwrap().get_messages();
+        assert!(messages[0].content.contains(".env"));
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
 }
@@ -1,4 +1,8 @@
+use crate::config::{GenerationConfig, NetworkConfig};
 use crate::http::anthropic::AnthropicHttpClient;
+use crate::http::metrics::Metrics;
+use crate::http::transport;
+use crate::http::wire_log::WireLog;
 use crate::providers::error::{ProviderError, ProviderResult};
 use crate::providers::trait_def::AiProvider;
 use futures::Stream;
@@ -8,6 +12,9 @@ pub struct AnthropicProvider {
     api_key: String,
     model: String,
     api_base: String,
+    network: Option<NetworkConfig>,
+    metrics: Option<Metrics>,
+    generation: GenerationConfig,
     http_client: AnthropicHttpClient,
 }
 
@@ -26,21 +33,79 @@ impl AnthropicProvider {
         }
 
         let api_base = "https://api.anthropic.com/v1".to_string();
-        let http_client = AnthropicHttpClient::new(api_base.clone())?;
+        let generation = GenerationConfig::default();
+        let http_client = Self::build_http_client(api_base.clone(), None, None, &generation)?;
 
         Ok(AnthropicProvider {
             api_key,
             model,
             api_base,
+            network: None,
+            metrics: None,
+            generation,
             http_client,
         })
     }
 
     pub fn with_api_base(mut self, api_base: String) -> ProviderResult<Self> {
         self.api_base = api_base.clone();
-        self.http_client = AnthropicHttpClient::new(api_base)?;
+        self.http_client = Self::build_http_client(
+            api_base,
+            self.network.as_ref(),
+            self.metrics.as_ref(),
+            &self.generation,
+        )?;
+        Ok(self)
+    }
+
+    pub fn with_network_config(mut self, network: NetworkConfig) -> ProviderResult<Self> {
+        self.http_client = Self::build_http_client(
+            self.api_base.clone(),
+            Some(&network),
+            self.metrics.as_ref(),
+            &self.generation,
+        )?;
+        self.network = Some(network);
+        Ok(self)
+    }
+
+    pub fn with_metrics(mut self, metrics: Metrics) -> Self {
+        self.http_client = self.http_client.with_metrics(metrics.clone());
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Overrides the vendor defaults for temperature and completion length.
+    pub fn with_generation(mut self, generation: GenerationConfig) -> ProviderResult<Self> {
+        self.http_client = self
+            .http_client
+            .with_generation(generation.temperature, generation.max_tokens)?;
+        self.generation = generation;
         Ok(self)
     }
+
+    fn build_http_client(
+        api_base: String,
+        network: Option<&NetworkConfig>,
+        metrics: Option<&Metrics>,
+        generation: &GenerationConfig,
+    ) -> ProviderResult<AnthropicHttpClient> {
+        let mut http_client = AnthropicHttpClient::new(api_base)?;
+        if let Some(network) = network {
+            http_client = http_client.with_http_client(transport::build_http_client(network)?)?;
+            if let Some(wire_log) = WireLog::from_network(network)? {
+                http_client = http_client.with_wire_log(wire_log);
+            }
+        }
+        if *generation != GenerationConfig::default() {
+            http_client =
+                http_client.with_generation(generation.temperature, generation.max_tokens)?;
+        }
+        if let Some(metrics) = metrics {
+            http_client = http_client.with_metrics(metrics.clone());
+        }
+        Ok(http_client)
+    }
 }
 
 #[async_trait::async_trait(?Send)]
@@ -69,6 +134,18 @@ impl AiProvider for AnthropicProvider {
         &self.model
     }
 
+    fn temperature(&self) -> Option<f32> {
+        self.generation.temperature
+    }
+
+    fn api_base(&self) -> Option<&str> {
+        Some(&self.api_base)
+    }
+
+    fn max_tokens(&self) -> Option<u32> {
+        self.generation.max_tokens
+    }
+
     async fn complete_stream(
         &self,
         prompt: &str,
@@ -169,4 +246,36 @@ mod tests {
         assert_eq!(payload["model"], "claude-3-sonnet");
         assert_eq!(payload["messages"][0]["content"], "test prompt");
     }
+
+    #[test]
+    fn test_anthropic_provider_with_network_config() {
+        let network = NetworkConfig {
+            proxy: Some("http://proxy.corp:3128".to_string()),
+            ..Default::default()
+        };
+        let provider =
+            AnthropicProvider::new("sk-ant-test-key".to_string(), "claude-3-sonnet".to_string())
+                .unwrap()
+                .with_network_config(network)
+                .unwrap()
+                .with_api_base("https://gateway.corp/v1".to_string())
+                .unwrap();
+        assert_eq!(
+            provider.network.and_then(|n| n.proxy).as_deref(),
+            Some("http://proxy.corp:3128")
+        );
+    }
+
+    #[test]
+    fn test_anthropic_provider_with_invalid_network_config() {
+        let network = NetworkConfig {
+            proxy: Some("not a url".to_string()),
+            ..Default::default()
+        };
+        let result =
+            AnthropicProvider::new("sk-ant-test-key".to_string(), "claude-3-sonnet".to_string())
+                .unwrap()
+                .with_network_config(network);
+        assert!(result.is_err());
+    }
 }
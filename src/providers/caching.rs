@@ -0,0 +1,920 @@
+use crate::config::CacheConfig;
+use crate::http::clock::{self, Clock};
+use crate::providers::error::{ProviderError, ProviderResult};
+use crate::providers::trait_def::AiProvider;
+use futures::channel::oneshot;
+use futures::future::{FutureExt, Shared};
+use futures::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+type TextStream = Pin<Box<dyn Stream<Item = ProviderResult<String>> + Send>>;
+type InFlight = Shared<oneshot::Receiver<ProviderResult<String>>>;
+
+/// Everything that shapes a response: the provider and endpoint, the
+/// profile it was built for, the prompt and the sampling settings.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct CacheKey {
+    provider: String,
+    api_base: Option<String>,
+    profile: Option<String>,
+    model: String,
+    prompt: String,
+    temperature_bits: Option<u32>,
+    max_tokens: Option<u32>,
+}
+
+impl CacheKey {
+    pub fn new(provider: &dyn AiProvider, profile: Option<&str>, prompt: &str) -> Self {
+        CacheKey {
+            provider: provider.name().to_string(),
+            api_base: provider.api_base().map(str::to_string),
+            profile: profile.map(str::to_string),
+            model: provider.model().to_string(),
+            prompt: prompt.to_string(),
+            temperature_bits: provider.temperature().map(f32::to_bits),
+            max_tokens: provider.max_tokens(),
+        }
+    }
+
+    /// SHA-256 of the key's JSON encoding, whose field order is fixed, so
+    /// disk entries keep their names across builds and Rust releases.
+    fn file_name(&self) -> String {
+        let encoded = serde_json::to_vec(self).unwrap_or_default();
+        let digest = Sha256::digest(&encoded);
+        let hex: String = digest.iter().map(|byte| format!("{:02x}", byte)).collect();
+        format!("{}.json", hex)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CachePolicy {
+    capacity: usize,
+    ttl: Duration,
+    max_temperature: f32,
+    disk_path: Option<PathBuf>,
+    profile: Option<String>,
+    clock: Arc<dyn Clock>,
+}
+
+impl CachePolicy {
+    pub fn new(capacity: usize, ttl: Duration) -> Self {
+        CachePolicy {
+            capacity: capacity.max(1),
+            ttl,
+            max_temperature: 0.0,
+            disk_path: None,
+            profile: None,
+            clock: clock::system_clock(),
+        }
+    }
+
+    /// Requests sampled above this temperature are coalesced but never
+    /// cached. Providers that leave the temperature to the vendor default
+    /// are treated as non-deterministic.
+    pub fn with_max_temperature(mut self, max_temperature: f32) -> Self {
+        self.max_temperature = max_temperature;
+        self
+    }
+
+    pub fn with_disk_store(mut self, path: impl Into<PathBuf>) -> Self {
+        self.disk_path = Some(path.into());
+        self
+    }
+
+    /// Profile the cached provider was built for. It is part of every key,
+    /// so profiles sharing a disk store never share entries.
+    pub fn with_profile(mut self, profile: impl Into<String>) -> Self {
+        self.profile = Some(profile.into());
+        self
+    }
+
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Relative disk paths are resolved against `work_dir`.
+    pub fn from_config(config: &CacheConfig, work_dir: &Path) -> Self {
+        let policy = CachePolicy::new(config.capacity, Duration::from_secs(config.ttl_secs))
+            .with_max_temperature(config.max_temperature);
+        match &config.disk_path {
+            Some(path) => policy.with_disk_store(work_dir.join(path)),
+            None => policy,
+        }
+    }
+
+    fn is_cacheable(&self, temperature: Option<f32>) -> bool {
+        temperature.is_some_and(|t| t <= self.max_temperature)
+    }
+}
+
+impl Default for CachePolicy {
+    fn default() -> Self {
+        Self::new(256, Duration::from_secs(3600))
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub coalesced: u64,
+    pub bypassed: u64,
+}
+
+struct MemoryEntry {
+    text: String,
+    expires_at: Instant,
+    last_used: u64,
+}
+
+/// Least-recently-used map; `order` indexes keys by their last-use tick.
+struct MemoryLru {
+    capacity: usize,
+    tick: u64,
+    entries: HashMap<CacheKey, MemoryEntry>,
+    order: BTreeMap<u64, CacheKey>,
+}
+
+impl MemoryLru {
+    fn new(capacity: usize) -> Self {
+        MemoryLru {
+            capacity,
+            tick: 0,
+            entries: HashMap::new(),
+            order: BTreeMap::new(),
+        }
+    }
+
+    fn get(&mut self, key: &CacheKey, now: Instant) -> Option<String> {
+        let entry = self.entries.get_mut(key)?;
+        if entry.expires_at <= now {
+            self.order.remove(&entry.last_used);
+            self.entries.remove(key);
+            return None;
+        }
+
+        self.tick += 1;
+        self.order.remove(&entry.last_used);
+        entry.last_used = self.tick;
+        self.order.insert(self.tick, key.clone());
+        Some(entry.text.clone())
+    }
+
+    fn insert(&mut self, key: CacheKey, text: String, expires_at: Instant) {
+        if let Some(previous) = self.entries.remove(&key) {
+            self.order.remove(&previous.last_used);
+        }
+
+        while self.entries.len() >= self.capacity {
+            let Some((_, oldest)) = self.order.pop_first() else {
+                break;
+            };
+            self.entries.remove(&oldest);
+        }
+
+        self.tick += 1;
+        self.order.insert(self.tick, key.clone());
+        self.entries.insert(
+            key,
+            MemoryEntry {
+                text,
+                expires_at,
+                last_used: self.tick,
+            },
+        );
+    }
+}
+
+/// Only the response and its expiry are stored; the key, which holds the
+/// prompt, is represented by the file name alone.
+#[derive(Serialize, Deserialize)]
+struct DiskRecord {
+    text: String,
+    expires_at_ms: u64,
+}
+
+/// One JSON file per entry, named by [`CacheKey::file_name`]. Each write
+/// removes expired entries and, beyond `capacity`, the ones closest to
+/// expiring.
+struct DiskStore {
+    dir: PathBuf,
+    capacity: usize,
+}
+
+impl DiskStore {
+    fn get(&self, key: &CacheKey) -> Option<String> {
+        let path = self.dir.join(key.file_name());
+        let record = read_record(&path)?;
+        if record.expires_at_ms <= unix_millis(SystemTime::now()) {
+            let _ = fs::remove_file(&path);
+            return None;
+        }
+        Some(record.text)
+    }
+
+    fn put(&self, key: &CacheKey, text: &str, ttl: Duration) -> ProviderResult<()> {
+        let record = DiskRecord {
+            text: text.to_string(),
+            expires_at_ms: unix_millis(SystemTime::now() + ttl),
+        };
+        let bytes = serde_json::to_vec(&record).map_err(|e| {
+            ProviderError::ParseError(format!("Failed to serialize cache entry: {}", e))
+        })?;
+
+        let path = self.dir.join(key.file_name());
+        let tmp = path.with_extension("json.tmp");
+        fs::create_dir_all(&self.dir)
+            .and_then(|()| fs::write(&tmp, bytes))
+            .and_then(|()| fs::rename(&tmp, &path))
+            .and_then(|()| self.prune())
+            .map_err(|e| {
+                ProviderError::ConfigError(format!(
+                    "Failed to write cache entry {}: {}",
+                    path.display(),
+                    e
+                ))
+            })
+    }
+
+    /// Only touches files named like cache entries, so a misconfigured
+    /// `disk_path` never loses unrelated files.
+    fn prune(&self) -> io::Result<()> {
+        let now = unix_millis(SystemTime::now());
+        let mut live = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if !is_entry_file(&path) {
+                continue;
+            }
+            match read_record(&path) {
+                Some(record) if record.expires_at_ms > now => {
+                    live.push((record.expires_at_ms, path))
+                }
+                _ => {
+                    let _ = fs::remove_file(&path);
+                }
+            }
+        }
+
+        if live.len() > self.capacity {
+            live.sort();
+            for (_, path) in &live[..live.len() - self.capacity] {
+                let _ = fs::remove_file(path);
+            }
+        }
+        Ok(())
+    }
+}
+
+fn read_record(path: &Path) -> Option<DiskRecord> {
+    serde_json::from_slice(&fs::read(path).ok()?).ok()
+}
+
+fn is_entry_file(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "json")
+        && path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .is_some_and(|stem| {
+                stem.len() == 64 && stem.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+            })
+}
+
+fn unix_millis(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+struct CacheStore {
+    memory: Mutex<MemoryLru>,
+    disk: Option<DiskStore>,
+    ttl: Duration,
+    clock: Arc<dyn Clock>,
+}
+
+impl CacheStore {
+    fn get(&self, key: &CacheKey) -> Option<String> {
+        let now = self.clock.now();
+        if let Some(text) = lock(&self.memory).get(key, now) {
+            return Some(text);
+        }
+
+        let text = self.disk.as_ref()?.get(key)?;
+        lock(&self.memory).insert(key.clone(), text.clone(), now + self.ttl);
+        Some(text)
+    }
+
+    fn put(&self, key: CacheKey, text: String) {
+        if let Some(disk) = &self.disk {
+            if let Err(e) = disk.put(&key, &text, self.ttl) {
+                log::warn!("Failed to write response cache entry: {}", e);
+            }
+        }
+        let expires_at = self.clock.now() + self.ttl;
+        lock(&self.memory).insert(key, text, expires_at);
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Removes the in-flight entry even if the leading request is dropped, so
+/// followers see a cancelled channel and issue their own request.
+struct InFlightGuard<'a> {
+    in_flight: &'a Mutex<HashMap<CacheKey, InFlight>>,
+    key: &'a CacheKey,
+}
+
+impl Drop for InFlightGuard<'_> {
+    fn drop(&mut self) {
+        lock(self.in_flight).remove(self.key);
+    }
+}
+
+/// `AiProvider` decorator that caches deterministic responses and shares one
+/// upstream call between identical concurrent `complete` requests. Streamed
+/// requests are cached once they finish but are not coalesced.
+pub struct CachingProvider {
+    inner: Box<dyn AiProvider>,
+    policy: CachePolicy,
+    store: Arc<CacheStore>,
+    in_flight: Mutex<HashMap<CacheKey, InFlight>>,
+    stats: Mutex<CacheStats>,
+}
+
+impl CachingProvider {
+    pub fn new(inner: Box<dyn AiProvider>, policy: CachePolicy) -> Self {
+        let store = CacheStore {
+            memory: Mutex::new(MemoryLru::new(policy.capacity)),
+            disk: policy.disk_path.clone().map(|dir| DiskStore {
+                dir,
+                capacity: policy.capacity,
+            }),
+            ttl: policy.ttl,
+            clock: Arc::clone(&policy.clock),
+        };
+
+        CachingProvider {
+            inner,
+            policy,
+            store: Arc::new(store),
+            in_flight: Mutex::new(HashMap::new()),
+            stats: Mutex::new(CacheStats::default()),
+        }
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        *lock(&self.stats)
+    }
+
+    fn cacheable(&self) -> bool {
+        self.policy.is_cacheable(self.inner.temperature())
+    }
+
+    fn lookup(&self, key: &CacheKey) -> Option<String> {
+        let hit = self.store.get(key);
+        let mut stats = lock(&self.stats);
+        match hit {
+            Some(_) => stats.hits += 1,
+            None => stats.misses += 1,
+        }
+        hit
+    }
+
+    async fn complete_uncoalesced(&self, key: &CacheKey, prompt: &str) -> ProviderResult<String> {
+        let result = self.inner.complete(prompt).await;
+        if let Ok(text) = &result {
+            if self.cacheable() {
+                self.store.put(key.clone(), text.clone());
+            }
+        }
+        result
+    }
+}
+
+#[async_trait::async_trait(?Send)]
+impl AiProvider for CachingProvider {
+    async fn complete(&self, prompt: &str) -> ProviderResult<String> {
+        let key = CacheKey::new(self.inner.as_ref(), self.policy.profile.as_deref(), prompt);
+
+        if !self.cacheable() {
+            lock(&self.stats).bypassed += 1;
+        } else if let Some(text) = self.lookup(&key) {
+            return Ok(text);
+        }
+
+        let pending = {
+            let mut in_flight = lock(&self.in_flight);
+            match in_flight.get(&key) {
+                Some(pending) => Err(pending.clone()),
+                None => {
+                    let (tx, rx) = oneshot::channel();
+                    in_flight.insert(key.clone(), rx.shared());
+                    Ok(tx)
+                }
+            }
+        };
+
+        match pending {
+            Ok(tx) => {
+                let guard = InFlightGuard {
+                    in_flight: &self.in_flight,
+                    key: &key,
+                };
+                let result = self.complete_uncoalesced(&key, prompt).await;
+                drop(guard);
+                let _ = tx.send(result.clone());
+                result
+            }
+            Err(pending) => match pending.await {
+                Ok(result) => {
+                    lock(&self.stats).coalesced += 1;
+                    result
+                }
+                Err(oneshot::Canceled) => self.complete_uncoalesced(&key, prompt).await,
+            },
+        }
+    }
+
+    async fn is_available(&self) -> bool {
+        self.inner.is_available().await
+    }
+
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn model(&self) -> &str {
+        self.inner.model()
+    }
+
+    fn temperature(&self) -> Option<f32> {
+        self.inner.temperature()
+    }
+
+    fn api_base(&self) -> Option<&str> {
+        self.inner.api_base()
+    }
+
+    fn max_tokens(&self) -> Option<u32> {
+        self.inner.max_tokens()
+    }
+
+    async fn complete_stream(&self, prompt: &str) -> ProviderResult<TextStream> {
+        if !self.cacheable() {
+            lock(&self.stats).bypassed += 1;
+            return self.inner.complete_stream(prompt).await;
+        }
+
+        let key = CacheKey::new(self.inner.as_ref(), self.policy.profile.as_deref(), prompt);
+        if let Some(text) = self.lookup(&key) {
+            return Ok(Box::pin(futures::stream::once(async move { Ok(text) })));
+        }
+
+        let stream = self.inner.complete_stream(prompt).await?;
+        Ok(cache_on_completion(stream, Arc::clone(&self.store), key))
+    }
+}
+
+/// Passes the stream through and stores the full text once it ends
+/// without error.
+fn cache_on_completion(stream: TextStream, store: Arc<CacheStore>, key: CacheKey) -> TextStream {
+    let state = (stream, store, key, String::new(), false);
+    Box::pin(futures::stream::unfold(
+        state,
+        |(mut stream, store, key, mut text, mut failed)| async move {
+            match stream.next().await {
+                Some(Ok(chunk)) => {
+                    text.push_str(&chunk);
+                    Some((Ok(chunk), (stream, store, key, text, failed)))
+                }
+                Some(Err(e)) => {
+                    failed = true;
+                    Some((Err(e), (stream, store, key, text, failed)))
+                }
+                None => {
+                    if !failed {
+                        store.put(key, text);
+                    }
+                    None
+                }
+            }
+        },
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::clock::VirtualClock;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    struct FakeProvider {
+        calls: Rc<Cell<u32>>,
+        temperature: Option<f32>,
+        fail: bool,
+    }
+
+    impl FakeProvider {
+        fn with_temperature(temperature: Option<f32>) -> (Box<dyn AiProvider>, Rc<Cell<u32>>) {
+            let calls = Rc::new(Cell::new(0));
+            let provider = FakeProvider {
+                calls: Rc::clone(&calls),
+                temperature,
+                fail: false,
+            };
+            (Box::new(provider), calls)
+        }
+
+        fn failing() -> (Box<dyn AiProvider>, Rc<Cell<u32>>) {
+            let calls = Rc::new(Cell::new(0));
+            let provider = FakeProvider {
+                calls: Rc::clone(&calls),
+                temperature: Some(0.0),
+                fail: true,
+            };
+            (Box::new(provider), calls)
+        }
+    }
+
+    #[async_trait::async_trait(?Send)]
+    impl AiProvider for FakeProvider {
+        async fn complete(&self, prompt: &str) -> ProviderResult<String> {
+            self.calls.set(self.calls.get() + 1);
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            if self.fail {
+                return Err(ProviderError::ApiError("Server error: 500".to_string()));
+            }
+            Ok(format!("answer {} to {}", self.calls.get(), prompt))
+        }
+
+        async fn is_available(&self) -> bool {
+            true
+        }
+
+        fn name(&self) -> &str {
+            "fake"
+        }
+
+        fn model(&self) -> &str {
+            "fake-1"
+        }
+
+        fn temperature(&self) -> Option<f32> {
+            self.temperature
+        }
+
+        async fn complete_stream(&self, prompt: &str) -> ProviderResult<TextStream> {
+            self.calls.set(self.calls.get() + 1);
+            let chunks = vec![Ok("streamed ".to_string()), Ok(prompt.to_string())];
+            Ok(Box::pin(futures::stream::iter(chunks)))
+        }
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("zed-copilot-cache-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[tokio::test]
+    async fn test_caches_deterministic_responses() {
+        let (inner, calls) = FakeProvider::with_temperature(Some(0.0));
+        let provider = CachingProvider::new(inner, CachePolicy::default());
+
+        let first = provider.complete("explain").await.unwrap();
+        let second = provider.complete("explain").await.unwrap();
+        provider.complete("other").await.unwrap();
+
+        assert_eq!(first, second);
+        assert_eq!(calls.get(), 2);
+        assert_eq!(
+            provider.stats(),
+            CacheStats {
+                hits: 1,
+                misses: 2,
+                coalesced: 0,
+                bypassed: 0,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_coalesces_concurrent_requests() {
+        let (inner, calls) = FakeProvider::with_temperature(Some(0.0));
+        let provider = CachingProvider::new(inner, CachePolicy::default());
+
+        let (a, b, c) = futures::join!(
+            provider.complete("explain"),
+            provider.complete("explain"),
+            provider.complete("explain"),
+        );
+
+        assert_eq!(calls.get(), 1);
+        assert_eq!(a.unwrap(), b.as_ref().unwrap().clone());
+        assert_eq!(b.unwrap(), c.unwrap());
+        assert_eq!(provider.stats().coalesced, 2);
+    }
+
+    #[tokio::test]
+    async fn test_nondeterministic_requests_bypass_cache_but_coalesce() {
+        let (inner, calls) = FakeProvider::with_temperature(Some(0.7));
+        let provider = CachingProvider::new(inner, CachePolicy::default());
+
+        let (a, b) = futures::join!(provider.complete("explain"), provider.complete("explain"));
+        assert_eq!(a.unwrap(), b.unwrap());
+        assert_eq!(calls.get(), 1);
+
+        provider.complete("explain").await.unwrap();
+        assert_eq!(calls.get(), 2);
+        assert_eq!(provider.stats().hits, 0);
+        assert_eq!(provider.stats().bypassed, 3);
+    }
+
+    #[tokio::test]
+    async fn test_vendor_default_temperature_is_not_cached() {
+        let (inner, calls) = FakeProvider::with_temperature(None);
+        let provider =
+            CachingProvider::new(inner, CachePolicy::default().with_max_temperature(2.0));
+
+        provider.complete("explain").await.unwrap();
+        provider.complete("explain").await.unwrap();
+        assert_eq!(calls.get(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_max_temperature_opt_in() {
+        let (inner, calls) = FakeProvider::with_temperature(Some(0.7));
+        let provider =
+            CachingProvider::new(inner, CachePolicy::default().with_max_temperature(0.7));
+
+        provider.complete("explain").await.unwrap();
+        provider.complete("explain").await.unwrap();
+        assert_eq!(calls.get(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_errors_are_shared_but_not_cached() {
+        let (inner, calls) = FakeProvider::failing();
+        let provider = CachingProvider::new(inner, CachePolicy::default());
+
+        let (a, b) = futures::join!(provider.complete("explain"), provider.complete("explain"));
+        assert!(a.is_err() && b.is_err());
+        assert_eq!(calls.get(), 1);
+
+        assert!(provider.complete("explain").await.is_err());
+        assert_eq!(calls.get(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_ttl_expiry() {
+        let clock = VirtualClock::new();
+        let (inner, calls) = FakeProvider::with_temperature(Some(0.0));
+        let policy =
+            CachePolicy::new(8, Duration::from_secs(60)).with_clock(Arc::new(clock.clone()));
+        let provider = CachingProvider::new(inner, policy);
+
+        provider.complete("explain").await.unwrap();
+        clock.advance(Duration::from_secs(59));
+        provider.complete("explain").await.unwrap();
+        assert_eq!(calls.get(), 1);
+
+        clock.advance(Duration::from_secs(1));
+        provider.complete("explain").await.unwrap();
+        assert_eq!(calls.get(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_lru_eviction() {
+        let (inner, calls) = FakeProvider::with_temperature(Some(0.0));
+        let provider = CachingProvider::new(inner, CachePolicy::new(2, Duration::from_secs(60)));
+
+        provider.complete("a").await.unwrap();
+        provider.complete("b").await.unwrap();
+        provider.complete("a").await.unwrap();
+        provider.complete("c").await.unwrap();
+        assert_eq!(calls.get(), 3);
+
+        provider.complete("a").await.unwrap();
+        assert_eq!(calls.get(), 3);
+        provider.complete("b").await.unwrap();
+        assert_eq!(calls.get(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_disk_store_survives_restart() {
+        let dir = temp_dir("disk");
+        let policy = || CachePolicy::default().with_disk_store(&dir);
+
+        let (inner, _) = FakeProvider::with_temperature(Some(0.0));
+        let first = CachingProvider::new(inner, policy())
+            .complete("explain")
+            .await
+            .unwrap();
+
+        let (inner, calls) = FakeProvider::with_temperature(Some(0.0));
+        let second = CachingProvider::new(inner, policy())
+            .complete("explain")
+            .await
+            .unwrap();
+
+        assert_eq!(first, second);
+        assert_eq!(calls.get(), 0);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_disk_store_keeps_prompts_off_disk() {
+        let dir = temp_dir("plaintext");
+        let (inner, _) = FakeProvider::with_temperature(Some(0.0));
+        let provider = CachingProvider::new(inner, CachePolicy::default().with_disk_store(&dir));
+        provider.complete("customer secret").await.unwrap();
+
+        let files: Vec<PathBuf> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        assert_eq!(files.len(), 1);
+        let content = fs::read_to_string(&files[0]).unwrap();
+        assert!(!content.contains("\"prompt\""));
+        assert!(!content.contains("fake-1"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_disk_store_is_pruned_to_capacity() {
+        let dir = temp_dir("prune");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("notes.json"), "{}").unwrap();
+        let (inner, calls) = FakeProvider::with_temperature(Some(0.0));
+        let provider = CachingProvider::new(
+            inner,
+            CachePolicy::new(2, Duration::from_secs(60)).with_disk_store(&dir),
+        );
+
+        for prompt in ["a", "b", "c"] {
+            provider.complete(prompt).await.unwrap();
+            std::thread::sleep(Duration::from_millis(2));
+        }
+        let entries = fs::read_dir(&dir)
+            .unwrap()
+            .filter(|entry| is_entry_file(&entry.as_ref().unwrap().path()))
+            .count();
+        assert_eq!(entries, 2);
+        assert!(dir.join("notes.json").exists());
+
+        let (inner, _) = FakeProvider::with_temperature(Some(0.0));
+        let restarted = CachingProvider::new(
+            inner,
+            CachePolicy::new(2, Duration::from_secs(60)).with_disk_store(&dir),
+        );
+        restarted.complete("c").await.unwrap();
+        assert_eq!(restarted.stats().hits, 1);
+        assert_eq!(calls.get(), 3);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_disk_store_expired_entry_is_removed() {
+        let dir = temp_dir("expired");
+        let (inner, calls) = FakeProvider::with_temperature(Some(0.0));
+        let provider = CachingProvider::new(
+            inner,
+            CachePolicy::new(8, Duration::ZERO).with_disk_store(&dir),
+        );
+
+        provider.complete("explain").await.unwrap();
+        provider.complete("explain").await.unwrap();
+        assert_eq!(calls.get(), 2);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_stream_is_cached_after_completion() {
+        let (inner, calls) = FakeProvider::with_temperature(Some(0.0));
+        let provider = CachingProvider::new(inner, CachePolicy::default());
+
+        let first: Vec<_> = provider
+            .complete_stream("hi")
+            .await
+            .unwrap()
+            .collect()
+            .await;
+        assert_eq!(first.len(), 2);
+
+        let second: Vec<String> = provider
+            .complete_stream("hi")
+            .await
+            .unwrap()
+            .map(|chunk| chunk.unwrap())
+            .collect()
+            .await;
+        assert_eq!(second, vec!["streamed hi"]);
+        assert_eq!(calls.get(), 1);
+
+        assert_eq!(provider.complete("hi").await.unwrap(), "streamed hi");
+        assert_eq!(calls.get(), 1);
+    }
+
+    #[test]
+    fn test_cache_key_includes_model_and_temperature() {
+        let (a, _) = FakeProvider::with_temperature(Some(0.0));
+        let (b, _) = FakeProvider::with_temperature(Some(0.2));
+        assert_ne!(
+            CacheKey::new(a.as_ref(), None, "x"),
+            CacheKey::new(b.as_ref(), None, "x")
+        );
+        assert_eq!(
+            CacheKey::new(a.as_ref(), None, "x"),
+            CacheKey::new(a.as_ref(), None, "x")
+        );
+    }
+
+    #[test]
+    fn test_cache_key_includes_endpoint_token_limit_and_profile() {
+        use crate::config::GenerationConfig;
+        use crate::providers::openai::OpenAiProvider;
+
+        let provider = || OpenAiProvider::new("sk-test".to_string(), "gpt-4".to_string()).unwrap();
+        let base = provider();
+        let gateway = provider()
+            .with_api_base("https://gateway.example/v1".to_string())
+            .unwrap();
+        let short = provider().with_generation(GenerationConfig {
+            max_tokens: Some(16),
+            ..Default::default()
+        });
+
+        let key = CacheKey::new(&base, Some("work"), "x");
+        assert_ne!(key, CacheKey::new(&gateway, Some("work"), "x"));
+        assert_ne!(key, CacheKey::new(&short, Some("work"), "x"));
+        assert_ne!(key, CacheKey::new(&base, Some("home"), "x"));
+        assert_ne!(key, CacheKey::new(&base, None, "x"));
+    }
+
+    #[test]
+    fn test_cache_key_file_name_is_stable() {
+        let (provider, _) = FakeProvider::with_temperature(Some(0.0));
+        let key = CacheKey::new(provider.as_ref(), Some("work"), "explain");
+        assert_eq!(
+            key.file_name(),
+            "42cc428df259f886535a5a6eb16332ca20e817a2a3e5803dfa9897523f463f3a.json"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_profiles_do_not_share_disk_entries() {
+        let dir = temp_dir("profiles");
+        let policy = CachePolicy::default().with_disk_store(&dir);
+
+        let (inner, first_calls) = FakeProvider::with_temperature(Some(0.0));
+        let work = CachingProvider::new(inner, policy.clone().with_profile("work"));
+        work.complete("explain").await.unwrap();
+
+        let (inner, calls) = FakeProvider::with_temperature(Some(0.0));
+        let home = CachingProvider::new(inner, policy.clone().with_profile("home"));
+        home.complete("explain").await.unwrap();
+        assert_eq!(calls.get(), 1);
+
+        let (inner, calls) = FakeProvider::with_temperature(Some(0.0));
+        let work_again = CachingProvider::new(inner, policy.with_profile("work"));
+        work_again.complete("explain").await.unwrap();
+        assert_eq!(calls.get(), 0);
+        assert_eq!(first_calls.get(), 1);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_policy_from_config() {
+        let config = CacheConfig {
+            enabled: true,
+            capacity: 16,
+            ttl_secs: 30,
+            max_temperature: 0.5,
+            disk_path: Some("response-cache".to_string()),
+        };
+        let policy = CachePolicy::from_config(&config, Path::new("/work"));
+        assert_eq!(policy.capacity, 16);
+        assert_eq!(policy.ttl, Duration::from_secs(30));
+        assert_eq!(
+            policy.disk_path,
+            Some(PathBuf::from("/work/response-cache"))
+        );
+        assert!(policy.is_cacheable(Some(0.5)));
+        assert!(!policy.is_cacheable(Some(0.6)));
+    }
+}
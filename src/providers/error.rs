@@ -7,6 +7,27 @@ pub enum ProviderError {
     NetworkError(String),
     ParseError(String),
     NotAvailable(String),
+    StreamInterrupted { partial: String, reason: String },
+}
+
+impl ProviderError {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ProviderError::ApiError(_) => "api_error",
+            ProviderError::ConfigError(_) => "config_error",
+            ProviderError::NetworkError(_) => "network_error",
+            ProviderError::ParseError(_) => "parse_error",
+            ProviderError::NotAvailable(_) => "not_available",
+            ProviderError::StreamInterrupted { .. } => "stream_interrupted",
+        }
+    }
+
+    pub fn partial_response(&self) -> Option<&str> {
+        match self {
+            ProviderError::StreamInterrupted { partial, .. } => Some(partial),
+            _ => None,
+        }
+    }
 }
 
 impl fmt::Display for ProviderError {
@@ -17,6 +38,12 @@ impl fmt::Display for ProviderError {
             ProviderError::NetworkError(msg) => write!(f, "Network error: {}", msg),
             ProviderError::ParseError(msg) => write!(f, "Parse error: {}", msg),
             ProviderError::NotAvailable(msg) => write!(f, "Provider not available: {}", msg),
+            ProviderError::StreamInterrupted { partial, reason } => write!(
+                f,
+                "Stream interrupted after {} characters: {}",
+                partial.chars().count(),
+                reason
+            ),
         }
     }
 }
@@ -24,3 +51,20 @@ impl fmt::Display for ProviderError {
 impl std::error::Error for ProviderError {}
 
 pub type ProviderResult<T> = Result<T, ProviderError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stream_interrupted_counts_characters() {
+        let error = ProviderError::StreamInterrupted {
+            partial: "héllo wörld 👋".to_string(),
+            reason: "connection reset".to_string(),
+        };
+        assert_eq!(
+            error.to_string(),
+            "Stream interrupted after 13 characters: connection reset"
+        );
+    }
+}
@@ -1,7 +1,67 @@
+use crate::config::{
+    GenerationConfig, NetworkConfig, Profile, ProviderConfig, ProviderKind, ProviderRegistry,
+    RootConfig,
+};
+use crate::http::metrics::Metrics;
 use crate::providers::anthropic::AnthropicProvider;
-use crate::providers::error::ProviderResult;
+use crate::providers::caching::CachePolicy;
+use crate::providers::error::{ProviderError, ProviderResult};
+use crate::providers::layer::{
+    CachingLayer, LoggingLayer, PromptRewriteLayer, ProviderStack, RedactionLayer,
+};
 use crate::providers::openai::OpenAiProvider;
 use crate::providers::trait_def::AiProvider;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::{OnceLock, RwLock};
+
+/// Builds a provider from its resolved config, the shared network settings
+/// and the profile's generation overrides.
+pub type ProviderConstructor =
+    fn(&ProviderConfig, &NetworkConfig, &GenerationConfig) -> ProviderResult<Box<dyn AiProvider>>;
+
+fn constructors() -> &'static RwLock<BTreeMap<String, ProviderConstructor>> {
+    static CONSTRUCTORS: OnceLock<RwLock<BTreeMap<String, ProviderConstructor>>> = OnceLock::new();
+    CONSTRUCTORS.get_or_init(|| {
+        let mut builtins = BTreeMap::new();
+        builtins.insert(
+            "openai".to_string(),
+            construct_openai as ProviderConstructor,
+        );
+        builtins.insert(
+            "anthropic".to_string(),
+            construct_anthropic as ProviderConstructor,
+        );
+        RwLock::new(builtins)
+    })
+}
+
+fn construct_openai(
+    config: &ProviderConfig,
+    network: &NetworkConfig,
+    generation: &GenerationConfig,
+) -> ProviderResult<Box<dyn AiProvider>> {
+    let provider = OpenAiProvider::new(config.api_key().to_string(), config.model().to_string())?
+        .with_metrics(Metrics::global().clone())
+        .with_generation(*generation)
+        .with_network_config(network.clone())?
+        .with_api_base(config.api_base().to_string())?;
+    Ok(Box::new(provider))
+}
+
+fn construct_anthropic(
+    config: &ProviderConfig,
+    network: &NetworkConfig,
+    generation: &GenerationConfig,
+) -> ProviderResult<Box<dyn AiProvider>> {
+    let provider =
+        AnthropicProvider::new(config.api_key().to_string(), config.model().to_string())?
+            .with_metrics(Metrics::global().clone())
+            .with_generation(*generation)?
+            .with_network_config(network.clone())?
+            .with_api_base(config.api_base().to_string())?;
+    Ok(Box::new(provider))
+}
 
 pub struct ProviderFactory;
 
@@ -33,11 +93,182 @@ impl ProviderFactory {
         let provider = AnthropicProvider::new(api_key, model)?.with_api_base(api_base)?;
         Ok(Box::new(provider))
     }
+
+    /// Registers a provider kind with the global config registry together
+    /// with its constructor, replacing any kind of the same name.
+    pub fn register(kind: ProviderKind, construct: ProviderConstructor) {
+        let name = kind.name().to_string();
+        ProviderRegistry::global().register(kind);
+        constructors()
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(name, construct);
+    }
+
+    pub fn from_config(config: &ProviderConfig) -> ProviderResult<Box<dyn AiProvider>> {
+        Self::from_config_with_network(config, &NetworkConfig::default())
+    }
+
+    pub fn from_config_with_network(
+        config: &ProviderConfig,
+        network: &NetworkConfig,
+    ) -> ProviderResult<Box<dyn AiProvider>> {
+        Self::construct(config, network, &GenerationConfig::default())
+    }
+
+    pub fn from_profile(
+        profile: &Profile,
+        network: &NetworkConfig,
+    ) -> ProviderResult<Box<dyn AiProvider>> {
+        Self::construct(&profile.provider, network, &profile.generation)
+    }
+
+    fn construct(
+        config: &ProviderConfig,
+        network: &NetworkConfig,
+        generation: &GenerationConfig,
+    ) -> ProviderResult<Box<dyn AiProvider>> {
+        let name = config.provider_name();
+        let construct = constructors()
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(name)
+            .copied()
+            .ok_or_else(|| {
+                ProviderError::ConfigError(format!("No constructor registered for '{}'", name))
+            })?;
+        construct(config, network, generation)
+    }
+
+    /// Builds the default profile from an interpolated, validated config and
+    /// wraps it in the layers enabled by the settings.
+    pub fn from_settings(
+        config: &RootConfig,
+        work_dir: &Path,
+    ) -> ProviderResult<Box<dyn AiProvider>> {
+        Self::profile_from_settings(config, config.default_profile_name(), work_dir)
+    }
+
+    /// Builds the named profile (see [`ProviderRegistry::profile`]) with the
+    /// configured layers.
+    pub fn profile_from_settings(
+        config: &RootConfig,
+        profile: &str,
+        work_dir: &Path,
+    ) -> ProviderResult<Box<dyn AiProvider>> {
+        let resolved = ProviderRegistry::global()
+            .profile(config, profile)
+            .map_err(|e| ProviderError::ConfigError(e.to_string()))?;
+        let network = config.network.clone().unwrap_or_default();
+        let provider = Self::from_profile(&resolved, &network)?;
+        Ok(Self::stack(config, profile, work_dir)?.build(provider))
+    }
+
+    /// Layers enabled by the settings for providers of `profile`, outermost
+    /// first: request logging, prompt prefix, redaction, then the response
+    /// cache, which therefore only ever holds redacted prompts.
+    pub fn stack(
+        config: &RootConfig,
+        profile: &str,
+        work_dir: &Path,
+    ) -> ProviderResult<ProviderStack> {
+        let mut stack = ProviderStack::new();
+        let middleware = config.middleware.clone().unwrap_or_default();
+
+        if middleware.log_requests {
+            stack = stack.layer(LoggingLayer);
+        }
+        if let Some(prefix) = middleware.prompt_prefix.filter(|p| !p.is_empty()) {
+            stack = stack.layer(PromptRewriteLayer::prefix(prefix));
+        }
+        if let Some(redaction) = config.redaction.as_ref().filter(|r| r.enabled) {
+            let layer = RedactionLayer::from_config(redaction, work_dir)
+                .map_err(|e| ProviderError::ConfigError(e.to_string()))?;
+            stack = stack.layer(layer);
+        }
+        if let Some(cache) = config.cache.as_ref().filter(|c| c.enabled) {
+            let policy = CachePolicy::from_config(cache, work_dir).with_profile(profile);
+            stack = stack.layer(CachingLayer::new(policy));
+        }
+
+        Ok(stack)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::{
+        AnthropicConfig, CacheConfig, ConfigLoader, CustomProviderConfig, MiddlewareConfig,
+        OpenAiConfig, RedactionConfig,
+    };
+    use futures::Stream;
+    use std::pin::Pin;
+
+    struct StaticProvider {
+        model: String,
+        reply: String,
+    }
+
+    #[async_trait::async_trait(?Send)]
+    impl AiProvider for StaticProvider {
+        async fn complete(&self, _prompt: &str) -> ProviderResult<String> {
+            Ok(self.reply.clone())
+        }
+
+        async fn is_available(&self) -> bool {
+            true
+        }
+
+        fn name(&self) -> &str {
+            "static-test"
+        }
+
+        fn model(&self) -> &str {
+            &self.model
+        }
+
+        async fn complete_stream(
+            &self,
+            _prompt: &str,
+        ) -> ProviderResult<Pin<Box<dyn Stream<Item = ProviderResult<String>> + Send>>> {
+            let reply = Ok(self.reply.clone());
+            Ok(Box::pin(futures::stream::iter(vec![reply])))
+        }
+    }
+
+    fn construct_static(
+        config: &ProviderConfig,
+        _network: &NetworkConfig,
+        _generation: &GenerationConfig,
+    ) -> ProviderResult<Box<dyn AiProvider>> {
+        let ProviderConfig::Custom(custom) = config else {
+            return Err(ProviderError::ConfigError(
+                "expected custom config".to_string(),
+            ));
+        };
+        let reply = custom
+            .options
+            .get("reply")
+            .and_then(|v| v.as_str())
+            .unwrap_or("hello")
+            .to_string();
+        Ok(Box::new(StaticProvider {
+            model: custom.model.clone(),
+            reply,
+        }))
+    }
+
+    fn openai_settings() -> RootConfig {
+        RootConfig {
+            enabled: true,
+            openai: Some(OpenAiConfig {
+                api_key: "sk-test-key".to_string(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
 
     #[test]
     fn test_factory_create_openai() {
@@ -103,4 +334,150 @@ mod tests {
         .unwrap();
         assert_eq!(provider.name(), "anthropic");
     }
+
+    #[test]
+    fn test_from_settings_builds_selected_provider() {
+        let provider = ProviderFactory::from_settings(&openai_settings(), Path::new(".")).unwrap();
+        assert_eq!(provider.name(), "openai");
+        assert_eq!(provider.model(), "gpt-4");
+
+        let config = RootConfig {
+            enabled: true,
+            provider: "anthropic".to_string(),
+            anthropic: Some(AnthropicConfig {
+                api_key: "sk-ant-test-key".to_string(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let provider = ProviderFactory::from_settings(&config, Path::new(".")).unwrap();
+        assert_eq!(provider.name(), "anthropic");
+    }
+
+    #[test]
+    fn test_from_settings_unknown_provider() {
+        let config = RootConfig {
+            provider: "mistral".to_string(),
+            ..openai_settings()
+        };
+        let result = ProviderFactory::from_settings(&config, Path::new("."));
+        assert!(matches!(result, Err(ProviderError::ConfigError(_))));
+    }
+
+    #[test]
+    fn test_stack_defaults_to_no_layers() {
+        assert!(
+            ProviderFactory::stack(&openai_settings(), "openai", Path::new("."))
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_stack_from_settings() {
+        let config = RootConfig {
+            cache: Some(CacheConfig {
+                enabled: true,
+                ..Default::default()
+            }),
+            middleware: Some(MiddlewareConfig {
+                log_requests: true,
+                prompt_prefix: Some("Answer in English.".to_string()),
+            }),
+            ..openai_settings()
+        };
+        assert_eq!(
+            ProviderFactory::stack(&config, "openai", Path::new("."))
+                .unwrap()
+                .len(),
+            3
+        );
+
+        let provider = ProviderFactory::from_settings(&config, Path::new(".")).unwrap();
+        assert_eq!(provider.name(), "openai");
+        assert_eq!(provider.temperature(), Some(0.7));
+    }
+
+    #[test]
+    fn test_stack_with_redaction() {
+        let mut config = RootConfig {
+            redaction: Some(RedactionConfig {
+                enabled: true,
+                ..Default::default()
+            }),
+            ..openai_settings()
+        };
+        assert_eq!(
+            ProviderFactory::stack(&config, "openai", Path::new("."))
+                .unwrap()
+                .len(),
+            1
+        );
+
+        if let Some(redaction) = config.redaction.as_mut() {
+            redaction
+                .patterns
+                .insert("ticket".to_string(), "TICKET-[0-9".to_string());
+        }
+        match ProviderFactory::from_settings(&config, Path::new(".")) {
+            Err(ProviderError::ConfigError(msg)) => {
+                assert!(msg.contains("redaction.patterns.ticket"))
+            }
+            Err(other) => panic!("Expected ConfigError, got {:?}", other),
+            Ok(_) => panic!("Expected ConfigError"),
+        }
+    }
+
+    #[test]
+    fn test_from_config_builtins() {
+        let openai = ProviderConfig::OpenAi(OpenAiConfig {
+            api_key: "sk-test-key".to_string(),
+            ..Default::default()
+        });
+        assert_eq!(
+            ProviderFactory::from_config(&openai).unwrap().name(),
+            "openai"
+        );
+
+        let anthropic = ProviderConfig::Anthropic(AnthropicConfig {
+            api_key: "sk-ant-test-key".to_string(),
+            ..Default::default()
+        });
+        let provider = ProviderFactory::from_config(&anthropic).unwrap();
+        assert_eq!(provider.name(), "anthropic");
+        assert_eq!(provider.model(), anthropic.model());
+    }
+
+    #[test]
+    fn test_from_config_without_constructor() {
+        let config = ProviderConfig::Custom(CustomProviderConfig {
+            name: "unregistered-test".to_string(),
+            api_key: "k".to_string(),
+            model: "m".to_string(),
+            api_base: String::new(),
+            timeout_secs: 30,
+            options: Default::default(),
+        });
+        let result = ProviderFactory::from_config(&config);
+        assert!(matches!(result, Err(ProviderError::ConfigError(_))));
+    }
+
+    #[tokio::test]
+    async fn test_registered_provider_builds_from_settings() {
+        ProviderFactory::register(ProviderKind::custom("static-test"), construct_static);
+
+        let config = ConfigLoader::load_from_json_string(
+            r#"{
+                "enabled": true,
+                "provider": "static-test",
+                "static-test": {"api_key": "k", "model": "static-1", "reply": "pong"}
+            }"#,
+        )
+        .unwrap();
+        crate::config::ConfigValidator::validate(&config).unwrap();
+
+        let provider = ProviderFactory::from_settings(&config, Path::new(".")).unwrap();
+        assert_eq!(provider.model(), "static-1");
+        assert_eq!(provider.complete("ping").await.unwrap(), "pong");
+    }
 }
@@ -0,0 +1,413 @@
+use crate::config::{ConfigResult, RedactionConfig, Redactor};
+use crate::providers::caching::{CachePolicy, CachingProvider};
+use crate::providers::error::ProviderResult;
+use crate::providers::redaction::{AuditTrail, RedactingProvider};
+use crate::providers::trait_def::AiProvider;
+use futures::Stream;
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Instant;
+
+type TextStream = Pin<Box<dyn Stream<Item = ProviderResult<String>> + Send>>;
+
+/// Wraps a provider in another provider that adds one cross-cutting
+/// behaviour, in the spirit of a tower `Layer`.
+pub trait ProviderLayer {
+    fn layer(&self, inner: Box<dyn AiProvider>) -> Box<dyn AiProvider>;
+}
+
+/// Ordered list of layers. The first layer added is the outermost one and
+/// sees each request first.
+#[derive(Default)]
+pub struct ProviderStack {
+    layers: Vec<Box<dyn ProviderLayer>>,
+}
+
+impl ProviderStack {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn layer(mut self, layer: impl ProviderLayer + 'static) -> Self {
+        self.layers.push(Box::new(layer));
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.layers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.layers.is_empty()
+    }
+
+    pub fn build(&self, provider: Box<dyn AiProvider>) -> Box<dyn AiProvider> {
+        self.layers
+            .iter()
+            .rev()
+            .fold(provider, |inner, layer| layer.layer(inner))
+    }
+}
+
+/// Logs each request's provider, model, prompt size, latency and outcome at
+/// debug level. Prompt text is never logged.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LoggingLayer;
+
+impl ProviderLayer for LoggingLayer {
+    fn layer(&self, inner: Box<dyn AiProvider>) -> Box<dyn AiProvider> {
+        Box::new(LoggingProvider { inner })
+    }
+}
+
+pub struct LoggingProvider {
+    inner: Box<dyn AiProvider>,
+}
+
+impl LoggingProvider {
+    fn log_outcome<T>(
+        &self,
+        operation: &str,
+        prompt: &str,
+        started: Instant,
+        result: &ProviderResult<T>,
+    ) {
+        let outcome = match result {
+            Ok(_) => "ok",
+            Err(e) => e.kind(),
+        };
+        log::debug!(
+            "{} {}/{}: {} prompt chars, {:?}, {}",
+            operation,
+            self.inner.name(),
+            self.inner.model(),
+            prompt.chars().count(),
+            started.elapsed(),
+            outcome
+        );
+    }
+}
+
+#[async_trait::async_trait(?Send)]
+impl AiProvider for LoggingProvider {
+    async fn complete(&self, prompt: &str) -> ProviderResult<String> {
+        let started = Instant::now();
+        let result = self.inner.complete(prompt).await;
+        self.log_outcome("complete", prompt, started, &result);
+        result
+    }
+
+    async fn is_available(&self) -> bool {
+        self.inner.is_available().await
+    }
+
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn model(&self) -> &str {
+        self.inner.model()
+    }
+
+    fn temperature(&self) -> Option<f32> {
+        self.inner.temperature()
+    }
+
+    fn api_base(&self) -> Option<&str> {
+        self.inner.api_base()
+    }
+
+    fn max_tokens(&self) -> Option<u32> {
+        self.inner.max_tokens()
+    }
+
+    async fn complete_stream(&self, prompt: &str) -> ProviderResult<TextStream> {
+        let started = Instant::now();
+        let result = self.inner.complete_stream(prompt).await;
+        self.log_outcome("complete_stream", prompt, started, &result);
+        result
+    }
+}
+
+type Rewrite = Arc<dyn Fn(&str) -> String>;
+
+/// Rewrites every prompt before it reaches the inner provider.
+#[derive(Clone)]
+pub struct PromptRewriteLayer {
+    rewrite: Rewrite,
+}
+
+impl PromptRewriteLayer {
+    pub fn new(rewrite: impl Fn(&str) -> String + 'static) -> Self {
+        PromptRewriteLayer {
+            rewrite: Arc::new(rewrite),
+        }
+    }
+
+    /// Prepends `prefix` and a blank line to each prompt.
+    pub fn prefix(prefix: impl Into<String>) -> Self {
+        let prefix = prefix.into();
+        Self::new(move |prompt| format!("{}\n\n{}", prefix, prompt))
+    }
+}
+
+impl ProviderLayer for PromptRewriteLayer {
+    fn layer(&self, inner: Box<dyn AiProvider>) -> Box<dyn AiProvider> {
+        Box::new(PromptRewriteProvider {
+            inner,
+            rewrite: Arc::clone(&self.rewrite),
+        })
+    }
+}
+
+pub struct PromptRewriteProvider {
+    inner: Box<dyn AiProvider>,
+    rewrite: Rewrite,
+}
+
+#[async_trait::async_trait(?Send)]
+impl AiProvider for PromptRewriteProvider {
+    async fn complete(&self, prompt: &str) -> ProviderResult<String> {
+        self.inner.complete(&(self.rewrite)(prompt)).await
+    }
+
+    async fn is_available(&self) -> bool {
+        self.inner.is_available().await
+    }
+
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn model(&self) -> &str {
+        self.inner.model()
+    }
+
+    fn temperature(&self) -> Option<f32> {
+        self.inner.temperature()
+    }
+
+    fn api_base(&self) -> Option<&str> {
+        self.inner.api_base()
+    }
+
+    fn max_tokens(&self) -> Option<u32> {
+        self.inner.max_tokens()
+    }
+
+    async fn complete_stream(&self, prompt: &str) -> ProviderResult<TextStream> {
+        self.inner.complete_stream(&(self.rewrite)(prompt)).await
+    }
+}
+
+/// Wraps the provider in a [`CachingProvider`]. Each provider built by the
+/// layer gets its own in-memory store.
+#[derive(Debug, Clone)]
+pub struct CachingLayer {
+    policy: CachePolicy,
+}
+
+impl CachingLayer {
+    pub fn new(policy: CachePolicy) -> Self {
+        CachingLayer { policy }
+    }
+}
+
+impl ProviderLayer for CachingLayer {
+    fn layer(&self, inner: Box<dyn AiProvider>) -> Box<dyn AiProvider> {
+        Box::new(CachingProvider::new(inner, self.policy.clone()))
+    }
+}
+
+/// Wraps the provider in a [`RedactingProvider`]. Providers built by the
+/// layer share placeholders and the audit trail.
+#[derive(Debug, Clone)]
+pub struct RedactionLayer {
+    redactor: Arc<Redactor>,
+    audit: AuditTrail,
+    restore_responses: bool,
+}
+
+impl RedactionLayer {
+    pub fn new(redactor: Redactor) -> Self {
+        RedactionLayer {
+            redactor: Arc::new(redactor),
+            audit: AuditTrail::new(),
+            restore_responses: false,
+        }
+    }
+
+    pub fn with_audit_trail(mut self, audit: AuditTrail) -> Self {
+        self.audit = audit;
+        self
+    }
+
+    pub fn with_restored_responses(mut self, restore: bool) -> Self {
+        self.restore_responses = restore;
+        self
+    }
+
+    /// The audit log path is resolved against `work_dir`, the extension's
+    /// working directory.
+    pub fn from_config(config: &RedactionConfig, work_dir: &Path) -> ConfigResult<Self> {
+        let audit = match &config.audit_log {
+            Some(path) => AuditTrail::new().with_file(work_dir.join(path)),
+            None => AuditTrail::new(),
+        };
+        Ok(Self::new(Redactor::new(config)?)
+            .with_audit_trail(audit)
+            .with_restored_responses(config.restore_responses))
+    }
+
+    pub fn audit_trail(&self) -> &AuditTrail {
+        &self.audit
+    }
+}
+
+impl ProviderLayer for RedactionLayer {
+    fn layer(&self, inner: Box<dyn AiProvider>) -> Box<dyn AiProvider> {
+        Box::new(
+            RedactingProvider::new(inner, Arc::clone(&self.redactor), self.audit.clone())
+                .with_restored_responses(self.restore_responses),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    struct EchoProvider {
+        prompts: Rc<RefCell<Vec<String>>>,
+    }
+
+    impl EchoProvider {
+        fn boxed() -> (Box<dyn AiProvider>, Rc<RefCell<Vec<String>>>) {
+            let prompts = Rc::new(RefCell::new(Vec::new()));
+            let provider = EchoProvider {
+                prompts: Rc::clone(&prompts),
+            };
+            (Box::new(provider), prompts)
+        }
+    }
+
+    #[async_trait::async_trait(?Send)]
+    impl AiProvider for EchoProvider {
+        async fn complete(&self, prompt: &str) -> ProviderResult<String> {
+            self.prompts.borrow_mut().push(prompt.to_string());
+            Ok(format!("echo: {}", prompt))
+        }
+
+        async fn is_available(&self) -> bool {
+            true
+        }
+
+        fn name(&self) -> &str {
+            "echo"
+        }
+
+        fn model(&self) -> &str {
+            "echo-1"
+        }
+
+        fn temperature(&self) -> Option<f32> {
+            Some(0.0)
+        }
+
+        async fn complete_stream(&self, prompt: &str) -> ProviderResult<TextStream> {
+            self.prompts.borrow_mut().push(prompt.to_string());
+            let chunk = Ok(format!("echo: {}", prompt));
+            Ok(Box::pin(futures::stream::iter(vec![chunk])))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_empty_stack_returns_provider_unchanged() {
+        let (inner, prompts) = EchoProvider::boxed();
+        let provider = ProviderStack::new().build(inner);
+
+        assert_eq!(provider.complete("hi").await.unwrap(), "echo: hi");
+        assert_eq!(*prompts.borrow(), vec!["hi"]);
+    }
+
+    #[tokio::test]
+    async fn test_first_layer_is_outermost() {
+        let (inner, prompts) = EchoProvider::boxed();
+        let provider = ProviderStack::new()
+            .layer(PromptRewriteLayer::new(|p| format!("outer({})", p)))
+            .layer(PromptRewriteLayer::new(|p| format!("inner({})", p)))
+            .build(inner);
+
+        provider.complete("hi").await.unwrap();
+        assert_eq!(*prompts.borrow(), vec!["inner(outer(hi))"]);
+    }
+
+    #[tokio::test]
+    async fn test_prompt_prefix_applies_to_streams() {
+        let (inner, prompts) = EchoProvider::boxed();
+        let provider = ProviderStack::new()
+            .layer(PromptRewriteLayer::prefix("Be brief."))
+            .build(inner);
+
+        let _ = provider.complete_stream("hi").await.unwrap();
+        assert_eq!(*prompts.borrow(), vec!["Be brief.\n\nhi"]);
+    }
+
+    #[tokio::test]
+    async fn test_layers_preserve_provider_identity() {
+        let (inner, _) = EchoProvider::boxed();
+        let provider = ProviderStack::new()
+            .layer(LoggingLayer)
+            .layer(PromptRewriteLayer::prefix("x"))
+            .layer(CachingLayer::new(CachePolicy::default()))
+            .build(inner);
+
+        assert_eq!(provider.name(), "echo");
+        assert_eq!(provider.model(), "echo-1");
+        assert_eq!(provider.temperature(), Some(0.0));
+        assert!(provider.is_available().await);
+    }
+
+    #[tokio::test]
+    async fn test_redaction_above_cache_keeps_secrets_out_of_it() {
+        let (inner, prompts) = EchoProvider::boxed();
+        let redaction = RedactionLayer::new(Redactor::new(&RedactionConfig::default()).unwrap());
+        let audit = redaction.audit_trail().clone();
+        let stack = ProviderStack::new()
+            .layer(PromptRewriteLayer::prefix("Reply to ops@example.com"))
+            .layer(redaction)
+            .layer(CachingLayer::new(CachePolicy::default()));
+
+        let first = stack.build(inner);
+        first.complete("hi").await.unwrap();
+        let (inner, _) = EchoProvider::boxed();
+        let second = stack.build(inner);
+        second.complete("cc ops@example.com").await.unwrap();
+
+        assert_eq!(prompts.borrow()[0], "Reply to [REDACTED_EMAIL_1]\n\nhi");
+        // The second prompt has the address in its prefix and its text.
+        let records = audit.records();
+        assert_eq!(records.len(), 3);
+        assert!(records
+            .iter()
+            .all(|record| record.placeholder == "[REDACTED_EMAIL_1]"));
+    }
+
+    #[tokio::test]
+    async fn test_caching_below_rewrite_keys_on_rewritten_prompt() {
+        let (inner, prompts) = EchoProvider::boxed();
+        let provider = ProviderStack::new()
+            .layer(LoggingLayer)
+            .layer(PromptRewriteLayer::prefix("ctx"))
+            .layer(CachingLayer::new(CachePolicy::default()))
+            .build(inner);
+
+        let first = provider.complete("hi").await.unwrap();
+        let second = provider.complete("hi").await.unwrap();
+        assert_eq!(first, second);
+        assert_eq!(prompts.borrow().len(), 1);
+    }
+}
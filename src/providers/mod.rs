@@ -1,9 +1,19 @@
 pub mod anthropic;
+pub mod caching;
 pub mod error;
 pub mod factory;
+pub mod layer;
 pub mod openai;
+pub mod profiles;
+pub mod redaction;
+pub mod routing;
 pub mod trait_def;
 
+pub use caching::{CachePolicy, CachingProvider};
 pub use error::{ProviderError, ProviderResult};
 pub use factory::ProviderFactory;
+pub use layer::{ProviderLayer, ProviderStack};
+pub use profiles::ProfileSwitcher;
+pub use redaction::{AuditRecord, AuditTrail, RedactingProvider};
+pub use routing::{ModelRouter, RouteRequest};
 pub use trait_def::AiProvider;
@@ -1,4 +1,8 @@
-use crate::http::openai::OpenAiHttpClient;
+use crate::config::{GenerationConfig, NetworkConfig};
+use crate::http::metrics::Metrics;
+use crate::http::openai::{self, OpenAiHttpClient};
+use crate::http::transport;
+use crate::http::wire_log::WireLog;
 use crate::providers::error::{ProviderError, ProviderResult};
 use crate::providers::trait_def::AiProvider;
 use futures::Stream;
@@ -8,6 +12,9 @@ pub struct OpenAiProvider {
     api_key: String,
     model: String,
     api_base: String,
+    network: Option<NetworkConfig>,
+    metrics: Option<Metrics>,
+    generation: GenerationConfig,
     http_client: OpenAiHttpClient,
 }
 
@@ -26,21 +33,87 @@ impl OpenAiProvider {
         }
 
         let api_base = "https://api.openai.com/v1".to_string();
-        let http_client = OpenAiHttpClient::new(api_base.clone())?;
+        let generation = GenerationConfig::default();
+        let http_client = Self::build_http_client(api_base.clone(), None, None, &generation)?;
 
         Ok(OpenAiProvider {
             api_key,
             model,
             api_base,
+            network: None,
+            metrics: None,
+            generation,
             http_client,
         })
     }
 
     pub fn with_api_base(mut self, api_base: String) -> ProviderResult<Self> {
         self.api_base = api_base.clone();
-        self.http_client = OpenAiHttpClient::new(api_base)?;
+        self.http_client = Self::build_http_client(
+            api_base,
+            self.network.as_ref(),
+            self.metrics.as_ref(),
+            &self.generation,
+        )?;
+        Ok(self)
+    }
+
+    pub fn with_network_config(mut self, network: NetworkConfig) -> ProviderResult<Self> {
+        self.http_client = Self::build_http_client(
+            self.api_base.clone(),
+            Some(&network),
+            self.metrics.as_ref(),
+            &self.generation,
+        )?;
+        self.network = Some(network);
         Ok(self)
     }
+
+    pub fn with_metrics(mut self, metrics: Metrics) -> Self {
+        self.http_client = self.http_client.with_metrics(metrics.clone());
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Overrides the default temperature and completion token limit.
+    pub fn with_generation(mut self, generation: GenerationConfig) -> Self {
+        self.http_client = self.http_client.with_generation(
+            Self::temperature_for(&generation),
+            Self::max_tokens_for(&generation),
+        );
+        self.generation = generation;
+        self
+    }
+
+    fn temperature_for(generation: &GenerationConfig) -> f32 {
+        generation.temperature.unwrap_or(openai::TEMPERATURE)
+    }
+
+    fn max_tokens_for(generation: &GenerationConfig) -> u32 {
+        generation.max_tokens.unwrap_or(openai::MAX_TOKENS)
+    }
+
+    fn build_http_client(
+        api_base: String,
+        network: Option<&NetworkConfig>,
+        metrics: Option<&Metrics>,
+        generation: &GenerationConfig,
+    ) -> ProviderResult<OpenAiHttpClient> {
+        let mut http_client = OpenAiHttpClient::new(api_base)?.with_generation(
+            Self::temperature_for(generation),
+            Self::max_tokens_for(generation),
+        );
+        if let Some(network) = network {
+            http_client = http_client.with_http_client(transport::build_http_client(network)?);
+            if let Some(wire_log) = WireLog::from_network(network)? {
+                http_client = http_client.with_wire_log(wire_log);
+            }
+        }
+        if let Some(metrics) = metrics {
+            http_client = http_client.with_metrics(metrics.clone());
+        }
+        Ok(http_client)
+    }
 }
 
 #[async_trait::async_trait(?Send)]
@@ -69,6 +142,18 @@ impl AiProvider for OpenAiProvider {
         &self.model
     }
 
+    fn temperature(&self) -> Option<f32> {
+        Some(Self::temperature_for(&self.generation))
+    }
+
+    fn api_base(&self) -> Option<&str> {
+        Some(&self.api_base)
+    }
+
+    fn max_tokens(&self) -> Option<u32> {
+        Some(Self::max_tokens_for(&self.generation))
+    }
+
     async fn complete_stream(
         &self,
         prompt: &str,
@@ -141,4 +226,124 @@ mod tests {
         let result = provider.complete("").await;
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_openai_provider_with_network_config() {
+        let network = NetworkConfig {
+            proxy: Some("http://proxy.corp:3128".to_string()),
+            ..Default::default()
+        };
+        let provider = OpenAiProvider::new("sk-test-key".to_string(), "gpt-4".to_string())
+            .unwrap()
+            .with_network_config(network)
+            .unwrap()
+            .with_api_base("https://gateway.corp/v1".to_string())
+            .unwrap();
+        assert_eq!(
+            provider.network.and_then(|n| n.proxy).as_deref(),
+            Some("http://proxy.corp:3128")
+        );
+    }
+
+    #[test]
+    fn test_openai_provider_with_invalid_network_config() {
+        let network = NetworkConfig {
+            proxy: Some("not a url".to_string()),
+            ..Default::default()
+        };
+        let result = OpenAiProvider::new("sk-test-key".to_string(), "gpt-4".to_string())
+            .unwrap()
+            .with_network_config(network);
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_openai_provider_metrics_survive_api_base_change() {
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(401))
+            .mount(&server)
+            .await;
+
+        let metrics = Metrics::new();
+        let provider = OpenAiProvider::new("sk-test-key".to_string(), "gpt-4".to_string())
+            .unwrap()
+            .with_metrics(metrics.clone())
+            .with_api_base(server.uri())
+            .unwrap();
+
+        assert!(provider.complete("Hello").await.is_err());
+
+        let snapshot = metrics.snapshot();
+        let recorded = snapshot.get("openai", "gpt-4").unwrap();
+        assert_eq!(recorded.requests, 1);
+        assert_eq!(recorded.errors.values().sum::<u64>(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_openai_provider_writes_wire_log() {
+        use crate::config::WireLogConfig;
+        use crate::http::wire_log::WireLogEntry;
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "id": "chatcmpl-1",
+                "object": "chat.completion",
+                "created": 1,
+                "model": "gpt-4",
+                "choices": [{
+                    "index": 0,
+                    "message": {"role": "assistant", "content": "Hi from sk-wire-key!"},
+                    "finish_reason": "stop"
+                }]
+            })))
+            .mount(&server)
+            .await;
+
+        let log_path = std::env::temp_dir()
+            .join(format!("zed-copilot-provider-wire-{}", std::process::id()))
+            .join("wire.jsonl");
+        let _ = std::fs::remove_file(&log_path);
+        let network = NetworkConfig {
+            wire_log: Some(WireLogConfig {
+                enabled: true,
+                path: log_path.to_string_lossy().into_owned(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let provider = OpenAiProvider::new("sk-wire-key".to_string(), "gpt-4".to_string())
+            .unwrap()
+            .with_network_config(network)
+            .unwrap()
+            .with_api_base(server.uri())
+            .unwrap();
+
+        assert_eq!(
+            provider.complete("Hello").await.unwrap(),
+            "Hi from sk-wire-key!"
+        );
+
+        let log = std::fs::read_to_string(&log_path).unwrap();
+        assert!(!log.contains("sk-wire-key"));
+        let entry: WireLogEntry = serde_json::from_str(log.lines().next().unwrap()).unwrap();
+        assert_eq!(entry.request.method, "POST");
+        assert_eq!(
+            entry.request.url,
+            format!("{}/chat/completions", server.uri())
+        );
+        assert!(entry.request.body.contains(r#""content":"Hello""#));
+        let response = entry.response.unwrap();
+        assert_eq!(response.status, 200);
+        assert!(response.body.contains("Hi from [REDACTED]!"));
+
+        std::fs::remove_dir_all(log_path.parent().unwrap()).unwrap();
+    }
 }
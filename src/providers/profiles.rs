@@ -0,0 +1,357 @@
+use crate::config::{ConfigChange, ConfigManager, SettingsWatcher, ZedSettingsPaths};
+use crate::providers::error::{ProviderError, ProviderResult};
+use crate::providers::factory::ProviderFactory;
+use crate::providers::trait_def::AiProvider;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+/// Built providers keyed by profile name and model override.
+type BuiltProviders = BTreeMap<(String, Option<String>), Rc<dyn AiProvider>>;
+
+type ChangeListener = Box<dyn Fn(&ConfigChange)>;
+
+/// Builds providers for named profiles on first use and tracks the active
+/// profile, so switching does not reload the configuration.
+pub struct ProfileSwitcher {
+    manager: ConfigManager,
+    work_dir: PathBuf,
+    built: RefCell<BuiltProviders>,
+    listeners: Vec<ChangeListener>,
+}
+
+impl ProfileSwitcher {
+    pub fn new(manager: ConfigManager, work_dir: impl Into<PathBuf>) -> Self {
+        ProfileSwitcher {
+            manager,
+            work_dir: work_dir.into(),
+            built: RefCell::new(BTreeMap::new()),
+            listeners: Vec::new(),
+        }
+    }
+
+    /// Calls `listener` after each reload that changed the configuration.
+    pub fn on_change(&mut self, listener: impl Fn(&ConfigChange) + 'static) {
+        self.listeners.push(Box::new(listener));
+    }
+
+    /// Reloads the configuration from all settings layers in `paths`. The
+    /// active profile's provider is built before anything is swapped, so an
+    /// invalid config or a provider that fails to build leaves the current
+    /// state in place. Providers already handed out keep working with the
+    /// old settings.
+    pub fn reload(&mut self, paths: &ZedSettingsPaths) -> ProviderResult<ConfigChange> {
+        let mut next = self.manager.clone();
+        let change = next
+            .reload_layered(paths, std::env::vars())
+            .map_err(config_error)?;
+        if change.is_empty() {
+            return Ok(change);
+        }
+
+        let active = next.active_profile_name().to_string();
+        let provider = build_provider(&next, &self.work_dir, &active, None)?;
+        let mut built = BTreeMap::new();
+        built.insert((active, None), provider);
+
+        self.manager = next;
+        self.built.replace(built);
+        for listener in &self.listeners {
+            listener(&change);
+        }
+        Ok(change)
+    }
+
+    /// Reloads if one of `watcher`'s files changed since the last poll.
+    pub fn poll(&mut self, watcher: &mut SettingsWatcher) -> ProviderResult<Option<ConfigChange>> {
+        if !watcher.poll().map_err(config_error)? {
+            return Ok(None);
+        }
+        let change = self.reload(watcher.paths())?;
+        Ok((!change.is_empty()).then_some(change))
+    }
+
+    pub fn manager(&self) -> &ConfigManager {
+        &self.manager
+    }
+
+    pub fn active_profile(&self) -> &str {
+        self.manager.active_profile_name()
+    }
+
+    /// Makes `name` the profile used when a request does not name one.
+    pub fn switch(&mut self, name: &str) -> ProviderResult<()> {
+        self.manager.set_active_profile(name).map_err(config_error)
+    }
+
+    /// Provider for `profile`, or for the active profile when `None`. A chat
+    /// session that pinned a profile passes its name here.
+    pub fn provider(&self, profile: Option<&str>) -> ProviderResult<Rc<dyn AiProvider>> {
+        self.provider_with_model(profile, None)
+    }
+
+    /// Like [`provider`](Self::provider), with the profile's model replaced
+    /// by `model` when given.
+    pub fn provider_with_model(
+        &self,
+        profile: Option<&str>,
+        model: Option<&str>,
+    ) -> ProviderResult<Rc<dyn AiProvider>> {
+        let name = profile.unwrap_or_else(|| self.active_profile());
+        let key = (name.to_string(), model.map(str::to_string));
+        if let Some(provider) = self.built.borrow().get(&key) {
+            return Ok(Rc::clone(provider));
+        }
+
+        let provider = build_provider(&self.manager, &self.work_dir, name, model)?;
+        self.built.borrow_mut().insert(key, Rc::clone(&provider));
+        Ok(provider)
+    }
+}
+
+fn config_error(e: crate::config::ConfigError) -> ProviderError {
+    ProviderError::ConfigError(e.to_string())
+}
+
+fn build_provider(
+    manager: &ConfigManager,
+    work_dir: &Path,
+    profile: &str,
+    model: Option<&str>,
+) -> ProviderResult<Rc<dyn AiProvider>> {
+    let config = manager.config();
+    let mut resolved = manager.get_profile(profile).map_err(config_error)?;
+    if let Some(model) = model {
+        resolved.provider = resolved.provider.with_model(model);
+    }
+
+    let network = config.network.clone().unwrap_or_default();
+    let provider = ProviderFactory::from_profile(&resolved, &network)?;
+    Ok(Rc::from(
+        ProviderFactory::stack(config, profile, work_dir)?.build(provider),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SETTINGS: &str = r#"
+    {"zed_copilot": {
+        "enabled": true,
+        "provider": "openai",
+        "openai": {"api_key": "sk-test-key", "model": "gpt-4"},
+        "anthropic": {"api_key": "sk-ant-test-key"},
+        "default_profile": "fast",
+        "profiles": {
+            "fast": {"provider": "openai", "model": "gpt-4o-mini", "temperature": 0.2},
+            "smart": {"provider": "anthropic", "model": "claude-opus"},
+            "local": {
+                "provider": "openai",
+                "model": "llama3",
+                "api_base": "http://localhost:11434/v1",
+                "max_tokens": 256
+            }
+        }
+    }}
+    "#;
+
+    /// User and project settings files under a fresh directory, with the
+    /// user file holding `SETTINGS`.
+    fn settings_paths(name: &str) -> ZedSettingsPaths {
+        let root = std::env::temp_dir().join(format!(
+            "zed-copilot-profiles-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(&root).unwrap();
+        let paths = ZedSettingsPaths {
+            user: Some(root.join("user.json")),
+            project: Some(root.join("project.json")),
+        };
+        std::fs::write(paths.user.as_ref().unwrap(), SETTINGS).unwrap();
+        paths
+    }
+
+    fn remove(paths: &ZedSettingsPaths) {
+        let root = paths.user.as_ref().unwrap().parent().unwrap();
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    fn switcher_for(paths: &ZedSettingsPaths) -> ProfileSwitcher {
+        let manager = ConfigManager::initialize_layered(paths, Vec::new()).unwrap();
+        ProfileSwitcher::new(manager, ".")
+    }
+
+    fn switcher() -> ProfileSwitcher {
+        let paths = settings_paths("switch");
+        let switcher = switcher_for(&paths);
+        remove(&paths);
+        switcher
+    }
+
+    #[test]
+    fn test_default_profile_is_active() {
+        let switcher = switcher();
+        assert_eq!(switcher.active_profile(), "fast");
+
+        let provider = switcher.provider(None).unwrap();
+        assert_eq!(provider.name(), "openai");
+        assert_eq!(provider.model(), "gpt-4o-mini");
+        assert_eq!(provider.temperature(), Some(0.2));
+    }
+
+    #[test]
+    fn test_switch_changes_active_provider() {
+        let mut switcher = switcher();
+        switcher.switch("smart").unwrap();
+
+        let provider = switcher.provider(None).unwrap();
+        assert_eq!(provider.name(), "anthropic");
+        assert_eq!(provider.model(), "claude-opus");
+    }
+
+    #[test]
+    fn test_switch_to_unknown_profile_keeps_active() {
+        let mut switcher = switcher();
+        assert!(matches!(
+            switcher.switch("missing"),
+            Err(ProviderError::ConfigError(_))
+        ));
+        assert_eq!(switcher.active_profile(), "fast");
+    }
+
+    #[test]
+    fn test_session_override_does_not_switch() {
+        let switcher = switcher();
+        let provider = switcher.provider(Some("local")).unwrap();
+        assert_eq!(provider.model(), "llama3");
+        assert_eq!(switcher.active_profile(), "fast");
+    }
+
+    #[test]
+    fn test_providers_are_built_once() {
+        let switcher = switcher();
+        let first = switcher.provider(Some("smart")).unwrap();
+        let second = switcher.provider(Some("smart")).unwrap();
+        assert!(Rc::ptr_eq(&first, &second));
+    }
+
+    #[test]
+    fn test_top_level_provider_is_an_implicit_profile() {
+        let switcher = switcher();
+        let provider = switcher.provider(Some("openai")).unwrap();
+        assert_eq!(provider.model(), "gpt-4");
+        assert_eq!(
+            provider.temperature(),
+            Some(crate::http::openai::TEMPERATURE)
+        );
+    }
+
+    #[test]
+    fn test_reload_swaps_providers_and_notifies() {
+        let paths = settings_paths("reload");
+        let mut switcher = switcher_for(&paths);
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let sink = Rc::clone(&events);
+        switcher.on_change(move |change| sink.borrow_mut().push(change.changed.clone()));
+
+        let before = switcher.provider(None).unwrap();
+        std::fs::write(
+            paths.user.as_ref().unwrap(),
+            SETTINGS.replace("gpt-4o-mini", "gpt-4.1-mini"),
+        )
+        .unwrap();
+        let change = switcher.reload(&paths).unwrap();
+        assert_eq!(change.changed, vec!["profiles.fast.model"]);
+
+        assert_eq!(before.model(), "gpt-4o-mini");
+        assert_eq!(switcher.provider(None).unwrap().model(), "gpt-4.1-mini");
+        assert_eq!(*events.borrow(), vec![vec!["profiles.fast.model"]]);
+
+        remove(&paths);
+    }
+
+    #[test]
+    fn test_reload_without_changes_keeps_providers() {
+        let paths = settings_paths("unchanged");
+        let mut switcher = switcher_for(&paths);
+        let before = switcher.provider(None).unwrap();
+        assert!(switcher.reload(&paths).unwrap().is_empty());
+        assert!(Rc::ptr_eq(&before, &switcher.provider(None).unwrap()));
+
+        remove(&paths);
+    }
+
+    #[test]
+    fn test_reload_keeps_project_profiles() {
+        let paths = settings_paths("project");
+        let project = paths.project.clone().unwrap();
+        std::fs::write(
+            &project,
+            r#"{"zed_copilot": {"profiles": {"repo": {"provider": "anthropic", "model": "claude-haiku"}}}}"#,
+        )
+        .unwrap();
+        let mut switcher = switcher_for(&paths);
+        switcher.switch("repo").unwrap();
+
+        std::fs::write(
+            paths.user.as_ref().unwrap(),
+            SETTINGS.replace("gpt-4o-mini", "gpt-4.1-mini"),
+        )
+        .unwrap();
+        let change = switcher.reload(&paths).unwrap();
+        assert_eq!(change.changed, vec!["profiles.fast.model"]);
+        assert_eq!(switcher.active_profile(), "repo");
+        assert_eq!(switcher.provider(None).unwrap().model(), "claude-haiku");
+        assert_eq!(
+            switcher.manager().source_of("/profiles/repo/model"),
+            Some(&crate::config::ConfigSource::Project(project))
+        );
+
+        remove(&paths);
+    }
+
+    #[test]
+    fn test_failed_reload_keeps_last_good_config() {
+        let paths = settings_paths("invalid");
+        let mut switcher = switcher_for(&paths);
+        switcher.switch("smart").unwrap();
+        let notified = Rc::new(RefCell::new(false));
+        let flag = Rc::clone(&notified);
+        switcher.on_change(move |_| *flag.borrow_mut() = true);
+
+        let invalid = SETTINGS.replace(
+            r#""default_profile": "fast""#,
+            r#""default_profile": "gone""#,
+        );
+        std::fs::write(paths.user.as_ref().unwrap(), invalid).unwrap();
+        assert!(matches!(
+            switcher.reload(&paths),
+            Err(ProviderError::ConfigError(_))
+        ));
+        assert_eq!(switcher.active_profile(), "smart");
+        assert_eq!(switcher.provider(None).unwrap().name(), "anthropic");
+        assert!(!*notified.borrow());
+
+        remove(&paths);
+    }
+
+    #[test]
+    fn test_poll_reloads_changed_file() {
+        let paths = settings_paths("poll");
+        let mut switcher = switcher_for(&paths);
+        let mut watcher = SettingsWatcher::new(paths.clone());
+        assert_eq!(switcher.poll(&mut watcher).unwrap(), None);
+
+        std::fs::write(paths.user.as_ref().unwrap(), SETTINGS.replace("0.2", "0.5")).unwrap();
+        let change = switcher.poll(&mut watcher).unwrap().unwrap();
+        assert!(change.touches("profiles.fast"));
+        assert_eq!(switcher.provider(None).unwrap().temperature(), Some(0.5));
+        assert_eq!(switcher.poll(&mut watcher).unwrap(), None);
+
+        remove(&paths);
+    }
+}
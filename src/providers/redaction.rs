@@ -0,0 +1,359 @@
+use crate::config::redaction::partial_placeholder_start;
+use crate::config::{Redaction, Redactor};
+use crate::providers::error::ProviderResult;
+use crate::providers::trait_def::AiProvider;
+use futures::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+type TextStream = Pin<Box<dyn Stream<Item = ProviderResult<String>> + Send>>;
+
+/// Records kept in memory; older ones are only in the audit file.
+const AUDIT_CAPACITY: usize = 1000;
+
+/// One value redacted from a prompt. Holds nothing of the value itself.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditRecord {
+    pub at_ms: u64,
+    pub provider: String,
+    pub model: String,
+    pub detector: String,
+    pub placeholder: String,
+    /// Byte offset of the value in the prompt.
+    pub offset: usize,
+    /// Length of the value in bytes.
+    pub length: usize,
+}
+
+/// Redactions made so far, oldest first, optionally appended to a JSONL
+/// file as well. Clones share the same trail.
+#[derive(Debug, Clone, Default)]
+pub struct AuditTrail {
+    records: Arc<Mutex<VecDeque<AuditRecord>>>,
+    path: Option<PathBuf>,
+}
+
+impl AuditTrail {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// The most recent records, up to the last 1000.
+    pub fn records(&self) -> Vec<AuditRecord> {
+        let records = self.records.lock().unwrap_or_else(|e| e.into_inner());
+        records.iter().cloned().collect()
+    }
+
+    pub fn record(&self, provider: &str, model: &str, redactions: &[Redaction]) {
+        let at_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+        let new: Vec<AuditRecord> = redactions
+            .iter()
+            .map(|redaction| AuditRecord {
+                at_ms,
+                provider: provider.to_string(),
+                model: model.to_string(),
+                detector: redaction.detector.clone(),
+                placeholder: redaction.placeholder.clone(),
+                offset: redaction.offset,
+                length: redaction.length,
+            })
+            .collect();
+
+        let mut records = self.records.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(path) = &self.path {
+            if let Err(e) = append(path, &new) {
+                log::warn!(
+                    "Failed to write redaction audit log {}: {}",
+                    path.display(),
+                    e
+                );
+            }
+        }
+        records.extend(new);
+        while records.len() > AUDIT_CAPACITY {
+            records.pop_front();
+        }
+    }
+}
+
+fn append(path: &Path, records: &[AuditRecord]) -> std::io::Result<()> {
+    let mut lines = String::new();
+    for record in records {
+        lines.push_str(&serde_json::to_string(record)?);
+        lines.push('\n');
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?
+        .write_all(lines.as_bytes())
+}
+
+/// Redacts every prompt before the inner provider sees it, and records
+/// each redaction in the audit trail. With `restore_responses`, the
+/// original values are put back where a response repeats a placeholder.
+pub struct RedactingProvider {
+    inner: Box<dyn AiProvider>,
+    redactor: Arc<Redactor>,
+    audit: AuditTrail,
+    restore_responses: bool,
+}
+
+impl RedactingProvider {
+    pub fn new(inner: Box<dyn AiProvider>, redactor: Arc<Redactor>, audit: AuditTrail) -> Self {
+        RedactingProvider {
+            inner,
+            redactor,
+            audit,
+            restore_responses: false,
+        }
+    }
+
+    pub fn with_restored_responses(mut self, restore: bool) -> Self {
+        self.restore_responses = restore;
+        self
+    }
+
+    fn redact(&self, prompt: &str) -> String {
+        let redacted = self.redactor.redact(prompt);
+        if !redacted.redactions.is_empty() {
+            log::info!(
+                "Redacted {} value(s) from the prompt to {}/{}",
+                redacted.redactions.len(),
+                self.inner.name(),
+                self.inner.model()
+            );
+            self.audit
+                .record(self.inner.name(), self.inner.model(), &redacted.redactions);
+        }
+        redacted.text
+    }
+}
+
+#[async_trait::async_trait(?Send)]
+impl AiProvider for RedactingProvider {
+    async fn complete(&self, prompt: &str) -> ProviderResult<String> {
+        let response = self.inner.complete(&self.redact(prompt)).await?;
+        if self.restore_responses {
+            Ok(self.redactor.restore(&response))
+        } else {
+            Ok(response)
+        }
+    }
+
+    async fn is_available(&self) -> bool {
+        self.inner.is_available().await
+    }
+
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn model(&self) -> &str {
+        self.inner.model()
+    }
+
+    fn temperature(&self) -> Option<f32> {
+        self.inner.temperature()
+    }
+
+    fn api_base(&self) -> Option<&str> {
+        self.inner.api_base()
+    }
+
+    fn max_tokens(&self) -> Option<u32> {
+        self.inner.max_tokens()
+    }
+
+    async fn complete_stream(&self, prompt: &str) -> ProviderResult<TextStream> {
+        let stream = self.inner.complete_stream(&self.redact(prompt)).await?;
+        if self.restore_responses {
+            Ok(restore_stream(stream, Arc::clone(&self.redactor)))
+        } else {
+            Ok(stream)
+        }
+    }
+}
+
+/// Restores placeholders in streamed chunks. A placeholder split across
+/// chunks is held back until its end arrives.
+fn restore_stream(stream: TextStream, redactor: Arc<Redactor>) -> TextStream {
+    let state = Some((stream, String::new()));
+    Box::pin(futures::stream::unfold(state, move |state| {
+        let redactor = Arc::clone(&redactor);
+        async move {
+            let (mut stream, mut pending) = state?;
+            loop {
+                match stream.next().await {
+                    Some(Ok(chunk)) => {
+                        pending.push_str(&chunk);
+                        let ready = partial_placeholder_start(&pending).unwrap_or(pending.len());
+                        if ready > 0 {
+                            let rest = pending.split_off(ready);
+                            return Some((Ok(redactor.restore(&pending)), Some((stream, rest))));
+                        }
+                    }
+                    Some(Err(e)) => return Some((Err(e), Some((stream, pending)))),
+                    None if pending.is_empty() => return None,
+                    None => return Some((Ok(redactor.restore(&pending)), None)),
+                }
+            }
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::RedactionConfig;
+
+    /// Replies with the prompt, streamed in chunks of `chunk_size` bytes.
+    struct EchoProvider {
+        chunk_size: usize,
+    }
+
+    #[async_trait::async_trait(?Send)]
+    impl AiProvider for EchoProvider {
+        async fn complete(&self, prompt: &str) -> ProviderResult<String> {
+            Ok(format!("echo: {}", prompt))
+        }
+
+        async fn is_available(&self) -> bool {
+            true
+        }
+
+        fn name(&self) -> &str {
+            "echo"
+        }
+
+        fn model(&self) -> &str {
+            "echo-1"
+        }
+
+        async fn complete_stream(&self, prompt: &str) -> ProviderResult<TextStream> {
+            let reply = format!("echo: {}", prompt);
+            let chunks: Vec<ProviderResult<String>> = reply
+                .as_bytes()
+                .chunks(self.chunk_size)
+                .map(|chunk| Ok(String::from_utf8_lossy(chunk).into_owned()))
+                .collect();
+            Ok(Box::pin(futures::stream::iter(chunks)))
+        }
+    }
+
+    fn provider(chunk_size: usize, restore: bool, audit: AuditTrail) -> RedactingProvider {
+        let redactor = Redactor::new(&RedactionConfig::default()).unwrap();
+        RedactingProvider::new(
+            Box::new(EchoProvider { chunk_size }),
+            Arc::new(redactor),
+            audit,
+        )
+        .with_restored_responses(restore)
+    }
+
+    #[tokio::test]
+    async fn test_prompt_is_redacted_and_audited() {
+        let audit = AuditTrail::new();
+        let provider = provider(8, false, audit.clone());
+
+        let reply = provider
+            .complete("mail ops@example.com about ops@example.com")
+            .await
+            .unwrap();
+        assert_eq!(
+            reply,
+            "echo: mail [REDACTED_EMAIL_1] about [REDACTED_EMAIL_1]"
+        );
+
+        let records = audit.records();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].provider, "echo");
+        assert_eq!(records[0].detector, "email");
+        assert_eq!(records[1].offset, 27);
+        assert_eq!(records[1].length, 15);
+        assert!(!serde_json::to_string(&records).unwrap().contains("ops@"));
+
+        provider.complete("nothing to hide").await.unwrap();
+        assert_eq!(audit.records().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_restores_responses() {
+        let provider = provider(8, true, AuditTrail::new());
+        let prompt = "connect to 10.20.30.40 as ops@example.com";
+        assert_eq!(
+            provider.complete(prompt).await.unwrap(),
+            format!("echo: {}", prompt)
+        );
+
+        // Chunks of 3 bytes split every placeholder.
+        let provider = self::provider(3, true, AuditTrail::new());
+        let chunks: Vec<String> = provider
+            .complete_stream(prompt)
+            .await
+            .unwrap()
+            .map(|chunk| chunk.unwrap())
+            .collect()
+            .await;
+        assert_eq!(chunks.concat(), format!("echo: {}", prompt));
+        assert!(chunks.iter().all(|chunk| !chunk.contains("[RED")));
+
+        let provider = self::provider(3, false, AuditTrail::new());
+        let chunks: Vec<String> = provider
+            .complete_stream(prompt)
+            .await
+            .unwrap()
+            .map(|chunk| chunk.unwrap())
+            .collect()
+            .await;
+        assert_eq!(
+            chunks.concat(),
+            "echo: connect to [REDACTED_IP_ADDRESS_1] as [REDACTED_EMAIL_1]"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_audit_file() {
+        let dir = std::env::temp_dir().join(format!(
+            "zed-copilot-redaction-audit-{}",
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        let path = dir.join("logs/redactions.jsonl");
+        let provider = provider(8, false, AuditTrail::new().with_file(&path));
+
+        provider.complete("ops@example.com").await.unwrap();
+        provider.complete("dev@example.com").await.unwrap();
+
+        let content = fs::read_to_string(&path).unwrap();
+        let records: Vec<AuditRecord> = content
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[1].placeholder, "[REDACTED_EMAIL_2]");
+        assert!(!content.contains("example.com"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,252 @@
+use crate::config::{RouteTarget, TaskKind};
+use crate::providers::error::ProviderResult;
+use crate::providers::profiles::ProfileSwitcher;
+use crate::providers::trait_def::AiProvider;
+use std::path::Path;
+use std::rc::Rc;
+
+/// What the router needs to know about a request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RouteRequest<'a> {
+    pub task: TaskKind,
+    pub language: Option<&'a str>,
+    pub path: Option<&'a Path>,
+    pub prompt_chars: usize,
+    pub pinned_profile: Option<&'a str>,
+}
+
+impl<'a> RouteRequest<'a> {
+    pub fn new(task: TaskKind) -> Self {
+        RouteRequest {
+            task,
+            language: None,
+            path: None,
+            prompt_chars: 0,
+            pinned_profile: None,
+        }
+    }
+
+    pub fn with_language(mut self, language: &'a str) -> Self {
+        self.language = Some(language);
+        self
+    }
+
+    /// The file the request is about, for path `overrides`.
+    pub fn with_path(mut self, path: &'a Path) -> Self {
+        self.path = Some(path);
+        self
+    }
+
+    pub fn with_prompt(mut self, prompt: &str) -> Self {
+        self.prompt_chars = prompt.chars().count();
+        self
+    }
+
+    /// A profile chosen explicitly, e.g. by a chat session. It bypasses the
+    /// routing policy.
+    pub fn pinned(mut self, profile: &'a str) -> Self {
+        self.pinned_profile = Some(profile);
+        self
+    }
+}
+
+/// Picks the provider for each request from the file's `overrides`, then
+/// the `routing` settings, falling back to the active profile.
+pub struct ModelRouter {
+    switcher: ProfileSwitcher,
+}
+
+impl ModelRouter {
+    pub fn new(switcher: ProfileSwitcher) -> Self {
+        ModelRouter { switcher }
+    }
+
+    pub fn switcher(&self) -> &ProfileSwitcher {
+        &self.switcher
+    }
+
+    pub fn switcher_mut(&mut self) -> &mut ProfileSwitcher {
+        &mut self.switcher
+    }
+
+    pub fn route(&self, request: &RouteRequest) -> RouteTarget {
+        if let Some(profile) = request.pinned_profile {
+            return RouteTarget {
+                profile: Some(profile.to_string()),
+                model: None,
+            };
+        }
+
+        let manager = self.switcher.manager();
+        let file = manager.effective_for(request.path, request.language);
+        if file.target.profile.is_some() || file.target.model.is_some() {
+            return file.target;
+        }
+
+        manager
+            .config()
+            .routing
+            .as_ref()
+            .and_then(|routing| {
+                routing.select(request.task, request.language, request.prompt_chars)
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn provider_for(&self, request: &RouteRequest) -> ProviderResult<Rc<dyn AiProvider>> {
+        let target = self.route(request);
+        log::debug!(
+            "Routing {:?} to profile {:?}, model {:?}",
+            request.task,
+            target.profile,
+            target.model
+        );
+        self.switcher
+            .provider_with_model(target.profile.as_deref(), target.model.as_deref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ConfigManager;
+
+    const SETTINGS: &str = r#"
+    {
+        "enabled": true,
+        "provider": "openai",
+        "openai": {"api_key": "sk-test-key", "model": "gpt-4"},
+        "anthropic": {"api_key": "sk-ant-test-key"},
+        "profiles": {
+            "fast": {"provider": "openai", "model": "gpt-4o-mini"},
+            "smart": {"provider": "anthropic", "model": "claude-opus"}
+        },
+        "routing": {
+            "tasks": {
+                "inline_completion": "fast",
+                "commit_message": {"profile": "fast", "model": "gpt-4.1-nano"},
+                "chat": "smart",
+                "review": {"model": "o3"}
+            },
+            "rules": [
+                {"task": "chat", "min_prompt_chars": 20, "profile": "fast", "model": "gpt-4.1"},
+                {"languages": ["Rust"], "task": "explain", "profile": "smart"}
+            ]
+        }
+    }
+    "#;
+
+    fn router() -> ModelRouter {
+        let manager = ConfigManager::initialize_from_json(SETTINGS).unwrap();
+        ModelRouter::new(ProfileSwitcher::new(manager, "."))
+    }
+
+    #[test]
+    fn test_task_defaults() {
+        let router = router();
+        let provider = router
+            .provider_for(&RouteRequest::new(TaskKind::InlineCompletion))
+            .unwrap();
+        assert_eq!(provider.model(), "gpt-4o-mini");
+
+        let provider = router
+            .provider_for(&RouteRequest::new(TaskKind::Chat).with_prompt("hi"))
+            .unwrap();
+        assert_eq!(provider.name(), "anthropic");
+    }
+
+    #[test]
+    fn test_model_override_within_profile() {
+        let router = router();
+        let provider = router
+            .provider_for(&RouteRequest::new(TaskKind::CommitMessage))
+            .unwrap();
+        assert_eq!(provider.name(), "openai");
+        assert_eq!(provider.model(), "gpt-4.1-nano");
+    }
+
+    #[test]
+    fn test_model_only_target_uses_active_profile() {
+        let mut router = router();
+        let provider = router
+            .provider_for(&RouteRequest::new(TaskKind::Review))
+            .unwrap();
+        assert_eq!(provider.name(), "openai");
+        assert_eq!(provider.model(), "o3");
+
+        router.switcher_mut().switch("smart").unwrap();
+        let provider = router
+            .provider_for(&RouteRequest::new(TaskKind::Review))
+            .unwrap();
+        assert_eq!(provider.name(), "anthropic");
+        assert_eq!(provider.model(), "o3");
+    }
+
+    #[test]
+    fn test_rules_take_precedence() {
+        let router = router();
+        let request = RouteRequest::new(TaskKind::Chat).with_prompt(&"x".repeat(20));
+        assert_eq!(
+            router.route(&request),
+            RouteTarget {
+                profile: Some("fast".to_string()),
+                model: Some("gpt-4.1".to_string()),
+            }
+        );
+
+        let request = RouteRequest::new(TaskKind::Explain).with_language("rust");
+        assert_eq!(router.route(&request).profile.as_deref(), Some("smart"));
+        let request = RouteRequest::new(TaskKind::Explain).with_language("python");
+        assert_eq!(router.route(&request), RouteTarget::default());
+    }
+
+    #[test]
+    fn test_unrouted_task_uses_active_profile() {
+        let router = router();
+        let provider = router
+            .provider_for(&RouteRequest::new(TaskKind::Summarization))
+            .unwrap();
+        assert_eq!(provider.model(), "gpt-4");
+    }
+
+    #[test]
+    fn test_pinned_profile_bypasses_routing() {
+        let router = router();
+        let request = RouteRequest::new(TaskKind::InlineCompletion).pinned("smart");
+        assert_eq!(router.provider_for(&request).unwrap().name(), "anthropic");
+    }
+
+    #[test]
+    fn test_file_overrides_take_precedence_over_routing() {
+        let settings: serde_json::Value = serde_json::from_str(SETTINGS).unwrap();
+        let mut settings = settings.as_object().unwrap().clone();
+        settings.insert(
+            "overrides".to_string(),
+            serde_json::json!({
+                "languages": {"SQL": {"model": "gpt-4o"}},
+                "paths": {"vendor/**": {"profile": "smart"}}
+            }),
+        );
+        let manager =
+            ConfigManager::initialize_from_json(&serde_json::Value::Object(settings).to_string())
+                .unwrap();
+        let router = ModelRouter::new(ProfileSwitcher::new(manager, "."));
+
+        let request = RouteRequest::new(TaskKind::InlineCompletion).with_language("sql");
+        let provider = router.provider_for(&request).unwrap();
+        assert_eq!(provider.name(), "openai");
+        assert_eq!(provider.model(), "gpt-4o");
+
+        let path = Path::new("/w/vendor/lib/a.rs");
+        let request = RouteRequest::new(TaskKind::InlineCompletion).with_path(path);
+        assert_eq!(router.provider_for(&request).unwrap().name(), "anthropic");
+
+        let request = RouteRequest::new(TaskKind::InlineCompletion)
+            .with_path(path)
+            .pinned("fast");
+        assert_eq!(
+            router.provider_for(&request).unwrap().model(),
+            "gpt-4o-mini"
+        );
+    }
+}
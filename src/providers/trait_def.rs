@@ -12,6 +12,23 @@ pub trait AiProvider {
 
     fn model(&self) -> &str;
 
+    /// Sampling temperature sent with each request, or `None` when the
+    /// vendor default applies.
+    fn temperature(&self) -> Option<f32> {
+        None
+    }
+
+    /// Endpoint requests are sent to, or `None` when it is not configurable.
+    fn api_base(&self) -> Option<&str> {
+        None
+    }
+
+    /// Completion token limit sent with each request, or `None` when the
+    /// vendor default applies.
+    fn max_tokens(&self) -> Option<u32> {
+        None
+    }
+
     async fn complete_stream(
         &self,
         prompt: &str,
@@ -1,3 +1,5 @@
+use crate::chat::PromptContext;
+
 pub struct Message {
     pub role: String,
     pub content: String,
@@ -161,6 +163,7 @@ pub struct ChatPanel {
     message_list: MessageListView,
     input_field: InputField,
     submit_button: SubmitButton,
+    profile: Option<String>,
 }
 
 impl ChatPanel {
@@ -171,6 +174,7 @@ impl ChatPanel {
             message_list: MessageListView::new(),
             input_field: InputField::new(),
             submit_button: SubmitButton::new(),
+            profile: None,
         }
     }
 
@@ -233,6 +237,23 @@ impl ChatPanel {
     pub fn get_submit_button_mut(&mut self) -> &mut SubmitButton {
         &mut self.submit_button
     }
+
+    /// Pins this session to a provider profile; `None` follows the active
+    /// profile.
+    pub fn set_profile(&mut self, profile: Option<String>) {
+        self.profile = profile;
+    }
+
+    pub fn profile(&self) -> Option<&str> {
+        self.profile.as_deref()
+    }
+
+    /// Tells the user which context the privacy policy kept back, if any.
+    pub fn report_withheld_context(&mut self, context: &PromptContext) {
+        if let Some(notice) = context.blocked_notice() {
+            self.add_message(Message::new("system", notice));
+        }
+    }
 }
 
 impl Default for ChatPanel {
@@ -251,6 +272,42 @@ mod tests {
         assert!(!panel.is_visible());
     }
 
+    #[test]
+    fn test_chat_panel_profile_override() {
+        let mut panel = ChatPanel::new();
+        assert_eq!(panel.profile(), None);
+
+        panel.set_profile(Some("smart".to_string()));
+        assert_eq!(panel.profile(), Some("smart"));
+
+        panel.set_profile(None);
+        assert_eq!(panel.profile(), None);
+    }
+
+    #[test]
+    fn test_chat_panel_reports_withheld_context() {
+        use crate::config::{BlockReason, BlockedItem};
+
+        let mut panel = ChatPanel::new();
+        panel.report_withheld_context(&PromptContext::default());
+        assert_eq!(panel.message_count(), 0);
+
+        let context = PromptContext {
+            items: Vec::new(),
+            blocked: vec![BlockedItem {
+                path: std::path::PathBuf::from("/w/.env"),
+                reason: BlockReason::Denied(".env".to_string()),
+            }],
+        };
+        panel.report_withheld_context(&context);
+        let message = &panel.get_messages()[0];
+        assert_eq!(message.role, "system");
+        assert_eq!(
+            message.content,
+            "Not sent to the provider:\n- /w/.env: matches deny pattern '.env'"
+        );
+    }
+
     #[test]
     fn test_chat_panel_open() {
         let mut panel = ChatPanel::new();
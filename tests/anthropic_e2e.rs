@@ -199,7 +199,7 @@ async fn test_anthropic_message_roles_valid() {
 async fn test_anthropic_max_tokens_bounds() {
     let max_tokens = 2048;
     assert!(
-        max_tokens >= 1 && max_tokens <= 4096,
+        (1..=4096).contains(&max_tokens),
         "max_tokens must be between 1 and 4096"
     );
 }
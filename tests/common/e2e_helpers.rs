@@ -5,6 +5,7 @@ pub struct E2ETestContext {
     lazy_mock_server: LazyMockServer,
 }
 
+#[allow(dead_code)]
 impl E2ETestContext {
     pub async fn new() -> Self {
         Self {
@@ -22,6 +22,7 @@ impl LazyMockServer {
         self.server.as_ref().unwrap()
     }
 
+    #[allow(dead_code)]
     pub fn server_mut(&mut self) -> &mut MockServer {
         if self.server.is_none() {
             panic!("MockServer accessed before mount_if_needed() was called");
@@ -1,9 +1,11 @@
 use serde_json::json;
 
 mod lazy_mock_server;
+#[allow(unused_imports)]
 pub use lazy_mock_server::LazyMockServer;
 
 mod e2e_helpers;
+#[allow(unused_imports)]
 pub use e2e_helpers::E2ETestContext;
 
 pub struct TestContext {
@@ -165,25 +165,27 @@ async fn test_openai_message_roles_valid() {
 
 #[tokio::test]
 async fn test_openai_temperature_bounds() {
+    let valid_range = 0.0..=2.0;
+
     assert!(
-        0.0 >= 0.0 && 0.0 <= 2.0,
+        valid_range.contains(&0.0),
         "Temperature 0.0 should be within bounds"
     );
     assert!(
-        1.0 >= 0.0 && 1.0 <= 2.0,
+        valid_range.contains(&1.0),
         "Temperature 1.0 should be within bounds"
     );
     assert!(
-        2.0 >= 0.0 && 2.0 <= 2.0,
+        valid_range.contains(&2.0),
         "Temperature 2.0 should be within bounds"
     );
 
     assert!(
-        !(-0.1 >= 0.0 && -0.1 <= 2.0),
+        !valid_range.contains(&-0.1),
         "Temperature -0.1 should be out of bounds"
     );
     assert!(
-        !(2.1 >= 0.0 && 2.1 <= 2.0),
+        !valid_range.contains(&2.1),
         "Temperature 2.1 should be out of bounds"
     );
 }